@@ -1,61 +1,242 @@
 //! FBX data.
 
-use std::{cell::RefCell, path::Path, rc::Rc};
-
-use fbxcel::pull_parser::{self as fbxbin, any::AnyParser};
-use gtk::{prelude::*, Window};
+use std::{
+    cell::RefCell,
+    io::{self, Read, Seek},
+    path::Path,
+    rc::Rc,
+};
 
-use crate::{
-    widgets::{FbxAttributeTable, FbxNodeTree, Logs},
-    WINDOW_TITLE_BASE,
+use fbxcel::{
+    low::FbxVersion,
+    pull_parser::{self as fbxbin, any::AnyParser},
 };
 
-pub use self::attribute::{Attribute, AttributeLoader};
+use self::lazy::LazySource;
+
+pub use self::{
+    animation::{
+        curve_node_channels, ticks_to_seconds, AnimationCurve, Interpolation, Key, TimeMode,
+        TICKS_PER_SECOND,
+    },
+    ascii::{load_ascii, save_ascii_file, write_ascii, ParseError},
+    attribute::{Attribute, AttributeLoader, ValueParseError},
+    diff::{
+        diff_documents, write_diff, AttributeDiff, DiffKind, DiffOptions, DocumentDiff, NodeDiff,
+        ObjectKey,
+    },
+    document::{Document, Node, NodeId},
+    dump::{dump_document, DumpOptions},
+    edit::{Edit, EditError, EditHistory},
+    embedded::{embedded_file_name, ImageFormat},
+    filter::NodeFilter,
+    json::{export_json, JsonOptions},
+    layer_element::{LayerElement, MappingMode, ReferenceMode, ResolvedElement},
+    lazy::{
+        decode_attribute, AttributeLocation, DeferredAttributeLoader, LoadedAttributes,
+        DEFAULT_CACHE_CAPACITY,
+    },
+    mesh::{Mesh, Polygon},
+    objects::{Connection, Object, ObjectIndex},
+    properties::{properties, template_properties, Property},
+    search::SearchQuery,
+    storage::{load_array_storage, ArrayStatistics, ArrayStorage},
+    writer::{save_binary_file, write_binary, ArrayCompression, WriteError, WriteOptions},
+};
 
+mod animation;
+mod ascii;
 mod attribute;
+mod diff;
+mod document;
+mod dump;
+mod edit;
+mod embedded;
+mod filter;
+mod json;
+mod layer_element;
+mod lazy;
+mod mesh;
+mod objects;
+mod properties;
+mod search;
+mod storage;
+mod writer;
 
-/// Loads the given FBX binary file.
-pub fn load_fbx_binary<P: AsRef<Path>>(
-    path: P,
-    window: &Window,
-    logs: &Logs,
-    node_tree: &FbxNodeTree,
-    node_attrs: &FbxAttributeTable,
-) {
-    use std::fs::File;
-    use std::io::BufReader;
-
-    let path = path.as_ref();
-    println!("FBX binary path = {}", path.display());
-    window.set_title(&format!("{} - {}", WINDOW_TITLE_BASE, path.display()));
-
-    logs.clear();
-    node_tree.clear();
-    node_attrs.clear();
-
-    let mut file = match File::open(path) {
-        Ok(file) => BufReader::new(file),
-        Err(err) => {
-            println!("Cannot open file {}: {}", path.display(), err);
-            logs.set_store(&vec![], Some(&err));
-            return;
+/// Number of nodes to be loaded between progress reports.
+const PROGRESS_INTERVAL_NODES: usize = 1024;
+
+/// Error type of loading.
+pub type LoadError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Warnings and their syntactic positions emitted during loading.
+pub type Warnings = Vec<(fbxbin::Warning, fbxbin::SyntacticPosition)>;
+
+/// Magic binary at the beginning of FBX binary files.
+const BINARY_MAGIC: &[u8] = b"Kaydara FBX Binary  \0";
+
+/// FBX file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// FBX binary.
+    Binary,
+    /// FBX ASCII.
+    Ascii,
+}
+
+impl Format {
+    /// Detects the format from the beginning of the file.
+    ///
+    /// Anything other than FBX binary is regarded as FBX ASCII.
+    pub fn detect(head: &[u8]) -> Self {
+        if head.starts_with(BINARY_MAGIC) {
+            Format::Binary
+        } else {
+            Format::Ascii
         }
-    };
-    let parser = match AnyParser::from_seekable_reader(&mut file) {
+    }
+}
+
+/// Detects the format of the given file.
+pub fn detect_file_format<P: AsRef<Path>>(path: P) -> io::Result<Format> {
+    open_file(path).map(|(format, _)| format)
+}
+
+/// Opens the given file and detects the format.
+///
+/// The returned reader is positioned at the beginning of the file.
+fn open_file<P: AsRef<Path>>(path: P) -> io::Result<(Format, io::BufReader<std::fs::File>)> {
+    let mut reader = io::BufReader::new(std::fs::File::open(path)?);
+    let mut head = Vec::with_capacity(BINARY_MAGIC.len());
+    (&mut reader)
+        .take(BINARY_MAGIC.len() as u64)
+        .read_to_end(&mut head)?;
+    reader.rewind()?;
+    Ok((Format::detect(&head), reader))
+}
+
+/// Result of loading an FBX file.
+#[derive(Debug)]
+pub struct LoadResult {
+    /// Loaded document.
+    ///
+    /// This is `None` if the FBX header cannot be read, and may be partial
+    /// if `error` is not `None`.
+    pub document: Option<Document>,
+    /// Warnings.
+    pub warnings: Warnings,
+    /// Error.
+    pub error: Option<LoadError>,
+}
+
+/// Loads the given FBX file, detecting the format automatically.
+///
+/// See [`load_binary`] for `progress`.
+pub fn load_file<P, F>(path: P, progress: F) -> LoadResult
+where
+    P: AsRef<Path>,
+    F: FnMut(u64) -> bool,
+{
+    let res = open_file(path);
+    match res {
+        Ok((Format::Binary, reader)) => load_binary(reader, progress),
+        Ok((Format::Ascii, reader)) => load_ascii(reader, progress),
+        Err(err) => LoadResult {
+            document: None,
+            warnings: Vec::new(),
+            error: Some(err.into()),
+        },
+    }
+}
+
+/// Loads the given FBX file, deferring arrays and binaries until they are
+/// needed.
+///
+/// Arrays and binaries of FBX binary are decoded on demand by
+/// [`Document::load_attributes`], and cached up to `cache_capacity` bytes.
+/// FBX ASCII is loaded eagerly.
+///
+/// The file is kept open while the document is alive.
+/// The document is not affected if the file is replaced (for example, by
+/// [`save_binary_file`]), but decoding fails if the file is modified in
+/// place.
+///
+/// See [`load_binary`] for `progress`.
+pub fn load_file_lazy<P, F>(path: P, cache_capacity: u64, progress: F) -> LoadResult
+where
+    P: AsRef<Path>,
+    F: FnMut(u64) -> bool,
+{
+    let res = open_file(path).and_then(|(format, reader)| match format {
+        Format::Binary => {
+            // The clone shares the file position, but decoding always seeks
+            // before reading.
+            let source = LazySource::new(reader.get_ref().try_clone()?, cache_capacity)?;
+            Ok((format, reader, Some(source)))
+        }
+        Format::Ascii => Ok((format, reader, None)),
+    });
+    match res {
+        Ok((Format::Binary, reader, source)) => load_binary_impl(reader, progress, source),
+        Ok((Format::Ascii, reader, _)) => load_ascii(reader, progress),
+        Err(err) => LoadResult {
+            document: None,
+            warnings: Vec::new(),
+            error: Some(err.into()),
+        },
+    }
+}
+
+/// Loads the given FBX binary file.
+///
+/// See [`load_binary`] for `progress`.
+pub fn load_binary_file<P, F>(path: P, progress: F) -> LoadResult
+where
+    P: AsRef<Path>,
+    F: FnMut(u64) -> bool,
+{
+    match std::fs::File::open(path) {
+        Ok(file) => load_binary(io::BufReader::new(file), progress),
+        Err(err) => LoadResult {
+            document: None,
+            warnings: Vec::new(),
+            error: Some(err.into()),
+        },
+    }
+}
+
+/// Loads an FBX binary from the given reader.
+///
+/// `progress` is called with the byte position the parser has read until,
+/// from time to time.
+/// If it returns `false`, loading is cancelled.
+pub fn load_binary<R, F>(reader: R, progress: F) -> LoadResult
+where
+    R: io::Read + io::Seek,
+    F: FnMut(u64) -> bool,
+{
+    load_binary_impl(reader, progress, None)
+}
+
+/// Loads an FBX binary from the given reader.
+///
+/// If `lazy` is given, arrays and binaries are deferred and will be decoded
+/// from the given source.
+fn load_binary_impl<R, F>(mut reader: R, mut progress: F, lazy: Option<LazySource>) -> LoadResult
+where
+    R: io::Read + io::Seek,
+    F: FnMut(u64) -> bool,
+{
+    let parser = match AnyParser::from_seekable_reader(&mut reader) {
         Ok(v) => v,
         Err(err) => {
-            println!("Cannot open file {} as FBX binary: {}", path.display(), err);
-            logs.set_store(&vec![], Some(&err));
-            return;
+            return LoadResult {
+                document: None,
+                warnings: Vec::new(),
+                error: Some(err.into()),
+            };
         }
     };
-    node_tree.append(None, "(FBX header)", None, 0);
-
-    println!(
-        "FBX version: {}.{}",
-        parser.fbx_version().major(),
-        parser.fbx_version().minor()
-    );
 
     match parser {
         AnyParser::V7400(mut parser) => {
@@ -69,14 +250,24 @@ pub fn load_fbx_binary<P: AsRef<Path>>(
                     Ok(())
                 });
             }
-            match load_fbx_binary_v7400(parser, node_tree, node_attrs) {
-                Ok(()) => {
-                    logs.set_store(warnings.borrow().iter(), None);
-                }
-                Err(err) => {
-                    println!("Failed to parse FBX file: {}", err);
-                    logs.set_store(warnings.borrow().iter(), Some(&err));
-                }
+            let mut document = Document::new(parser.fbx_version());
+            if let Some(source) = lazy {
+                document.set_lazy_source(source);
+            }
+            let res = load_fbx_binary_v7400(parser, &mut document, &mut progress);
+            let warnings = warnings.replace(Vec::new());
+            // Array storage is read only from valid files, since the offsets
+            // of a broken node may be wrong.
+            let error: Option<LoadError> = match res {
+                Ok(()) => load_array_storage(&mut reader, &mut document)
+                    .err()
+                    .map(Into::into),
+                Err(err) => Some(err.into()),
+            };
+            LoadResult {
+                document: Some(document),
+                warnings,
+                error,
             }
         }
         parser => {
@@ -85,21 +276,22 @@ pub fn load_fbx_binary<P: AsRef<Path>>(
                 parser.fbx_version().major(),
                 parser.fbx_version().minor()
             );
-            println!("Unsupported FBX version: {}", ver);
-            let err: Box<dyn std::error::Error> =
-                format!("Unsupported FBX version: {}", ver).into();
-            logs.set_store(&vec![], Some(err.as_ref()));
+            LoadResult {
+                document: None,
+                warnings: Vec::new(),
+                error: Some(format!("Unsupported FBX version: {}", ver).into()),
+            }
         }
     }
 }
 
-fn load_fbx_binary_v7400<R: std::io::Read>(
+fn load_fbx_binary_v7400<R: io::Read>(
     mut parser: fbxbin::v7400::Parser<R>,
-    node_tree: &FbxNodeTree,
-    node_attrs: &FbxAttributeTable,
+    document: &mut Document,
+    progress: &mut dyn FnMut(u64) -> bool,
 ) -> fbxbin::Result<()> {
-    let mut open_nodes_iter = Vec::new();
-    let mut attr_index = 0;
+    let mut open_nodes = Vec::new();
+    let mut num_loaded_nodes: usize = 0;
 
     'load_nodes: loop {
         use fbxbin::v7400::*;
@@ -108,24 +300,46 @@ fn load_fbx_binary_v7400<R: std::io::Read>(
             Event::StartNode(node) => {
                 let name = node.name().to_owned();
                 let mut attributes = node.attributes();
-                let tree_iter = node_tree.append(
-                    open_nodes_iter.last(),
-                    &name,
-                    attributes.total_count(),
-                    attr_index,
-                );
-                attr_index += attributes.total_count();
-                open_nodes_iter.push(tree_iter);
-                while let Some(attr) = attributes.load_next(AttributeLoader)? {
-                    node_attrs.push_attribute(attr);
+                let mut attrs = Vec::with_capacity(attributes.total_count() as usize);
+                if document.is_lazy() {
+                    while let Some(attr) = attributes.load_next(DeferredAttributeLoader)? {
+                        attrs.push(attr);
+                    }
+                } else {
+                    while let Some(attr) = attributes.load_next(AttributeLoader)? {
+                        attrs.push(attr);
+                    }
+                }
+                let pos = parser.position();
+                let start_offset = pos.component_byte_pos();
+                // Node record header: end offset, number of attributes, byte
+                // length of attributes, and the name.
+                let header_len = if document.fbx_version() >= FbxVersion::V7_5 {
+                    8 * 3 + 1
+                } else {
+                    4 * 3 + 1
+                };
+                let attributes_offset = start_offset + header_len + name.len() as u64;
+                let node_id =
+                    document.push_node(open_nodes.last().cloned(), name, attrs, start_offset);
+                document.set_attribute_offsets(node_id, attributes_offset, pos.byte_pos());
+                open_nodes.push(node_id);
+
+                num_loaded_nodes += 1;
+                if num_loaded_nodes % PROGRESS_INTERVAL_NODES == 0 && !progress(pos.byte_pos()) {
+                    return Err(
+                        io::Error::new(io::ErrorKind::Interrupted, "Loading cancelled").into(),
+                    );
                 }
             }
             Event::EndNode => {
-                open_nodes_iter.pop();
+                let node_id = open_nodes
+                    .pop()
+                    .expect("Should never fail: `EndNode` should close an open node");
+                document.set_end_offset(node_id, parser.position().byte_pos());
             }
             Event::EndFbx(footer_res) => {
-                node_tree.append(None, "(FBX footer)", None, 0);
-                let _ = footer_res?;
+                document.set_footer(*footer_res?);
                 break 'load_nodes;
             }
         }
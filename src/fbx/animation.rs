@@ -0,0 +1,583 @@
+//! Animation curves.
+
+use crate::fbx::{
+    mesh::{child_by_name, child_f64_array, child_i64_array},
+    properties, Attribute, Document, NodeId, ObjectIndex,
+};
+
+/// Number of FBX time ticks per second.
+pub const TICKS_PER_SECOND: i64 = 46_186_158_000;
+
+/// Interpolation flag for constant interpolation.
+const INTERPOLATION_CONSTANT: i32 = 0x0000_0002;
+/// Interpolation flag for linear interpolation.
+const INTERPOLATION_LINEAR: i32 = 0x0000_0004;
+/// Interpolation flag for cubic interpolation.
+const INTERPOLATION_CUBIC: i32 = 0x0000_0008;
+/// Constant mode flag to use the value of the next key.
+const CONSTANT_NEXT: i32 = 0x0000_0100;
+
+/// Names of other key flags.
+const KEY_FLAG_NAMES: &[(i32, &str)] = &[
+    (0x0000_0100, "auto tangent"),
+    (0x0000_0200, "TCB tangent"),
+    (0x0000_0400, "user tangent"),
+    (0x0000_0800, "broken tangent"),
+    (0x0000_1000, "clamp"),
+    (0x0000_2000, "time independent"),
+    (0x0000_4000, "clamp progressive"),
+    (0x0100_0000, "weighted right"),
+    (0x0200_0000, "weighted next left"),
+    (0x1000_0000, "velocity right"),
+    (0x2000_0000, "velocity next left"),
+];
+
+/// Time mode (`GlobalSettings` property `TimeMode`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeMode {
+    /// Raw enum value.
+    value: i64,
+    /// Frames per second.
+    frames_per_second: f64,
+}
+
+impl TimeMode {
+    /// Reads the time mode of the document.
+    ///
+    /// Falls back to 30 fps if the time mode is not found or unknown.
+    pub fn from_document(document: &Document) -> Self {
+        let global_properties = document
+            .toplevel_nodes()
+            .iter()
+            .cloned()
+            .find(|&id| document.node(id).name() == "GlobalSettings")
+            .and_then(|settings| child_by_name(document, settings, "Properties70"));
+        let property = |name: &str| {
+            global_properties
+                .map(|props| properties(document, props))
+                .and_then(|props| props.into_iter().find(|prop| prop.name() == name))
+                .and_then(|prop| prop.values().first().cloned())
+        };
+        let value = match property("TimeMode") {
+            Some(Attribute::SingleI32(v)) => i64::from(v),
+            Some(Attribute::SingleI64(v)) => v,
+            _ => 0,
+        };
+        let frames_per_second = match value {
+            1 => 120.0,
+            2 => 100.0,
+            3 => 60.0,
+            4 => 50.0,
+            5 => 48.0,
+            7 => 30.0,
+            8 | 9 => 30000.0 / 1001.0,
+            10 => 25.0,
+            11 => 24.0,
+            12 => 1000.0,
+            13 => 24000.0 / 1001.0,
+            14 => match property("CustomFrameRate") {
+                Some(Attribute::SingleF64(v)) if v > 0.0 => v,
+                Some(Attribute::SingleF32(v)) if v > 0.0 => f64::from(v),
+                _ => 30.0,
+            },
+            15 => 96.0,
+            16 => 72.0,
+            17 => 60000.0 / 1001.0,
+            18 => 120000.0 / 1001.0,
+            _ => 30.0,
+        };
+        Self {
+            value,
+            frames_per_second,
+        }
+    }
+
+    /// Returns the raw enum value.
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
+    /// Returns the frames per second.
+    pub fn frames_per_second(&self) -> f64 {
+        self.frames_per_second
+    }
+
+    /// Converts the time in ticks to frames.
+    pub fn ticks_to_frames(&self, ticks: i64) -> f64 {
+        ticks_to_seconds(ticks) * self.frames_per_second
+    }
+}
+
+/// Converts the time in ticks to seconds.
+pub fn ticks_to_seconds(ticks: i64) -> f64 {
+    ticks as f64 / TICKS_PER_SECOND as f64
+}
+
+/// Interpolation mode of a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Constant (step).
+    Constant,
+    /// Constant, using the value of the next key.
+    ConstantNext,
+    /// Linear.
+    Linear,
+    /// Cubic.
+    Cubic,
+    /// Unknown flags.
+    Unknown,
+}
+
+impl Interpolation {
+    /// Decodes the interpolation mode from the key flags.
+    pub fn from_flags(flags: i32) -> Self {
+        if flags & INTERPOLATION_CONSTANT != 0 {
+            if flags & CONSTANT_NEXT != 0 {
+                Interpolation::ConstantNext
+            } else {
+                Interpolation::Constant
+            }
+        } else if flags & INTERPOLATION_LINEAR != 0 {
+            Interpolation::Linear
+        } else if flags & INTERPOLATION_CUBIC != 0 {
+            Interpolation::Cubic
+        } else {
+            Interpolation::Unknown
+        }
+    }
+
+    /// Returns the name of the interpolation mode.
+    pub fn name(self) -> &'static str {
+        match self {
+            Interpolation::Constant => "constant",
+            Interpolation::ConstantNext => "constant (next)",
+            Interpolation::Linear => "linear",
+            Interpolation::Cubic => "cubic",
+            Interpolation::Unknown => "unknown",
+        }
+    }
+}
+
+/// Key of an animation curve.
+#[derive(Debug, Clone)]
+pub struct Key {
+    /// Time in ticks.
+    time: i64,
+    /// Value.
+    value: f64,
+    /// Flags.
+    flags: i32,
+    /// Right slope and next left slope, in value per second.
+    slopes: [f64; 2],
+}
+
+impl Key {
+    /// Returns the time in ticks.
+    pub fn time(&self) -> i64 {
+        self.time
+    }
+
+    /// Returns the value.
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Returns the raw flags.
+    pub fn flags(&self) -> i32 {
+        self.flags
+    }
+
+    /// Returns the interpolation mode to the next key.
+    pub fn interpolation(&self) -> Interpolation {
+        Interpolation::from_flags(self.flags)
+    }
+
+    /// Returns the right slope and the next left slope.
+    pub fn slopes(&self) -> [f64; 2] {
+        self.slopes
+    }
+
+    /// Returns the description of the flags other than the interpolation.
+    pub fn flags_description(&self) -> String {
+        let is_constant = self.flags & INTERPOLATION_CONSTANT != 0;
+        KEY_FLAG_NAMES
+            .iter()
+            // `0x100` means "next" for constant interpolation.
+            .filter(|&&(bit, _)| !(is_constant && bit == CONSTANT_NEXT))
+            .filter(|&&(bit, _)| self.flags & bit != 0)
+            .map(|&(_, name)| name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Animation curve decoded from an `AnimationCurve` node.
+#[derive(Debug, Clone)]
+pub struct AnimationCurve {
+    /// Node of the curve.
+    node: NodeId,
+    /// Default value.
+    default: Option<f64>,
+    /// Keys.
+    keys: Vec<Key>,
+    /// Problems found while decoding.
+    issues: Vec<String>,
+}
+
+impl AnimationCurve {
+    /// Decodes the given `AnimationCurve` node.
+    ///
+    /// Returns `None` if the node is not an `AnimationCurve` node.
+    pub fn from_node(document: &Document, node_id: NodeId) -> Option<Self> {
+        if document.node(node_id).name() != "AnimationCurve" {
+            return None;
+        }
+        let mut issues = Vec::new();
+
+        let default =
+            child_by_name(document, node_id, "Default").and_then(|child| {
+                match document.node(child).attributes().first() {
+                    Some(&Attribute::SingleF64(v)) => Some(v),
+                    Some(&Attribute::SingleF32(v)) => Some(f64::from(v)),
+                    _ => None,
+                }
+            });
+        let times = child_i64_array(document, node_id, "KeyTime").unwrap_or_default();
+        let values = child_f64_array(document, node_id, "KeyValueFloat").unwrap_or_default();
+        if times.len() != values.len() {
+            issues.push(format!(
+                "`KeyTime` has {} elements but `KeyValueFloat` has {}",
+                times.len(),
+                values.len()
+            ));
+        }
+        if times.windows(2).any(|w| w[0] > w[1]) {
+            issues.push("`KeyTime` is not sorted".to_owned());
+        }
+
+        // Key attributes are run-length encoded: `KeyAttrRefCount[i]` keys
+        // in a row share `KeyAttrFlags[i]` and `KeyAttrDataFloat[4*i..4*i+4]`.
+        let flags = child_i64_array(document, node_id, "KeyAttrFlags").unwrap_or_default();
+        let data = child_f64_array(document, node_id, "KeyAttrDataFloat").unwrap_or_default();
+        let ref_counts = child_i64_array(document, node_id, "KeyAttrRefCount").unwrap_or_default();
+        if flags.len() != ref_counts.len() {
+            issues.push(format!(
+                "`KeyAttrFlags` has {} elements but `KeyAttrRefCount` has {}",
+                flags.len(),
+                ref_counts.len()
+            ));
+        }
+        if data.len() != flags.len() * 4 {
+            issues.push(format!(
+                "`KeyAttrDataFloat` has {} elements but {} are expected",
+                data.len(),
+                flags.len() * 4
+            ));
+        }
+        let mut key_attrs = ref_counts
+            .iter()
+            .enumerate()
+            .flat_map(|(attr, &count)| std::iter::repeat(attr).take(count.max(0) as usize));
+        let num_keys = times.len().min(values.len());
+        let mut keys = Vec::with_capacity(num_keys);
+        for (&time, &value) in times.iter().zip(&values) {
+            let attr = key_attrs.next();
+            let key_flags = attr
+                .and_then(|attr| flags.get(attr))
+                .map_or(0, |&v| v as i32);
+            let slope = |i: usize| {
+                attr.and_then(|attr| data.get(attr * 4 + i))
+                    .cloned()
+                    .unwrap_or(0.0)
+            };
+            keys.push(Key {
+                time,
+                value,
+                flags: key_flags,
+                slopes: [slope(0), slope(1)],
+            });
+        }
+        // Broken counts may be huge, so the sum should not overflow.
+        let num_attr_keys = ref_counts
+            .iter()
+            .fold(0_usize, |sum, &v| sum.saturating_add(v.max(0) as usize));
+        if num_attr_keys != num_keys {
+            issues.push(format!(
+                "`KeyAttrRefCount` covers {} keys but the curve has {}",
+                num_attr_keys, num_keys
+            ));
+        }
+
+        Some(Self {
+            node: node_id,
+            default,
+            keys,
+            issues,
+        })
+    }
+
+    /// Returns the node of the curve.
+    pub fn node(&self) -> NodeId {
+        self.node
+    }
+
+    /// Returns the default value.
+    pub fn default_value(&self) -> Option<f64> {
+        self.default
+    }
+
+    /// Returns the keys.
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    /// Returns the problems found while decoding.
+    pub fn issues(&self) -> &[String] {
+        &self.issues
+    }
+
+    /// Evaluates the curve at the given time in ticks.
+    ///
+    /// Cubic segments are evaluated as Hermite curves using the key slopes,
+    /// and tangent weights are ignored.
+    /// Returns the default value if the curve has no keys.
+    pub fn evaluate(&self, time: i64) -> Option<f64> {
+        let (first, last) = match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return self.default,
+        };
+        if time <= first.time {
+            return Some(first.value);
+        }
+        if time >= last.time {
+            return Some(last.value);
+        }
+        let next_index = self.keys.iter().position(|key| key.time > time)?;
+        let (key, next) = (&self.keys[next_index - 1], &self.keys[next_index]);
+        let duration = ticks_to_seconds(next.time - key.time);
+        let t = (time - key.time) as f64 / (next.time - key.time) as f64;
+        let value = match key.interpolation() {
+            Interpolation::Constant | Interpolation::Unknown => key.value,
+            Interpolation::ConstantNext => next.value,
+            Interpolation::Linear => key.value + (next.value - key.value) * t,
+            Interpolation::Cubic => {
+                let (t2, t3) = (t * t, t * t * t);
+                (2.0 * t3 - 3.0 * t2 + 1.0) * key.value
+                    + (t3 - 2.0 * t2 + t) * key.slopes[0] * duration
+                    + (-2.0 * t3 + 3.0 * t2) * next.value
+                    + (t3 - t2) * key.slopes[1] * duration
+            }
+        };
+        Some(value)
+    }
+}
+
+/// Returns the curves connected to the given `AnimationCurveNode` node.
+///
+/// Each curve is returned with the channel name (such as `X` for the
+/// destination property `d|X`), in order of connections.
+pub fn curve_node_channels(
+    document: &Document,
+    index: &ObjectIndex,
+    curve_node: NodeId,
+) -> Vec<(String, NodeId)> {
+    if document.node(curve_node).name() != "AnimationCurveNode" {
+        return Vec::new();
+    }
+    let object = match index.objects().iter().find(|obj| obj.node() == curve_node) {
+        Some(v) => v,
+        None => return Vec::new(),
+    };
+    index
+        .children(object)
+        .filter_map(|conn| {
+            let child = index.object(conn.source())?;
+            if document.node(child.node()).name() != "AnimationCurve" {
+                return None;
+            }
+            let property = conn.destination_property().unwrap_or("");
+            let channel = property.strip_prefix("d|").unwrap_or(property);
+            Some((channel.to_owned(), child.node()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One second in ticks.
+    const SECOND: i64 = TICKS_PER_SECOND;
+
+    /// Creates a document with the given `GlobalSettings` properties.
+    fn settings(props: &[(&str, Attribute)]) -> Document {
+        let mut doc = Document::default();
+        let settings = doc.push_node(None, "GlobalSettings".to_owned(), vec![], 0);
+        let props70 = doc.push_node(Some(settings), "Properties70".to_owned(), vec![], 0);
+        for (name, value) in props {
+            let mut attrs: Vec<_> = [*name, "", "", ""]
+                .iter()
+                .map(|&s| Attribute::String(s.to_owned()))
+                .collect();
+            attrs.push(value.clone());
+            doc.push_node(Some(props70), "P".to_owned(), attrs, 0);
+        }
+        doc
+    }
+
+    #[test]
+    fn time_mode() {
+        let fps = |props: &[(&str, Attribute)]| {
+            TimeMode::from_document(&settings(props)).frames_per_second()
+        };
+        assert_eq!(fps(&[]), 30.0);
+        assert_eq!(fps(&[("TimeMode", Attribute::SingleI32(11))]), 24.0);
+        assert_eq!(fps(&[("TimeMode", Attribute::SingleI64(3))]), 60.0);
+        assert_eq!(fps(&[("TimeMode", Attribute::SingleI32(99))]), 30.0);
+        let mode = TimeMode::from_document(&settings(&[("TimeMode", Attribute::SingleI32(6))]));
+        assert_eq!(mode.value(), 6);
+        assert_eq!(mode.frames_per_second(), 30.0);
+        assert_eq!(mode.ticks_to_frames(2 * SECOND), 60.0);
+
+        // Custom frame rate, falling back to 30 fps if invalid.
+        let custom = |rate: Attribute| {
+            fps(&[
+                ("TimeMode", Attribute::SingleI32(14)),
+                ("CustomFrameRate", rate),
+            ])
+        };
+        assert_eq!(custom(Attribute::SingleF64(12.5)), 12.5);
+        assert_eq!(custom(Attribute::SingleF32(15.0)), 15.0);
+        assert_eq!(custom(Attribute::SingleF64(-1.0)), 30.0);
+        assert_eq!(custom(Attribute::SingleI32(12)), 30.0);
+        assert_eq!(fps(&[("TimeMode", Attribute::SingleI32(14))]), 30.0);
+    }
+
+    #[test]
+    fn interpolation_flags() {
+        let cases = [
+            (0x0000_0002, Interpolation::Constant),
+            (0x0000_0102, Interpolation::ConstantNext),
+            (0x0000_0004, Interpolation::Linear),
+            // `0x100` is auto tangent for non-constant interpolation.
+            (0x0000_0108, Interpolation::Cubic),
+            (0x0000_0000, Interpolation::Unknown),
+        ];
+        for &(flags, interpolation) in &cases {
+            assert_eq!(
+                Interpolation::from_flags(flags),
+                interpolation,
+                "{:#x}",
+                flags
+            );
+        }
+    }
+
+    /// Key attributes: flags, slopes and reference count.
+    type KeyAttr = (i32, [f32; 2], i32);
+
+    /// Creates a curve with the given keys and key attributes.
+    fn curve(keys: &[(i64, f32)], attrs: &[KeyAttr]) -> AnimationCurve {
+        let mut doc = Document::default();
+        let curve = doc.push_node(None, "AnimationCurve".to_owned(), vec![], 0);
+        let mut push = |name: &str, attr: Attribute| {
+            doc.push_node(Some(curve), name.to_owned(), vec![attr], 0);
+        };
+        push("Default", Attribute::SingleF64(-1.0));
+        push(
+            "KeyTime",
+            Attribute::ArrayI64(keys.iter().map(|&(time, _)| time).collect()),
+        );
+        push(
+            "KeyValueFloat",
+            Attribute::ArrayF32(keys.iter().map(|&(_, value)| value).collect()),
+        );
+        push(
+            "KeyAttrFlags",
+            Attribute::ArrayI32(attrs.iter().map(|&(flags, ..)| flags).collect()),
+        );
+        push(
+            "KeyAttrDataFloat",
+            Attribute::ArrayF32(
+                attrs
+                    .iter()
+                    .flat_map(|&(_, [right, next_left], _)| vec![right, next_left, 0.0, 0.0])
+                    .collect(),
+            ),
+        );
+        push(
+            "KeyAttrRefCount",
+            Attribute::ArrayI32(attrs.iter().map(|&(.., count)| count).collect()),
+        );
+        AnimationCurve::from_node(&doc, curve).unwrap()
+    }
+
+    #[test]
+    fn run_length_key_attributes() {
+        let curve = curve(
+            &[
+                (0, 0.0),
+                (SECOND, 1.0),
+                (2 * SECOND, 2.0),
+                (3 * SECOND, 3.0),
+            ],
+            &[(0x0000_0004, [0.0; 2], 3), (0x0000_0008, [1.0, 2.0], 1)],
+        );
+        assert!(curve.issues().is_empty(), "{:?}", curve.issues());
+        assert_eq!(curve.default_value(), Some(-1.0));
+        let interpolations: Vec<_> = curve.keys().iter().map(Key::interpolation).collect();
+        assert_eq!(
+            interpolations,
+            [
+                Interpolation::Linear,
+                Interpolation::Linear,
+                Interpolation::Linear,
+                Interpolation::Cubic
+            ]
+        );
+        assert_eq!(curve.keys()[3].slopes(), [1.0, 2.0]);
+    }
+
+    #[test]
+    fn broken_ref_counts() {
+        let curve = curve(
+            &[(0, 0.0), (SECOND, 1.0)],
+            &[
+                (0x0000_0004, [0.0; 2], i32::MAX),
+                (0x0000_0004, [0.0; 2], -5),
+            ],
+        );
+        assert_eq!(curve.keys().len(), 2);
+        assert_eq!(curve.issues().len(), 1, "{:?}", curve.issues());
+        assert!(curve.issues()[0].starts_with("`KeyAttrRefCount` covers"));
+    }
+
+    #[test]
+    fn evaluate() {
+        let keys = [(SECOND, 1.0), (2 * SECOND, 3.0)];
+        let evaluate = |flags: i32, slopes: [f32; 2], time: i64| {
+            curve(&keys, &[(flags, slopes, 2)]).evaluate(time).unwrap()
+        };
+        let middle = SECOND * 3 / 2;
+        assert_eq!(evaluate(0x0000_0002, [0.0; 2], middle), 1.0);
+        assert_eq!(evaluate(0x0000_0102, [0.0; 2], middle), 3.0);
+        assert_eq!(evaluate(0x0000_0004, [0.0; 2], middle), 2.0);
+        assert_eq!(evaluate(0x0000_0004, [0.0; 2], SECOND * 5 / 4), 1.5);
+        // Hermite curve with zero slopes is symmetric.
+        assert_eq!(evaluate(0x0000_0008, [0.0; 2], middle), 2.0);
+        // h00 = h01 = 1/2, h10 = 1/8, h11 = -1/8, and the duration is 1s.
+        assert_eq!(evaluate(0x0000_0008, [4.0, 8.0], middle), 2.0 + 0.5 - 1.0);
+        // Clamped before the first key and after the last key.
+        assert_eq!(evaluate(0x0000_0004, [0.0; 2], 0), 1.0);
+        assert_eq!(evaluate(0x0000_0004, [0.0; 2], SECOND), 1.0);
+        assert_eq!(evaluate(0x0000_0004, [0.0; 2], 2 * SECOND), 3.0);
+        assert_eq!(evaluate(0x0000_0004, [0.0; 2], 10 * SECOND), 3.0);
+    }
+
+    #[test]
+    fn evaluate_without_keys() {
+        let curve = curve(&[], &[]);
+        assert!(curve.issues().is_empty(), "{:?}", curve.issues());
+        assert_eq!(curve.evaluate(0), Some(-1.0));
+    }
+}
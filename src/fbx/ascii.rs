@@ -0,0 +1,139 @@
+//! FBX ASCII format.
+
+use std::{fmt, io};
+
+use fbxcel::low::FbxVersion;
+
+use crate::fbx::{Document, LoadResult};
+
+use self::parser::Parser;
+
+pub use self::writer::{save_ascii_file, write_ascii};
+
+mod lexer;
+mod parser;
+mod writer;
+
+/// Syntax error of FBX ASCII.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    /// Byte offset of the error.
+    offset: usize,
+    /// Line number (1-based), if known.
+    line: Option<usize>,
+    /// Column number in characters (1-based), if known.
+    column: Option<usize>,
+    /// Error message.
+    message: String,
+}
+
+impl ParseError {
+    /// Creates a new error.
+    pub(crate) fn new(offset: usize, message: impl Into<String>) -> Self {
+        Self {
+            offset,
+            line: None,
+            column: None,
+            message: message.into(),
+        }
+    }
+
+    /// Sets the line and column number, using the given source text.
+    fn locate(mut self, src: &str) -> Self {
+        let before = &src[..self.offset.min(src.len())];
+        let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+        self.line = Some(before.matches('\n').count() + 1);
+        self.column = Some(before[line_start..].chars().count() + 1);
+        self
+    }
+
+    /// Returns the byte offset of the error.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the line number (1-based) of the error, if known.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// Returns the column number (1-based) of the error, if known.
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+
+    /// Returns the error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "line {}, column {}: {}", line, column, self.message)
+            }
+            _ => write!(f, "byte offset {}: {}", self.offset, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Loads an FBX ASCII from the given reader.
+///
+/// `progress` is called with the byte position the parser has read until,
+/// from time to time.
+/// If it returns `false`, loading is cancelled.
+///
+/// Attributes are converted to the types FBX binary would use where known
+/// (for example, object IDs are `i64` and object names are
+/// `Name\x00\x01Class`), so that documents loaded from both formats can be
+/// handled in the same way.
+pub fn load_ascii<R, F>(mut reader: R, mut progress: F) -> LoadResult
+where
+    R: io::Read,
+    F: FnMut(u64) -> bool,
+{
+    let mut bytes = Vec::new();
+    if let Err(err) = reader.read_to_end(&mut bytes) {
+        return LoadResult {
+            document: None,
+            warnings: Vec::new(),
+            error: Some(err.into()),
+        };
+    }
+    let src = String::from_utf8_lossy(&bytes);
+
+    let mut document = Document::new(detect_version(&src));
+    let res = Parser::new(&src, &mut progress).parse(&mut document);
+    LoadResult {
+        document: Some(document),
+        warnings: Vec::new(),
+        error: res.err().map(|err| err.locate(&src).into()),
+    }
+}
+
+/// Detects the FBX version from the header comment
+/// (such as `; FBX 7.4.0 project file`).
+///
+/// Versions other than 7.4 and 7.5 are mapped to the nearest one, since
+/// `fbxcel` provides only them.
+fn detect_version(src: &str) -> FbxVersion {
+    let version = src
+        .lines()
+        .take_while(|line| line.trim_start().starts_with(';') || line.trim().is_empty())
+        .find_map(|line| {
+            let rest = line.trim_start_matches(|c: char| c == ';' || c.is_whitespace());
+            let rest = rest.strip_prefix("FBX ")?;
+            let mut parts = rest.split(|c: char| c == '.' || c.is_whitespace());
+            let major: u32 = parts.next()?.parse().ok()?;
+            let minor: u32 = parts.next()?.parse().ok()?;
+            Some(major * 1000 + minor * 100)
+        });
+    match version {
+        Some(v) if v >= 7500 => FbxVersion::V7_5,
+        _ => FbxVersion::V7_4,
+    }
+}
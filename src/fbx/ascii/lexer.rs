@@ -0,0 +1,152 @@
+//! Tokenizer for FBX ASCII format.
+
+use crate::fbx::ascii::ParseError;
+
+/// Token.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token<'a> {
+    /// Node name (an identifier followed by `:`).
+    Name(&'a str),
+    /// Bare word which is not a node name (such as `T` and `Y`).
+    Word(&'a str),
+    /// Quoted string (unescaped).
+    String(String),
+    /// Number.
+    Number(&'a str),
+    /// Array length (`*N`).
+    ArrayLen(usize),
+    /// `,`.
+    Comma,
+    /// `{`.
+    OpenBrace,
+    /// `}`.
+    CloseBrace,
+    /// End of the source.
+    Eof,
+}
+
+/// Tokenizer.
+#[derive(Debug, Clone)]
+pub(crate) struct Lexer<'a> {
+    /// Source text.
+    src: &'a str,
+    /// Current byte position.
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    /// Creates a new lexer.
+    pub(crate) fn new(src: &'a str) -> Self {
+        Self { src, pos: 0 }
+    }
+
+    /// Returns the current byte position.
+    pub(crate) fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the rest of the source.
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    /// Skips whitespaces and comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with(';') {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else {
+                return;
+            }
+        }
+    }
+
+    /// Reads the next token and returns it with its beginning position.
+    pub(crate) fn next_token(&mut self) -> Result<(usize, Token<'a>), ParseError> {
+        self.skip_trivia();
+        let start = self.pos;
+        let rest = self.rest();
+        let c = match rest.chars().next() {
+            Some(c) => c,
+            None => return Ok((start, Token::Eof)),
+        };
+        let token = match c {
+            ',' => {
+                self.pos += 1;
+                Token::Comma
+            }
+            '{' => {
+                self.pos += 1;
+                Token::OpenBrace
+            }
+            '}' => {
+                self.pos += 1;
+                Token::CloseBrace
+            }
+            '"' => self.read_string()?,
+            '*' => {
+                self.pos += 1;
+                let digits = self.take_while(|c| c.is_ascii_digit());
+                let len = digits
+                    .parse()
+                    .map_err(|_| ParseError::new(start, "Invalid array length"))?;
+                Token::ArrayLen(len)
+            }
+            c if c == '-' || c == '+' || c == '.' || c.is_ascii_digit() => {
+                Token::Number(self.take_while(is_number_char))
+            }
+            c if is_ident_char(c) => {
+                let ident = self.take_while(is_ident_char);
+                let after_ident = self.pos;
+                self.skip_trivia();
+                if self.rest().starts_with(':') {
+                    self.pos += 1;
+                    Token::Name(ident)
+                } else {
+                    self.pos = after_ident;
+                    Token::Word(ident)
+                }
+            }
+            c => {
+                return Err(ParseError::new(
+                    start,
+                    format!("Unexpected character {:?}", c),
+                ))
+            }
+        };
+        Ok((start, token))
+    }
+
+    /// Reads a quoted string.
+    fn read_string(&mut self) -> Result<Token<'a>, ParseError> {
+        let start = self.pos;
+        // Skip the opening quote.
+        let body = &self.rest()[1..];
+        let len = body
+            .find('"')
+            .ok_or_else(|| ParseError::new(start, "Unterminated string"))?;
+        self.pos += len + 2;
+        Ok(Token::String(body[..len].replace("&quot;", "\"")))
+    }
+
+    /// Consumes characters while the predicate holds, and returns them.
+    fn take_while<F: Fn(char) -> bool>(&mut self, pred: F) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !pred(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+}
+
+/// Returns whether the character can be a part of an identifier.
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '|'
+}
+
+/// Returns whether the character can be a part of a number.
+fn is_number_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '+' || c == '.' || c == '#'
+}
@@ -0,0 +1,542 @@
+//! Parser for FBX ASCII format.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+use crate::fbx::{
+    ascii::{
+        lexer::{Lexer, Token},
+        ParseError,
+    },
+    Attribute, Document, NodeId,
+};
+
+/// Number of nodes to be loaded between progress reports.
+const PROGRESS_INTERVAL_NODES: usize = 1024;
+
+/// Maximum number of array elements allocated before reading the elements.
+const MAX_PREALLOCATED_ELEMENTS: usize = 1 << 16;
+
+/// Names of array nodes whose elements are `f32` in FBX binary.
+const F32_ARRAY_NODES: &[&str] = &["KeyValueFloat", "KeyAttrDataFloat"];
+
+/// Names of array nodes whose elements are `f64` in FBX binary.
+///
+/// Elements of these arrays may be written without decimal points in FBX
+/// ASCII.
+const F64_ARRAY_NODES: &[&str] = &[
+    "Binormals",
+    "BinormalsW",
+    "Colors",
+    "FullWeights",
+    "KnotVector",
+    "KnotVectorU",
+    "KnotVectorV",
+    "Matrix",
+    "Normals",
+    "NormalsW",
+    "Points",
+    "Tangents",
+    "TangentsW",
+    "Transform",
+    "TransformAssociateModel",
+    "TransformLink",
+    "UV",
+    "Vertices",
+    "Weights",
+];
+
+/// Names of array nodes whose elements are `i64` in FBX binary.
+const I64_ARRAY_NODES: &[&str] = &["KeyTime"];
+
+/// Property types (in `Properties70`) whose values are integers.
+const INT_PROPERTY_TYPES: &[&str] = &["bool", "Bool", "enum", "Enum", "int", "Integer"];
+
+/// Property types (in `Properties70`) whose values are `i64`.
+const I64_PROPERTY_TYPES: &[&str] = &["KTime", "Reference", "ULongLong"];
+
+/// Parser.
+pub(crate) struct Parser<'a, 'p> {
+    /// Tokenizer.
+    lexer: Lexer<'a>,
+    /// Peeked token.
+    peeked: Option<(usize, Token<'a>)>,
+    /// Number of loaded nodes.
+    num_loaded_nodes: usize,
+    /// Progress callback.
+    progress: &'p mut dyn FnMut(u64) -> bool,
+}
+
+impl<'a, 'p> Parser<'a, 'p> {
+    /// Creates a new parser.
+    pub(crate) fn new(src: &'a str, progress: &'p mut dyn FnMut(u64) -> bool) -> Self {
+        Self {
+            lexer: Lexer::new(src),
+            peeked: None,
+            num_loaded_nodes: 0,
+            progress,
+        }
+    }
+
+    /// Peeks the next token.
+    fn peek(&mut self) -> Result<&Token<'a>, ParseError> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lexer.next_token()?);
+        }
+        Ok(&self
+            .peeked
+            .as_ref()
+            .expect("Should never fail: token is already peeked")
+            .1)
+    }
+
+    /// Reads the next token and returns it with its beginning position.
+    fn next(&mut self) -> Result<(usize, Token<'a>), ParseError> {
+        match self.peeked.take() {
+            Some(v) => Ok(v),
+            None => self.lexer.next_token(),
+        }
+    }
+
+    /// Returns the end position of the last consumed token.
+    fn position(&self) -> usize {
+        match self.peeked {
+            Some((pos, _)) => pos,
+            None => self.lexer.position(),
+        }
+    }
+
+    /// Parses the whole document and appends nodes to the given document.
+    pub(crate) fn parse(&mut self, document: &mut Document) -> Result<(), ParseError> {
+        loop {
+            match self.next()? {
+                (pos, Token::Name(name)) => self.parse_node(document, None, name, pos)?,
+                (_, Token::Eof) => return Ok(()),
+                (pos, token) => return Err(unexpected(pos, &token, "node name")),
+            }
+        }
+    }
+
+    /// Parses the node with the given name, and its children.
+    fn parse_node(
+        &mut self,
+        document: &mut Document,
+        parent: Option<NodeId>,
+        name: &str,
+        start: usize,
+    ) -> Result<(), ParseError> {
+        let mut attrs = self.parse_attributes(name)?;
+        normalize_attributes(document, parent, name, &mut attrs);
+        let node_id = document.push_node(parent, name.to_owned(), attrs, start as u64);
+
+        self.num_loaded_nodes += 1;
+        if self.num_loaded_nodes % PROGRESS_INTERVAL_NODES == 0 && !(self.progress)(start as u64) {
+            return Err(ParseError::new(start, "Loading cancelled"));
+        }
+
+        if *self.peek()? == Token::OpenBrace {
+            self.next()?;
+            loop {
+                match self.next()? {
+                    (pos, Token::Name(child)) => {
+                        self.parse_node(document, Some(node_id), child, pos)?
+                    }
+                    (_, Token::CloseBrace) => break,
+                    (pos, token) => return Err(unexpected(pos, &token, "child node name or `}`")),
+                }
+            }
+        }
+        document.set_end_offset(node_id, self.position() as u64);
+
+        Ok(())
+    }
+
+    /// Parses the attributes of the current node.
+    fn parse_attributes(&mut self, node_name: &str) -> Result<Vec<Attribute>, ParseError> {
+        let mut attrs = Vec::new();
+        loop {
+            let attr = match self.peek()? {
+                // Empty attributes (such as `Content: , "..."`) are skipped.
+                Token::Comma => {
+                    self.next()?;
+                    continue;
+                }
+                Token::String(_) | Token::Number(_) | Token::Word(_) | Token::ArrayLen(_) => {
+                    match self.next()? {
+                        (_, Token::String(s)) => Attribute::String(s),
+                        (pos, Token::Number(s)) => parse_number(pos, s)?,
+                        (_, Token::Word(s)) => parse_word(s),
+                        (pos, Token::ArrayLen(len)) => self.parse_array(node_name, pos, len)?,
+                        _ => unreachable!("Should never fail: the token is already peeked"),
+                    }
+                }
+                _ => return Ok(attrs),
+            };
+            attrs.push(attr);
+        }
+    }
+
+    /// Parses the array body (`{ a: ... }`).
+    fn parse_array(
+        &mut self,
+        node_name: &str,
+        start: usize,
+        len: usize,
+    ) -> Result<Attribute, ParseError> {
+        match self.next()? {
+            (_, Token::OpenBrace) => {}
+            (pos, token) => return Err(unexpected(pos, &token, "`{`")),
+        }
+        match self.next()? {
+            (_, Token::Name("a")) => {}
+            (pos, token) => return Err(unexpected(pos, &token, "`a:`")),
+        }
+        // The length is not trusted, since it may be broken.
+        let mut values = Vec::with_capacity(len.min(MAX_PREALLOCATED_ELEMENTS));
+        loop {
+            match self.next()? {
+                (_, Token::Number(s)) => values.push(s),
+                (_, Token::Comma) => {}
+                (_, Token::CloseBrace) => break,
+                (pos, token) => return Err(unexpected(pos, &token, "number or `}`")),
+            }
+        }
+
+        let parse_err = |_| ParseError::new(start, "Invalid number in array");
+        let is_float = values.iter().any(|s| s.contains(['.', 'e', 'E', '#']));
+        let attr = if F32_ARRAY_NODES.contains(&node_name) {
+            Attribute::ArrayF32(
+                values
+                    .iter()
+                    .map(|s| parse_float(s).map(|v| v as f32))
+                    .collect::<Result<_, _>>()
+                    .map_err(parse_err)?,
+            )
+        } else if is_float || F64_ARRAY_NODES.contains(&node_name) {
+            Attribute::ArrayF64(
+                values
+                    .iter()
+                    .map(|s| parse_float(s))
+                    .collect::<Result<_, _>>()
+                    .map_err(parse_err)?,
+            )
+        } else {
+            let values = values
+                .iter()
+                .map(|s| s.parse::<i64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| ParseError::new(start, "Invalid integer in array"))?;
+            if !I64_ARRAY_NODES.contains(&node_name) && values.iter().all(|&v| v as i32 as i64 == v)
+            {
+                Attribute::ArrayI32(values.into_iter().map(|v| v as i32).collect())
+            } else {
+                Attribute::ArrayI64(values)
+            }
+        };
+        Ok(attr)
+    }
+}
+
+/// Creates an error for unexpected token.
+fn unexpected(pos: usize, token: &Token<'_>, expected: &str) -> ParseError {
+    ParseError::new(
+        pos,
+        format!("Unexpected token {:?}: expected {}", token, expected),
+    )
+}
+
+/// Parses the given number.
+///
+/// Integers are `i32` if it fits, otherwise `i64`.
+/// Non-integers are `f64`.
+fn parse_number(pos: usize, s: &str) -> Result<Attribute, ParseError> {
+    if let Ok(v) = s.parse::<i64>() {
+        return Ok(if v as i32 as i64 == v {
+            Attribute::SingleI32(v as i32)
+        } else {
+            Attribute::SingleI64(v)
+        });
+    }
+    parse_float(s)
+        .map(Attribute::SingleF64)
+        .map_err(|_| ParseError::new(pos, format!("Invalid number {:?}", s)))
+}
+
+/// Parses the given floating point number.
+///
+/// This accepts infinity and NaN representations by MSVC runtime
+/// (such as `1.#INF` and `-1.#IND`) in addition to Rust ones.
+fn parse_float(s: &str) -> Result<f64, std::num::ParseFloatError> {
+    let lower = s.to_ascii_lowercase();
+    if lower.contains("#inf") {
+        return Ok(if s.starts_with('-') {
+            f64::NEG_INFINITY
+        } else {
+            f64::INFINITY
+        });
+    }
+    if lower.contains("#ind") || lower.contains("#qnan") || lower.contains("#snan") {
+        return Ok(f64::NAN);
+    }
+    s.parse()
+}
+
+/// Parses the given bare word.
+fn parse_word(s: &str) -> Attribute {
+    match s {
+        "T" | "Y" => Attribute::SingleBool(true),
+        "F" | "N" => Attribute::SingleBool(false),
+        _ => Attribute::String(s.to_owned()),
+    }
+}
+
+/// Converts attributes to the same representation as FBX binary.
+///
+/// FBX ASCII loses some type information, and uses different representation
+/// for object names.
+/// This function recovers them for well-known nodes.
+fn normalize_attributes(
+    document: &Document,
+    parent: Option<NodeId>,
+    name: &str,
+    attrs: &mut [Attribute],
+) {
+    let parent_name = parent.map(|id| document.node(id).name());
+    let grandparent = parent.and_then(|id| document.node(id).parent());
+    match (parent_name, grandparent, name) {
+        // Objects: `ID, "Class::Name", "Subclass"`.
+        (Some("Objects"), None, _) => {
+            if let Some(attr) = attrs.get_mut(0) {
+                to_i64(attr);
+            }
+            if let Some(Attribute::String(s)) = attrs.get_mut(1) {
+                if let Some(sep) = s.find("::") {
+                    *s = format!("{}\u{0}\u{1}{}", &s[(sep + 2)..], &s[..sep]);
+                }
+            }
+        }
+        // Connections: `"OO", ID, ID`.
+        (Some("Connections"), None, "C") => {
+            for attr in attrs.iter_mut().skip(1).take(2) {
+                to_i64(attr);
+            }
+        }
+        // Properties: `"Name", "Type", "Label", "Flags", values...`.
+        (Some("Properties70"), _, "P") => {
+            let ty = match attrs.get(1) {
+                Some(Attribute::String(ty)) => ty.clone(),
+                _ => return,
+            };
+            for attr in attrs.iter_mut().skip(4) {
+                if I64_PROPERTY_TYPES.contains(&ty.as_str()) {
+                    to_i64(attr);
+                } else if !INT_PROPERTY_TYPES.contains(&ty.as_str()) {
+                    to_f64(attr);
+                }
+            }
+        }
+        // Animation curves: `Default: value`.
+        (Some("AnimationCurve"), _, "Default") => {
+            if let Some(attr) = attrs.get_mut(0) {
+                to_f64(attr);
+            }
+        }
+        // Embedded files are base64-encoded.
+        (_, _, "Content") => {
+            if let Some(attr) = attrs.get_mut(0) {
+                let decoded = match attr {
+                    Attribute::String(s) => BASE64.decode(s.as_bytes()).ok(),
+                    _ => None,
+                };
+                if let Some(decoded) = decoded {
+                    *attr = Attribute::Binary(decoded);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Converts the given integer attribute into `i64`.
+fn to_i64(attr: &mut Attribute) {
+    if let Attribute::SingleI32(v) = *attr {
+        *attr = Attribute::SingleI64(i64::from(v));
+    }
+}
+
+/// Converts the given integer attribute into `f64`.
+fn to_f64(attr: &mut Attribute) {
+    match *attr {
+        Attribute::SingleI32(v) => *attr = Attribute::SingleF64(f64::from(v)),
+        Attribute::SingleI64(v) => *attr = Attribute::SingleF64(v as f64),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fbxcel::low::FbxVersion;
+
+    use crate::fbx::{load_ascii, Attribute, Document, NodeId, ParseError};
+
+    /// Minimal FBX ASCII.
+    const SAMPLE: &str = r#"; FBX 7.5.0 project file
+; ----------------------------------------------------
+
+FBXHeaderExtension:  {
+	FBXVersion: 7500
+}
+Objects:  {
+	Geometry: 1000, "Geometry::Cube", "Mesh" {
+		Vertices: *6 {
+			a: 0,0,1,
+			-1.5e2,1.#INF,2
+		}
+		PolygonVertexIndex: *3 {
+			a: 0,1,-3
+		}
+		KeyTime: *2 {
+			a: 0,1
+		}
+		Properties70:  {
+			P: "Visibility", "bool", "", "A",1
+			P: "Lcl Translation", "Lcl Translation", "", "A",1,2,3.5
+			P: "Time", "KTime", "Time", "",46186158000
+			P: "Name", "KString", "", "", "&quot;quoted&quot;"
+		}
+	}
+	Video: 2000, "Video::Tex", "Clip" {
+		Content: , "AAEC/w=="
+	}
+}
+Connections:  {
+	C: "OO",1000,0
+}
+"#;
+
+    /// Loads the given source, panicking on errors.
+    fn load(src: &str) -> Document {
+        let result = load_ascii(src.as_bytes(), |_| true);
+        if let Some(err) = result.error {
+            panic!("failed to load: {}", err);
+        }
+        result.document.unwrap()
+    }
+
+    /// Returns the node with the given path.
+    fn find(doc: &Document, path: &str) -> NodeId {
+        doc.node_ids()
+            .find(|&id| doc.path(id) == path)
+            .unwrap_or_else(|| panic!("no node {}", path))
+    }
+
+    /// Returns the attributes of the node with the given path.
+    fn attrs<'a>(doc: &'a Document, path: &str) -> &'a [Attribute] {
+        doc.node(find(doc, path)).attributes()
+    }
+
+    #[test]
+    fn version() {
+        assert_eq!(load(SAMPLE).fbx_version(), FbxVersion::V7_5);
+        assert_eq!(load("A: 1").fbx_version(), FbxVersion::V7_4);
+    }
+
+    #[test]
+    fn arrays() {
+        let doc = load(SAMPLE);
+        assert_eq!(
+            attrs(&doc, "Objects/Geometry/Vertices"),
+            [Attribute::ArrayF64(vec![
+                0.0,
+                0.0,
+                1.0,
+                -150.0,
+                f64::INFINITY,
+                2.0
+            ])]
+        );
+        assert_eq!(
+            attrs(&doc, "Objects/Geometry/PolygonVertexIndex"),
+            [Attribute::ArrayI32(vec![0, 1, -3])]
+        );
+        assert_eq!(
+            attrs(&doc, "Objects/Geometry/KeyTime"),
+            [Attribute::ArrayI64(vec![0, 1])]
+        );
+    }
+
+    #[test]
+    fn object_names() {
+        let doc = load(SAMPLE);
+        assert_eq!(
+            attrs(&doc, "Objects/Geometry"),
+            [
+                Attribute::SingleI64(1000),
+                Attribute::String("Cube\u{0}\u{1}Geometry".to_owned()),
+                Attribute::String("Mesh".to_owned()),
+            ]
+        );
+        assert_eq!(
+            attrs(&doc, "Connections/C"),
+            [
+                Attribute::String("OO".to_owned()),
+                Attribute::SingleI64(1000),
+                Attribute::SingleI64(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn properties() {
+        let doc = load(SAMPLE);
+        let props = find(&doc, "Objects/Geometry/Properties70");
+        let values: Vec<_> = doc
+            .node(props)
+            .children()
+            .iter()
+            .map(|&id| &doc.node(id).attributes()[4..])
+            .collect();
+        assert_eq!(values[0], [Attribute::SingleI32(1)]);
+        assert_eq!(
+            values[1],
+            [
+                Attribute::SingleF64(1.0),
+                Attribute::SingleF64(2.0),
+                Attribute::SingleF64(3.5),
+            ]
+        );
+        assert_eq!(values[2], [Attribute::SingleI64(46_186_158_000)]);
+        assert_eq!(values[3], [Attribute::String("\"quoted\"".to_owned())]);
+    }
+
+    #[test]
+    fn base64_content() {
+        let doc = load(SAMPLE);
+        assert_eq!(
+            attrs(&doc, "Objects/Video/Content"),
+            [Attribute::Binary(vec![0, 1, 2, 255])]
+        );
+    }
+
+    #[test]
+    fn error_position() {
+        let src = "A: 1 {\n\tB: *2 {\n\t\ta: 1,x\n\t}\n}\n";
+        let result = load_ascii(src.as_bytes(), |_| true);
+        let err = result.error.expect("should fail");
+        let err = err
+            .downcast_ref::<ParseError>()
+            .expect("should be a syntax error");
+        assert_eq!(err.line(), Some(3));
+        assert_eq!(err.column(), Some(8));
+        assert_eq!(err.offset(), src.find('x').unwrap());
+        // Nodes before the error are kept.
+        let doc = result.document.unwrap();
+        assert_eq!(doc.node(find(&doc, "A")).attributes().len(), 1);
+    }
+
+    #[test]
+    fn huge_array_length() {
+        // The declared length is not trusted for the allocation.
+        let doc = load("A: *4294967295 {\n\ta: 1,2\n}\n");
+        assert_eq!(attrs(&doc, "A"), [Attribute::ArrayI32(vec![1, 2])]);
+    }
+}
@@ -0,0 +1,284 @@
+//! Writer for FBX ASCII format.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+use crate::fbx::{Attribute, Document, NodeId};
+
+/// Number of array elements per line.
+///
+/// Arrays are wrapped to make the output friendly to line-based diff tools.
+const ELEMENTS_PER_LINE: usize = 16;
+
+/// Writes the document as FBX ASCII to the given writer.
+///
+/// Strings are quoted with `"` escaped as `&quot;`, and object names
+/// (`Name\x00\x01Class`) are written as `Class::Name`.
+/// Binaries are base64-encoded strings.
+///
+/// Note that FBX ASCII does not distinguish some types (such as `i32` and
+/// `i64`, or `f32` and `f64`), so they may be loaded as different types.
+/// The loader recovers the types of well-known nodes (such as object IDs
+/// and `KeyValueFloat`), but `i16` and `[bool]` are loaded as `i32` and
+/// `[i32]`, and other `f32` values as `f64`.
+pub fn write_ascii<W: Write>(document: &Document, out: &mut W) -> io::Result<()> {
+    let version = document.fbx_version();
+    writeln!(
+        out,
+        "; FBX {}.{}.0 project file",
+        version.major(),
+        version.minor()
+    )?;
+    writeln!(out, "; {}", "-".repeat(52))?;
+    for &node_id in document.toplevel_nodes() {
+        writeln!(out)?;
+        write_node(document, node_id, 0, out)?;
+    }
+    Ok(())
+}
+
+/// Writes the document as FBX ASCII to the given file.
+///
+/// The document is written to a temporary file in the same directory first,
+/// and it replaces the destination on success, so that the destination is
+/// kept on failure.
+pub fn save_ascii_file<P: AsRef<Path>>(document: &Document, path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut temp_name = path.file_name().unwrap_or_default().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let res = File::create(&temp_path)
+        .and_then(|file| {
+            let mut out = io::BufWriter::new(file);
+            write_ascii(document, &mut out)?;
+            out.flush()
+        })
+        .and_then(|_| std::fs::rename(&temp_path, path));
+    if res.is_err() {
+        // The temporary file is useless on failure.
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    res
+}
+
+/// Writes the given node and its descendants.
+fn write_node<W: Write>(
+    document: &Document,
+    node_id: NodeId,
+    depth: usize,
+    out: &mut W,
+) -> io::Result<()> {
+    let node = document.node(node_id);
+    let attrs = document.load_attributes(node_id)?;
+    let indent = "\t".repeat(depth);
+    write!(out, "{}{}:", indent, node.name())?;
+    for (i, attr) in attrs.iter().enumerate() {
+        write!(out, "{}", if i == 0 { " " } else { ", " })?;
+        write_attribute(out, attr, &indent)?;
+    }
+
+    let children = node.children();
+    if children.is_empty() {
+        return writeln!(out);
+    }
+    writeln!(out, " {{")?;
+    for &child in children {
+        write_node(document, child, depth + 1, out)?;
+    }
+    writeln!(out, "{}}}", indent)
+}
+
+/// Writes the attribute value.
+///
+/// `indent` is the indent of the node, used for array bodies.
+fn write_attribute<W: Write>(out: &mut W, attr: &Attribute, indent: &str) -> io::Result<()> {
+    match attr {
+        Attribute::SingleBool(v) => write!(out, "{}", if *v { "T" } else { "F" }),
+        Attribute::SingleI16(v) => write!(out, "{}", v),
+        Attribute::SingleI32(v) => write!(out, "{}", v),
+        Attribute::SingleI64(v) => write!(out, "{}", v),
+        Attribute::SingleF32(v) => write!(out, "{}", float_string(*v, f64::from(*v))),
+        Attribute::SingleF64(v) => write!(out, "{}", float_string(*v, *v)),
+        Attribute::ArrayBool(arr) => write_array(out, indent, arr, |&v| (v as u8).to_string()),
+        Attribute::ArrayI32(arr) => write_array(out, indent, arr, ToString::to_string),
+        Attribute::ArrayI64(arr) => write_array(out, indent, arr, ToString::to_string),
+        Attribute::ArrayF32(arr) => {
+            write_array(out, indent, arr, |&v| float_string(v, f64::from(v)))
+        }
+        Attribute::ArrayF64(arr) => write_array(out, indent, arr, |&v| float_string(v, v)),
+        Attribute::String(s) => write_string(out, s),
+        Attribute::Binary(bytes) => write!(out, "\"{}\"", BASE64.encode(bytes)),
+    }
+}
+
+/// Writes the array as `*N { a: ... }`.
+fn write_array<W, T, F>(out: &mut W, indent: &str, arr: &[T], to_string: F) -> io::Result<()>
+where
+    W: Write,
+    F: Fn(&T) -> String,
+{
+    writeln!(out, "*{} {{", arr.len())?;
+    write!(out, "{}\ta: ", indent)?;
+    for (i, v) in arr.iter().enumerate() {
+        if i != 0 {
+            write!(out, ",")?;
+            if i % ELEMENTS_PER_LINE == 0 {
+                write!(out, "\n{}\t   ", indent)?;
+            }
+        }
+        write!(out, "{}", to_string(v))?;
+    }
+    write!(out, "\n{}}}", indent)
+}
+
+/// Returns the string representation of the floating point number.
+///
+/// Finite numbers always have a decimal point or an exponent, so that they
+/// are not loaded as integers.
+/// Infinity and NaN are written in the MSVC runtime representation (such as
+/// `1.#INF`), as FBX SDK does.
+fn float_string<T: std::fmt::Debug>(v: T, as_f64: f64) -> String {
+    if as_f64.is_nan() {
+        "1.#QNAN".to_owned()
+    } else if as_f64 == f64::INFINITY {
+        "1.#INF".to_owned()
+    } else if as_f64 == f64::NEG_INFINITY {
+        "-1.#INF".to_owned()
+    } else {
+        // `Debug` prints the shortest representation which round-trips.
+        format!("{:?}", v)
+    }
+}
+
+/// Writes the quoted string.
+fn write_string<W: Write>(out: &mut W, s: &str) -> io::Result<()> {
+    let s = match s.find("\u{0}\u{1}") {
+        Some(sep) => format!("{}::{}", &s[(sep + 2)..], &s[..sep]),
+        None => s.to_owned(),
+    };
+    write!(out, "\"{}\"", s.replace('"', "&quot;"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fbx::load_ascii;
+
+    /// Appends a node.
+    fn push(
+        doc: &mut Document,
+        parent: Option<NodeId>,
+        name: &str,
+        attrs: Vec<Attribute>,
+    ) -> NodeId {
+        doc.push_node(parent, name.to_owned(), attrs, 0)
+    }
+
+    /// Creates a document with well-known nodes and an unknown node, and
+    /// returns it with the expected attributes of the unknown node after
+    /// the round trip.
+    fn sample() -> (Document, Vec<Attribute>) {
+        let mut doc = Document::default();
+        let objects = push(&mut doc, None, "Objects", vec![]);
+        let geometry = push(
+            &mut doc,
+            Some(objects),
+            "Geometry",
+            vec![
+                Attribute::SingleI64(1),
+                Attribute::String("Cube\u{0}\u{1}Geometry".to_owned()),
+                Attribute::String("Mesh".to_owned()),
+            ],
+        );
+        push(
+            &mut doc,
+            Some(geometry),
+            "Vertices",
+            vec![Attribute::ArrayF64(vec![0.0, 1.0, 2.5])],
+        );
+        push(
+            &mut doc,
+            Some(geometry),
+            "PolygonVertexIndex",
+            vec![Attribute::ArrayI32(vec![0, 1, -3])],
+        );
+        let unknown = vec![
+            Attribute::SingleBool(true),
+            Attribute::SingleI16(-2),
+            Attribute::SingleF32(0.5),
+            Attribute::ArrayBool(vec![true, false]),
+            Attribute::String("\"quoted\" C:\\path".to_owned()),
+        ];
+        push(&mut doc, Some(geometry), "Unknown", unknown);
+        let expected = vec![
+            Attribute::SingleBool(true),
+            Attribute::SingleI32(-2),
+            Attribute::SingleF64(0.5),
+            Attribute::ArrayI32(vec![1, 0]),
+            Attribute::String("\"quoted\" C:\\path".to_owned()),
+        ];
+        let curve = push(
+            &mut doc,
+            Some(objects),
+            "AnimationCurve",
+            vec![
+                Attribute::SingleI64(2),
+                Attribute::String("\u{0}\u{1}AnimCurve".to_owned()),
+                Attribute::String(String::new()),
+            ],
+        );
+        push(
+            &mut doc,
+            Some(curve),
+            "KeyTime",
+            vec![Attribute::ArrayI64(vec![0, 1_924_423_250])],
+        );
+        push(
+            &mut doc,
+            Some(curve),
+            "KeyValueFloat",
+            vec![Attribute::ArrayF32(vec![0.1, -1.0e-7])],
+        );
+        let video = push(
+            &mut doc,
+            Some(objects),
+            "Video",
+            vec![Attribute::SingleI64(3)],
+        );
+        push(
+            &mut doc,
+            Some(video),
+            "Content",
+            vec![Attribute::Binary(vec![0, 1, 2, 255])],
+        );
+        (doc, expected)
+    }
+
+    #[test]
+    fn round_trip() {
+        let (doc, expected) = sample();
+        let mut out = Vec::new();
+        write_ascii(&doc, &mut out).unwrap();
+        let result = load_ascii(&out[..], |_| true);
+        assert!(result.error.is_none(), "{:?}", result.error);
+        let loaded = result.document.unwrap();
+
+        assert_eq!(loaded.fbx_version(), doc.fbx_version());
+        assert_eq!(loaded.len(), doc.len());
+        for (old, new) in doc.node_ids().zip(loaded.node_ids()) {
+            let (old, new) = (doc.node(old), loaded.node(new));
+            assert_eq!(old.name(), new.name());
+            if old.name() == "Unknown" {
+                assert_eq!(new.attributes(), &expected[..]);
+            } else {
+                assert_eq!(old.attributes(), new.attributes(), "{}", old.name());
+            }
+        }
+    }
+}
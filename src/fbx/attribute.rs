@@ -1,11 +1,11 @@
 //! FBX node attribute.
 
-use std::io;
+use std::{fmt, io};
 
 use fbxcel::pull_parser::{self as fbxbin, Result};
 
 /// FBX node attribute.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Attribute {
     /// `bool`.
     SingleBool(bool),
@@ -55,6 +55,145 @@ impl Attribute {
         }
     }
 
+    /// Returns the number of elements if the attribute is an array or binary.
+    pub fn array_len(&self) -> Option<usize> {
+        match *self {
+            Attribute::ArrayBool(ref arr) => Some(arr.len()),
+            Attribute::ArrayI32(ref arr) => Some(arr.len()),
+            Attribute::ArrayI64(ref arr) => Some(arr.len()),
+            Attribute::ArrayF32(ref arr) => Some(arr.len()),
+            Attribute::ArrayF64(ref arr) => Some(arr.len()),
+            Attribute::Binary(ref arr) => Some(arr.len()),
+            _ => None,
+        }
+    }
+
+    /// Returns string representation of the element at the given index, if
+    /// the attribute is an array or binary.
+    pub fn element_string(&self, index: usize) -> Option<String> {
+        match *self {
+            Attribute::ArrayBool(ref arr) => arr.get(index).map(|&v| (v as u8).to_string()),
+            Attribute::ArrayI32(ref arr) => arr.get(index).map(ToString::to_string),
+            Attribute::ArrayI64(ref arr) => arr.get(index).map(ToString::to_string),
+            Attribute::ArrayF32(ref arr) => arr.get(index).map(ToString::to_string),
+            Attribute::ArrayF64(ref arr) => arr.get(index).map(ToString::to_string),
+            Attribute::Binary(ref arr) => arr.get(index).map(|v| format!("{:02x}", v)),
+            _ => None,
+        }
+    }
+
+    /// Returns single-line string representation.
+    ///
+    /// Arrays with more than `max_elements` elements are truncated, and the
+    /// number of elements is appended.
+    /// Strings are quoted and escaped.
+    pub fn value_string_oneline(&self, max_elements: Option<usize>) -> String {
+        fn join<T, I>(iter: I, max_elements: Option<usize>) -> String
+        where
+            T: std::fmt::Display,
+            I: ExactSizeIterator<Item = T>,
+        {
+            let len = iter.len();
+            let limit = max_elements.unwrap_or(len);
+            let mut s = String::from("[");
+            for (i, val) in iter.take(limit).enumerate() {
+                if i != 0 {
+                    s.push_str(", ");
+                }
+                s.push_str(&val.to_string());
+            }
+            if len > limit {
+                if limit != 0 {
+                    s.push_str(", ");
+                }
+                s.push_str(&format!("...] ({} elements)", len));
+            } else {
+                s.push(']');
+            }
+            s
+        }
+
+        match *self {
+            Attribute::ArrayBool(ref arr) => join(arr.iter().map(|&v| v as u8), max_elements),
+            Attribute::ArrayI32(ref arr) => join(arr.iter(), max_elements),
+            Attribute::ArrayI64(ref arr) => join(arr.iter(), max_elements),
+            Attribute::ArrayF32(ref arr) => join(arr.iter(), max_elements),
+            Attribute::ArrayF64(ref arr) => join(arr.iter(), max_elements),
+            Attribute::String(ref val) => format!("{:?}", val),
+            Attribute::Binary(ref arr) => {
+                join(arr.iter().map(|v| format!("{:02x}", v)), max_elements)
+            }
+            _ => self.value_string(),
+        }
+    }
+
+    /// Returns whether the value can be parsed from text by
+    /// [`parse_value`][`Self::parse_value`].
+    ///
+    /// This is true for single values and strings.
+    pub fn is_editable(&self) -> bool {
+        self.array_len().is_none()
+    }
+
+    /// Returns the text to edit the value, or `None` if the attribute is not
+    /// editable.
+    ///
+    /// This is [`value_string`][`Self::value_string`] except that
+    /// backslashes in strings are escaped as `\\`, so that
+    /// [`parse_value`][`Self::parse_value`] reads back the same string.
+    pub fn edit_text(&self) -> Option<String> {
+        match self {
+            Attribute::String(val) => Some(escape_string(val)),
+            _ if self.is_editable() => Some(self.value_string()),
+            _ => None,
+        }
+    }
+
+    /// Parses the text as a new value of the same type as the attribute.
+    ///
+    /// The text is expected in the form of [`edit_text`][`Self::edit_text`]:
+    /// booleans are `true` or `false` (`T`/`F` and `1`/`0` are also
+    /// accepted), and `\\`, `\r` and `\xNN` in strings are unescaped.
+    /// Integers out of range of the type are errors.
+    ///
+    /// Arrays and binaries are not editable and always result in errors.
+    pub fn parse_value(&self, text: &str) -> std::result::Result<Attribute, ValueParseError> {
+        let type_name = self.type_string();
+        let trimmed = text.trim();
+        let err = |e: &dyn fmt::Display| ValueParseError::new(type_name, e);
+        match *self {
+            Attribute::SingleBool(_) => match trimmed {
+                "true" | "T" | "1" => Ok(Attribute::SingleBool(true)),
+                "false" | "F" | "0" => Ok(Attribute::SingleBool(false)),
+                _ => Err(err(&"expected `true` or `false`")),
+            },
+            Attribute::SingleI16(_) => trimmed
+                .parse()
+                .map(Attribute::SingleI16)
+                .map_err(|e| err(&e)),
+            Attribute::SingleI32(_) => trimmed
+                .parse()
+                .map(Attribute::SingleI32)
+                .map_err(|e| err(&e)),
+            Attribute::SingleI64(_) => trimmed
+                .parse()
+                .map(Attribute::SingleI64)
+                .map_err(|e| err(&e)),
+            Attribute::SingleF32(_) => trimmed
+                .parse()
+                .map(Attribute::SingleF32)
+                .map_err(|e| err(&e)),
+            Attribute::SingleF64(_) => trimmed
+                .parse()
+                .map(Attribute::SingleF64)
+                .map_err(|e| err(&e)),
+            Attribute::String(_) => unescape_string(text)
+                .map(Attribute::String)
+                .ok_or_else(|| err(&"invalid escape sequence")),
+            _ => Err(err(&"arrays and binaries are not editable")),
+        }
+    }
+
     /// Returns string representation.
     pub fn value_string(&self) -> String {
         match *self {
@@ -147,6 +286,82 @@ impl Attribute {
     }
 }
 
+/// Error of parsing an attribute value from text.
+#[derive(Debug, Clone)]
+pub struct ValueParseError {
+    /// Type name of the expected value.
+    type_name: String,
+    /// Error message.
+    message: String,
+}
+
+impl ValueParseError {
+    /// Creates a new error.
+    fn new(type_name: &str, message: impl fmt::Display) -> Self {
+        Self {
+            type_name: type_name.to_owned(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ValueParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} value: {}", self.type_name, self.message)
+    }
+}
+
+impl std::error::Error for ValueParseError {}
+
+/// Escapes backslashes, `\r` and control characters (except `\n` and `\t`)
+/// as `\\`, `\r` and `\xNN`.
+fn escape_string(val: &str) -> String {
+    val.chars()
+        .fold(String::with_capacity(val.len()), |mut s, c| {
+            match c {
+                '\n' | '\t' => s.push(c),
+                '\\' => s.push_str("\\\\"),
+                '\r' => s.push_str("\\r"),
+                _ if (c <= '\x1f') || (c == '\x7f') => s.push_str(&format!("\\x{:02x}", c as u32)),
+                c => s.push(c),
+            }
+            s
+        })
+}
+
+/// Unescapes `\\`, `\r` and `\xNN` escaped by [`Attribute::edit_text`].
+///
+/// Returns `None` if the escape sequence is invalid, including lone
+/// backslashes.
+fn unescape_string(s: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(pos) = rest.find('\\') {
+        unescaped.push_str(&rest[..pos]);
+        rest = &rest[(pos + 1)..];
+        if let Some(after) = rest.strip_prefix('\\') {
+            unescaped.push('\\');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('r') {
+            unescaped.push('\r');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('x') {
+            let code = after
+                .get(..2)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())?;
+            if code > 0x7f {
+                return None;
+            }
+            unescaped.push(char::from(code));
+            rest = &after[2..];
+        } else {
+            return None;
+        }
+    }
+    unescaped.push_str(rest);
+    Some(unescaped)
+}
+
 /// FBX 7.4 attribute loader.
 #[derive(Debug, Clone)]
 pub struct AttributeLoader;
@@ -222,3 +437,84 @@ impl fbxbin::v7400::LoadAttribute for AttributeLoader {
         Ok(Attribute::String(buf))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses the edit text of the attribute as the same type.
+    fn round_trip(attr: &Attribute) -> Attribute {
+        let text = attr.edit_text().expect("attribute should be editable");
+        attr.parse_value(&text)
+            .expect("edit text should be parsable")
+    }
+
+    #[test]
+    fn string_round_trip() {
+        for s in &[
+            "",
+            r"C:\render\tex.png",
+            r"\\server\share\x41",
+            "trailing\\",
+            "\"quoted\" 'single'",
+            "日本語 and ümlauts",
+            "line\nbreak\ttab\rreturn",
+            "Cube\u{0}\u{1}Model",
+            "del\u{7f}",
+        ] {
+            let attr = Attribute::String((*s).to_owned());
+            assert_eq!(round_trip(&attr), attr, "string {:?}", s);
+        }
+    }
+
+    #[test]
+    fn scalar_round_trip() {
+        for attr in &[
+            Attribute::SingleBool(true),
+            Attribute::SingleBool(false),
+            Attribute::SingleI16(i16::MIN),
+            Attribute::SingleI32(-42),
+            Attribute::SingleI64(i64::MAX),
+            Attribute::SingleF32(0.1),
+            Attribute::SingleF64(-1.0e-300),
+        ] {
+            assert_eq!(&round_trip(attr), attr);
+        }
+    }
+
+    #[test]
+    fn value_string_keeps_backslashes() {
+        let attr = Attribute::String(r"C:\render\tex.png".to_owned());
+        assert_eq!(attr.value_string(), r"C:\render\tex.png");
+        assert_eq!(attr.edit_text().unwrap(), r"C:\\render\\tex.png");
+        let attr = Attribute::String("a\rb\u{1}".to_owned());
+        assert_eq!(attr.value_string(), r"a\rb\x01");
+        assert_eq!(attr.edit_text().unwrap(), attr.value_string());
+        assert_eq!(Attribute::ArrayI32(vec![1]).edit_text(), None);
+    }
+
+    #[test]
+    fn lone_backslash_is_error() {
+        let attr = Attribute::String(String::new());
+        assert!(attr.parse_value(r"C:\path").is_err());
+        assert!(attr.parse_value("\\").is_err());
+        assert!(attr.parse_value(r"\x8f").is_err());
+    }
+
+    #[test]
+    fn type_checked_parse() {
+        assert!(Attribute::SingleI16(0).parse_value("40000").is_err());
+        assert!(Attribute::SingleI32(0).parse_value("1.5").is_err());
+        assert!(Attribute::SingleBool(false).parse_value("yes").is_err());
+        assert_eq!(
+            Attribute::SingleBool(false).parse_value(" T ").unwrap(),
+            Attribute::SingleBool(true)
+        );
+        assert_eq!(
+            Attribute::SingleF64(0.0).parse_value("2").unwrap(),
+            Attribute::SingleF64(2.0)
+        );
+        assert!(Attribute::ArrayI32(vec![1]).parse_value("1").is_err());
+        assert!(Attribute::Binary(vec![1]).parse_value("01").is_err());
+    }
+}
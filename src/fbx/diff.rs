@@ -0,0 +1,879 @@
+//! Structural diff of FBX documents.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, Write},
+};
+
+use fbxcel::low::FbxVersion;
+
+use crate::fbx::{Attribute, Document, NodeId};
+
+/// Maximum number of array elements shown for each compared value.
+const MAX_VALUE_ELEMENTS: usize = 8;
+
+/// Options for [`diff_documents`].
+#[derive(Debug, Default, Clone)]
+pub struct DiffOptions {
+    /// Maximum absolute difference of floating point numbers regarded as
+    /// equal.
+    pub float_tolerance: f64,
+    /// How children of the top-level `Objects` node are aligned.
+    pub object_key: ObjectKey,
+    /// Whether numbers of different types (such as `i32` and `i64`, or
+    /// `[bool]` and `[i32]`) are compared by value.
+    ///
+    /// This is useful to compare with FBX ASCII files, which do not keep the
+    /// types of numbers.
+    pub ignore_numeric_types: bool,
+}
+
+/// Key to align children of the top-level `Objects` node.
+///
+/// Objects without the key (and other nodes) are aligned by the name and
+/// the position among the siblings with the same name.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKey {
+    /// Object ID (the first attribute).
+    #[default]
+    Id,
+    /// Object name (the second attribute), such as `Model::Cube`.
+    ///
+    /// This is useful to compare files with regenerated object IDs.
+    Name,
+}
+
+/// Kind of difference of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    /// The node exists in both documents with the same attributes.
+    ///
+    /// Descendants may differ.
+    Same,
+    /// The node exists in both documents with different attributes.
+    Changed,
+    /// The node exists only in the new document.
+    Added,
+    /// The node exists only in the old document.
+    Removed,
+}
+
+impl DiffKind {
+    /// Returns the marker used in the text output, such as `+` for added.
+    pub fn marker(self) -> char {
+        match self {
+            DiffKind::Same => ' ',
+            DiffKind::Changed => '~',
+            DiffKind::Added => '+',
+            DiffKind::Removed => '-',
+        }
+    }
+}
+
+/// Difference of an attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeDiff {
+    /// The attribute exists only in the new node.
+    Added {
+        /// Attribute index.
+        index: usize,
+        /// Type and value.
+        value: String,
+    },
+    /// The attribute exists only in the old node.
+    Removed {
+        /// Attribute index.
+        index: usize,
+        /// Type and value.
+        value: String,
+    },
+    /// The type or the value differs.
+    Changed {
+        /// Attribute index.
+        index: usize,
+        /// Old type and value.
+        old: String,
+        /// New type and value.
+        new: String,
+    },
+    /// Arrays (or binaries) of the same type and length differ at some
+    /// elements.
+    ElementsChanged {
+        /// Attribute index.
+        index: usize,
+        /// Number of elements.
+        len: usize,
+        /// Number of different elements.
+        count: usize,
+        /// Index of the first different element.
+        first: usize,
+        /// Old value of the first different element.
+        old: String,
+        /// New value of the first different element.
+        new: String,
+    },
+}
+
+impl AttributeDiff {
+    /// Returns the attribute index.
+    pub fn index(&self) -> usize {
+        match *self {
+            AttributeDiff::Added { index, .. }
+            | AttributeDiff::Removed { index, .. }
+            | AttributeDiff::Changed { index, .. }
+            | AttributeDiff::ElementsChanged { index, .. } => index,
+        }
+    }
+}
+
+impl fmt::Display for AttributeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeDiff::Added { index, value } => write!(f, "#{}: added {}", index, value),
+            AttributeDiff::Removed { index, value } => write!(f, "#{}: removed {}", index, value),
+            AttributeDiff::Changed { index, old, new } => {
+                write!(f, "#{}: {} -> {}", index, old, new)
+            }
+            AttributeDiff::ElementsChanged {
+                index,
+                len,
+                count,
+                first,
+                old,
+                new,
+            } => write!(
+                f,
+                "#{}: {} of {} elements differ, first at [{}]: {} -> {}",
+                index, count, len, first, old, new
+            ),
+        }
+    }
+}
+
+/// Difference of a node and its descendants.
+#[derive(Debug, Clone)]
+pub struct NodeDiff {
+    /// Node name with the alignment key, such as `Model(1234)` or `P[2]`.
+    label: String,
+    /// Node in the old document.
+    old: Option<NodeId>,
+    /// Node in the new document.
+    new: Option<NodeId>,
+    /// Differences of the attributes.
+    attributes: Vec<AttributeDiff>,
+    /// Children.
+    children: Vec<NodeDiff>,
+    /// Whether the node or its descendants differ.
+    has_differences: bool,
+}
+
+impl NodeDiff {
+    /// Returns the node name with the key used for the alignment.
+    ///
+    /// Children of `Objects` have the object ID or name in parentheses, and
+    /// nodes with same-name siblings have the position among them in
+    /// brackets.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the node in the old document.
+    pub fn old_node(&self) -> Option<NodeId> {
+        self.old
+    }
+
+    /// Returns the node in the new document.
+    pub fn new_node(&self) -> Option<NodeId> {
+        self.new
+    }
+
+    /// Returns the kind of difference of the node itself.
+    pub fn kind(&self) -> DiffKind {
+        match (self.old, self.new) {
+            (Some(_), None) => DiffKind::Removed,
+            (None, Some(_)) => DiffKind::Added,
+            _ if self.attributes.is_empty() => DiffKind::Same,
+            _ => DiffKind::Changed,
+        }
+    }
+
+    /// Returns the differences of the attributes.
+    pub fn attributes(&self) -> &[AttributeDiff] {
+        &self.attributes
+    }
+
+    /// Returns the children.
+    ///
+    /// Children of added or removed nodes are also added or removed.
+    pub fn children(&self) -> &[NodeDiff] {
+        &self.children
+    }
+
+    /// Returns whether the node or its descendants differ.
+    pub fn has_differences(&self) -> bool {
+        self.has_differences
+    }
+}
+
+/// Structural difference of two documents.
+#[derive(Debug, Clone)]
+pub struct DocumentDiff {
+    /// FBX version of the old document.
+    old_version: FbxVersion,
+    /// FBX version of the new document.
+    new_version: FbxVersion,
+    /// Top-level nodes.
+    nodes: Vec<NodeDiff>,
+}
+
+impl DocumentDiff {
+    /// Returns the FBX versions of the old and new documents.
+    pub fn versions(&self) -> (FbxVersion, FbxVersion) {
+        (self.old_version, self.new_version)
+    }
+
+    /// Returns the top-level nodes.
+    pub fn nodes(&self) -> &[NodeDiff] {
+        &self.nodes
+    }
+
+    /// Returns whether the documents differ.
+    pub fn has_differences(&self) -> bool {
+        self.old_version != self.new_version || self.nodes.iter().any(NodeDiff::has_differences)
+    }
+}
+
+/// Compares two documents.
+///
+/// Children of each node are aligned by the name and the position among the
+/// siblings with the same name, and children of the top-level `Objects` node
+/// are aligned by [`DiffOptions::object_key`] instead.
+/// Floating point numbers are regarded as equal if the difference is within
+/// [`DiffOptions::float_tolerance`] (or both are NaN).
+/// Numbers of different types are different unless
+/// [`DiffOptions::ignore_numeric_types`] is set.
+pub fn diff_documents(
+    old: &Document,
+    new: &Document,
+    options: &DiffOptions,
+) -> io::Result<DocumentDiff> {
+    let differ = Differ { old, new, options };
+    Ok(DocumentDiff {
+        old_version: old.fbx_version(),
+        new_version: new.fbx_version(),
+        nodes: differ.diff_children(old.toplevel_nodes(), new.toplevel_nodes(), false)?,
+    })
+}
+
+/// Writes the differences as text to the given writer.
+///
+/// Each differing node is printed in one line with its path, prefixed by
+/// `+` (added), `-` (removed) or `~` (changed), followed by the attribute
+/// differences.
+/// Descendants of added and removed nodes are not printed.
+pub fn write_diff<W: Write>(diff: &DocumentDiff, out: &mut W) -> io::Result<()> {
+    let (old_version, new_version) = diff.versions();
+    if old_version != new_version {
+        writeln!(
+            out,
+            "~ FBX version: {}.{} -> {}.{}",
+            old_version.major(),
+            old_version.minor(),
+            new_version.major(),
+            new_version.minor()
+        )?;
+    }
+    for node in diff.nodes() {
+        write_node_diff(node, "", out)?;
+    }
+    Ok(())
+}
+
+/// Writes the differences of the given node and its descendants.
+fn write_node_diff<W: Write>(node: &NodeDiff, parent_path: &str, out: &mut W) -> io::Result<()> {
+    if !node.has_differences() {
+        return Ok(());
+    }
+    let path = if parent_path.is_empty() {
+        node.label().to_owned()
+    } else {
+        format!("{}/{}", parent_path, node.label())
+    };
+    let kind = node.kind();
+    if kind != DiffKind::Same {
+        writeln!(out, "{} {}", kind.marker(), path)?;
+    }
+    for attr in node.attributes() {
+        writeln!(out, "    {}", attr)?;
+    }
+    if kind == DiffKind::Added || kind == DiffKind::Removed {
+        return Ok(());
+    }
+    for child in node.children() {
+        write_node_diff(child, &path, out)?;
+    }
+    Ok(())
+}
+
+/// Key to align a node with the siblings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Discriminant {
+    /// Position among the siblings with the same name.
+    Position(usize),
+    /// Object ID.
+    Id(i64),
+    /// Object name.
+    Name(String),
+}
+
+/// Context of the comparison.
+struct Differ<'a> {
+    /// Old document.
+    old: &'a Document,
+    /// New document.
+    new: &'a Document,
+    /// Options.
+    options: &'a DiffOptions,
+}
+
+impl Differ<'_> {
+    /// Compares the children.
+    ///
+    /// The result is in the order of the new children, with removed nodes
+    /// placed near their old positions.
+    fn diff_children(
+        &self,
+        old_children: &[NodeId],
+        new_children: &[NodeId],
+        is_objects: bool,
+    ) -> io::Result<Vec<NodeDiff>> {
+        let old_keys = self.keys(self.old, old_children, is_objects);
+        let new_keys = self.keys(self.new, new_children, is_objects);
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for (name, discriminant) in old_keys.iter().chain(&new_keys) {
+            if let Discriminant::Position(pos) = discriminant {
+                let count = counts.entry(name).or_insert(0);
+                *count = (*count).max(pos + 1);
+            }
+        }
+        let label = |(name, discriminant): &(&str, Discriminant)| match discriminant {
+            Discriminant::Position(_) if counts.get(name) == Some(&1) => name.to_string(),
+            Discriminant::Position(pos) => format!("{}[{}]", name, pos),
+            Discriminant::Id(id) => format!("{}({})", name, id),
+            Discriminant::Name(obj_name) => format!("{}({:?})", name, obj_name),
+        };
+
+        // Keys are unique among the siblings, so each old child matches at
+        // most one new child.
+        let old_index: HashMap<_, usize> = old_keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (key, i))
+            .collect();
+        let matches: Vec<Option<usize>> = new_keys
+            .iter()
+            .map(|key| old_index.get(key).copied())
+            .collect();
+        let mut old_done = vec![false; old_children.len()];
+        for &i in matches.iter().flatten() {
+            old_done[i] = true;
+        }
+
+        let mut diffs = Vec::with_capacity(new_children.len());
+        let mut old_pos = 0;
+        for ((new_key, &new_id), matched) in new_keys.iter().zip(new_children).zip(&matches) {
+            let i = match *matched {
+                Some(i) => i,
+                None => {
+                    diffs.push(self.one_side(label(new_key), new_id, false));
+                    continue;
+                }
+            };
+            // Removed nodes before the matched node.
+            for j in old_pos..i {
+                if !old_done[j] {
+                    old_done[j] = true;
+                    diffs.push(self.one_side(label(&old_keys[j]), old_children[j], true));
+                }
+            }
+            old_pos = old_pos.max(i + 1);
+            let is_objects = self.new.node(new_id).parent().is_none()
+                && self.new.node(new_id).name() == "Objects";
+            diffs.push(self.diff_node(label(new_key), old_children[i], new_id, is_objects)?);
+        }
+        // Removed nodes after the last matched node, or before it if the
+        // children are reordered.
+        for (j, &old_id) in old_children.iter().enumerate() {
+            if !old_done[j] {
+                diffs.push(self.one_side(label(&old_keys[j]), old_id, true));
+            }
+        }
+        Ok(diffs)
+    }
+
+    /// Returns the keys to align the given siblings.
+    fn keys<'d>(
+        &self,
+        document: &'d Document,
+        children: &[NodeId],
+        is_objects: bool,
+    ) -> Vec<(&'d str, Discriminant)> {
+        let object_keys: Vec<_> = children
+            .iter()
+            .map(|&id| {
+                let node = document.node(id);
+                let attrs = node.attributes();
+                match self.options.object_key {
+                    _ if !is_objects => None,
+                    ObjectKey::Id => match attrs.first() {
+                        Some(Attribute::SingleI64(id)) => Some(Discriminant::Id(*id)),
+                        _ => None,
+                    },
+                    ObjectKey::Name => match attrs.get(1) {
+                        Some(Attribute::String(name)) => {
+                            Some(Discriminant::Name(object_name(name)))
+                        }
+                        _ => None,
+                    },
+                }
+                .map(|key| (node.name(), key))
+            })
+            .collect();
+        // Duplicate object keys (such as unnamed objects) fall back to the
+        // positions.
+        let mut counts: HashMap<&(&str, Discriminant), usize> = HashMap::new();
+        for key in object_keys.iter().flatten() {
+            *counts.entry(key).or_insert(0) += 1;
+        }
+
+        let mut positions: HashMap<&str, usize> = HashMap::new();
+        children
+            .iter()
+            .zip(&object_keys)
+            .map(|(&id, object_key)| match object_key {
+                Some(key) if counts.get(key) == Some(&1) => key.clone(),
+                _ => {
+                    let name = document.node(id).name();
+                    let pos = positions.entry(name).or_insert(0);
+                    *pos += 1;
+                    (name, Discriminant::Position(*pos - 1))
+                }
+            })
+            .collect()
+    }
+
+    /// Compares the aligned nodes.
+    fn diff_node(
+        &self,
+        label: String,
+        old_id: NodeId,
+        new_id: NodeId,
+        is_objects: bool,
+    ) -> io::Result<NodeDiff> {
+        let old_attrs = self.old.load_attributes(old_id)?;
+        let new_attrs = self.new.load_attributes(new_id)?;
+        let mut attributes = Vec::new();
+        for index in 0..old_attrs.len().max(new_attrs.len()) {
+            let diff = match (old_attrs.get(index), new_attrs.get(index)) {
+                (Some(old), Some(new)) => self.diff_attribute(index, old, new),
+                (Some(old), None) => Some(AttributeDiff::Removed {
+                    index,
+                    value: typed_value_string(old),
+                }),
+                (None, Some(new)) => Some(AttributeDiff::Added {
+                    index,
+                    value: typed_value_string(new),
+                }),
+                (None, None) => None,
+            };
+            attributes.extend(diff);
+        }
+
+        let children = self.diff_children(
+            self.old.node(old_id).children(),
+            self.new.node(new_id).children(),
+            is_objects,
+        )?;
+        let has_differences =
+            !attributes.is_empty() || children.iter().any(NodeDiff::has_differences);
+        Ok(NodeDiff {
+            label,
+            old: Some(old_id),
+            new: Some(new_id),
+            attributes,
+            children,
+            has_differences,
+        })
+    }
+
+    /// Returns the diff of the node which exists only in one document.
+    ///
+    /// If `is_old` is true, the node is removed, otherwise added.
+    fn one_side(&self, label: String, node_id: NodeId, is_old: bool) -> NodeDiff {
+        let document = if is_old { self.old } else { self.new };
+        let children = document
+            .node(node_id)
+            .children()
+            .iter()
+            .map(|&child| self.one_side(document.node(child).name().to_owned(), child, is_old))
+            .collect();
+        let (old, new) = if is_old {
+            (Some(node_id), None)
+        } else {
+            (None, Some(node_id))
+        };
+        NodeDiff {
+            label,
+            old,
+            new,
+            attributes: Vec::new(),
+            children,
+            has_differences: true,
+        }
+    }
+
+    /// Compares the attributes.
+    fn diff_attribute(
+        &self,
+        index: usize,
+        old: &Attribute,
+        new: &Attribute,
+    ) -> Option<AttributeDiff> {
+        let tolerance = self.options.float_tolerance;
+        let float_eq =
+            |a: f64, b: f64| a == b || (a.is_nan() && b.is_nan()) || (a - b).abs() <= tolerance;
+        let elements = match (old, new) {
+            (Attribute::SingleBool(a), Attribute::SingleBool(b)) if a == b => return None,
+            (Attribute::SingleI16(a), Attribute::SingleI16(b)) if a == b => return None,
+            (Attribute::SingleI32(a), Attribute::SingleI32(b)) if a == b => return None,
+            (Attribute::SingleI64(a), Attribute::SingleI64(b)) if a == b => return None,
+            (Attribute::SingleF32(a), Attribute::SingleF32(b))
+                if float_eq(f64::from(*a), f64::from(*b)) =>
+            {
+                return None
+            }
+            (Attribute::SingleF64(a), Attribute::SingleF64(b)) if float_eq(*a, *b) => return None,
+            (Attribute::String(a), Attribute::String(b)) if a == b => return None,
+            (Attribute::ArrayBool(a), Attribute::ArrayBool(b)) => {
+                different_elements(a, b, |a, b| a == b)
+            }
+            (Attribute::ArrayI32(a), Attribute::ArrayI32(b)) => {
+                different_elements(a, b, |a, b| a == b)
+            }
+            (Attribute::ArrayI64(a), Attribute::ArrayI64(b)) => {
+                different_elements(a, b, |a, b| a == b)
+            }
+            (Attribute::ArrayF32(a), Attribute::ArrayF32(b)) => {
+                different_elements(a, b, |&a, &b| float_eq(f64::from(a), f64::from(b)))
+            }
+            (Attribute::ArrayF64(a), Attribute::ArrayF64(b)) => {
+                different_elements(a, b, |&a, &b| float_eq(a, b))
+            }
+            (Attribute::Binary(a), Attribute::Binary(b)) => different_elements(a, b, |a, b| a == b),
+            _ if self.options.ignore_numeric_types
+                && old.array_len().is_some() == new.array_len().is_some() =>
+            {
+                match (numbers(old), numbers(new)) {
+                    (Some(a), Some(b)) => {
+                        let elements =
+                            different_elements(&a, &b, |&a, &b| Number::value_eq(a, b, float_eq));
+                        // Different scalars are reported as changed values.
+                        match old.array_len() {
+                            Some(_) => elements,
+                            None => elements.filter(|&(count, _)| count == 0),
+                        }
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        match elements {
+            Some((0, _)) => None,
+            Some((count, first)) => Some(AttributeDiff::ElementsChanged {
+                index,
+                len: old.array_len().unwrap_or(0),
+                count,
+                first,
+                old: old.element_string(first).unwrap_or_default(),
+                new: new.element_string(first).unwrap_or_default(),
+            }),
+            None => Some(AttributeDiff::Changed {
+                index,
+                old: typed_value_string(old),
+                new: typed_value_string(new),
+            }),
+        }
+    }
+}
+
+/// Number of any type, to compare by value.
+#[derive(Debug, Clone, Copy)]
+enum Number {
+    /// Boolean or integer.
+    Int(i64),
+    /// `f32`.
+    F32(f32),
+    /// `f64`.
+    F64(f64),
+}
+
+impl Number {
+    /// Returns the value as `f64`.
+    fn to_f64(self) -> f64 {
+        match self {
+            Number::Int(v) => v as f64,
+            Number::F32(v) => f64::from(v),
+            Number::F64(v) => v,
+        }
+    }
+
+    /// Returns whether the numbers have the same value.
+    ///
+    /// `f64` is rounded to `f32` before compared with `f32`, since `f32`
+    /// values are written in the shortest representation in FBX ASCII.
+    fn value_eq<F>(a: Self, b: Self, float_eq: F) -> bool
+    where
+        F: Fn(f64, f64) -> bool,
+    {
+        match (a, b) {
+            (Number::Int(a), Number::Int(b)) => a == b,
+            (Number::F32(a), Number::F64(b)) | (Number::F64(b), Number::F32(a)) => {
+                float_eq(f64::from(a), f64::from(b as f32))
+            }
+            (a, b) => float_eq(a.to_f64(), b.to_f64()),
+        }
+    }
+}
+
+/// Returns the numbers in the scalar or array attribute, or `None` for
+/// strings and binaries.
+fn numbers(attr: &Attribute) -> Option<Vec<Number>> {
+    let numbers = match attr {
+        Attribute::SingleBool(v) => vec![Number::Int(i64::from(*v))],
+        Attribute::SingleI16(v) => vec![Number::Int(i64::from(*v))],
+        Attribute::SingleI32(v) => vec![Number::Int(i64::from(*v))],
+        Attribute::SingleI64(v) => vec![Number::Int(*v)],
+        Attribute::SingleF32(v) => vec![Number::F32(*v)],
+        Attribute::SingleF64(v) => vec![Number::F64(*v)],
+        Attribute::ArrayBool(arr) => arr.iter().map(|&v| Number::Int(i64::from(v))).collect(),
+        Attribute::ArrayI32(arr) => arr.iter().map(|&v| Number::Int(i64::from(v))).collect(),
+        Attribute::ArrayI64(arr) => arr.iter().map(|&v| Number::Int(v)).collect(),
+        Attribute::ArrayF32(arr) => arr.iter().map(|&v| Number::F32(v)).collect(),
+        Attribute::ArrayF64(arr) => arr.iter().map(|&v| Number::F64(v)).collect(),
+        Attribute::String(_) | Attribute::Binary(_) => return None,
+    };
+    Some(numbers)
+}
+
+/// Returns the number of different elements and the index of the first one,
+/// or `None` if the lengths differ.
+fn different_elements<T, F>(old: &[T], new: &[T], eq: F) -> Option<(usize, usize)>
+where
+    F: Fn(&T, &T) -> bool,
+{
+    if old.len() != new.len() {
+        return None;
+    }
+    let mut different = old
+        .iter()
+        .zip(new)
+        .enumerate()
+        .filter(|(_, (a, b))| !eq(a, b))
+        .map(|(i, _)| i);
+    let first = match different.next() {
+        Some(v) => v,
+        None => return Some((0, 0)),
+    };
+    Some((different.count() + 1, first))
+}
+
+/// Returns the type and the value, such as `(i64) 42`.
+fn typed_value_string(attr: &Attribute) -> String {
+    format!(
+        "({}) {}",
+        attr.type_string(),
+        attr.value_string_oneline(Some(MAX_VALUE_ELEMENTS))
+    )
+}
+
+/// Returns the object name in `Class::Name` form.
+fn object_name(name: &str) -> String {
+    match name.find("\u{0}\u{1}") {
+        Some(sep) => format!("{}::{}", &name[(sep + 2)..], &name[..sep]),
+        None => name.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a document with the given objects (ID, name, and a float).
+    fn objects(objects: &[(i64, &str, f64)]) -> Document {
+        let mut doc = Document::default();
+        doc.push_node(None, "Header".to_owned(), vec![], 0);
+        let parent = doc.push_node(None, "Objects".to_owned(), vec![], 0);
+        for &(id, name, value) in objects {
+            let model = doc.push_node(
+                Some(parent),
+                "Model".to_owned(),
+                vec![
+                    Attribute::SingleI64(id),
+                    Attribute::String(format!("{}\u{0}\u{1}Model", name)),
+                ],
+                0,
+            );
+            doc.push_node(
+                Some(model),
+                "Value".to_owned(),
+                vec![
+                    Attribute::SingleF64(value),
+                    Attribute::ArrayF32(vec![value as f32; 3]),
+                ],
+                0,
+            );
+        }
+        doc
+    }
+
+    /// Returns the text output of the diff.
+    fn diff_text(old: &Document, new: &Document, options: &DiffOptions) -> String {
+        let diff = diff_documents(old, new, options).unwrap();
+        let mut out = Vec::new();
+        write_diff(&diff, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn same_documents() {
+        let doc = objects(&[(1, "A", 0.0), (2, "B", 1.0)]);
+        let diff = diff_documents(&doc, &doc, &DiffOptions::default()).unwrap();
+        assert!(!diff.has_differences());
+    }
+
+    #[test]
+    fn reordered_siblings() {
+        let old = objects(&[(1, "A", 0.0), (2, "B", 1.0), (3, "C", 2.0)]);
+        let new = objects(&[(3, "C", 2.0), (2, "B", 1.0), (1, "A", 0.0)]);
+        for &object_key in &[ObjectKey::Id, ObjectKey::Name] {
+            let options = DiffOptions {
+                object_key,
+                ..DiffOptions::default()
+            };
+            let diff = diff_documents(&old, &new, &options).unwrap();
+            assert!(!diff.has_differences(), "{:?}", object_key);
+        }
+    }
+
+    #[test]
+    fn reordered_siblings_with_removal() {
+        let old = objects(&[(1, "A", 0.0), (2, "B", 1.0), (3, "C", 2.0)]);
+        let new = objects(&[(3, "C", 2.0), (1, "A", 0.0), (4, "D", 3.0)]);
+        let text = diff_text(&old, &new, &DiffOptions::default());
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(
+            lines,
+            ["- Objects/Model(2)", "+ Objects/Model(4)"],
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn float_tolerance() {
+        let old = objects(&[(1, "A", 1.0)]);
+        let new = objects(&[(1, "A", 1.0005)]);
+        let diff = diff_documents(&old, &new, &DiffOptions::default()).unwrap();
+        assert!(diff.has_differences());
+        let text = diff_text(&old, &new, &DiffOptions::default());
+        assert!(text.contains("~ Objects/Model(1)/Value"), "{}", text);
+
+        let options = DiffOptions {
+            float_tolerance: 1.0e-3,
+            ..DiffOptions::default()
+        };
+        let diff = diff_documents(&old, &new, &options).unwrap();
+        assert!(!diff.has_differences());
+
+        let options = DiffOptions {
+            float_tolerance: 1.0e-4,
+            ..DiffOptions::default()
+        };
+        let diff = diff_documents(&old, &new, &options).unwrap();
+        assert!(diff.has_differences());
+    }
+
+    #[test]
+    fn changed_attributes() {
+        let old = objects(&[(1, "A", 1.0)]);
+        let new = objects(&[(1, "B", 1.0)]);
+        let diff = diff_documents(&old, &new, &DiffOptions::default()).unwrap();
+        let objects = &diff.nodes()[1];
+        let model = &objects.children()[0];
+        assert_eq!(model.kind(), DiffKind::Changed);
+        assert_eq!(model.attributes().len(), 1);
+        assert_eq!(model.attributes()[0].index(), 1);
+
+        // Matched by name, the objects are different.
+        let options = DiffOptions {
+            object_key: ObjectKey::Name,
+            ..DiffOptions::default()
+        };
+        let text = diff_text(&old, &new, &options);
+        assert!(text.contains("+ Objects/Model(\"Model::B\")"), "{}", text);
+        assert!(text.contains("- Objects/Model(\"Model::A\")"), "{}", text);
+    }
+
+    #[test]
+    fn ignore_numeric_types() {
+        let node = |attrs: Vec<Attribute>| {
+            let mut doc = Document::default();
+            doc.push_node(None, "Node".to_owned(), attrs, 0);
+            doc
+        };
+        let old = node(vec![
+            Attribute::SingleI16(-2),
+            Attribute::SingleF32(0.1),
+            Attribute::ArrayBool(vec![true, false]),
+            Attribute::ArrayF32(vec![0.1, 3.0]),
+            Attribute::SingleI64(5),
+        ]);
+        let new = node(vec![
+            Attribute::SingleI32(-2),
+            Attribute::SingleF64(0.1),
+            Attribute::ArrayI32(vec![1, 0]),
+            Attribute::ArrayF64(vec![0.1, 3.0]),
+            Attribute::SingleF64(5.0),
+        ]);
+        let options = DiffOptions {
+            ignore_numeric_types: true,
+            ..DiffOptions::default()
+        };
+        let diff = diff_documents(&old, &new, &DiffOptions::default()).unwrap();
+        assert_eq!(diff.nodes()[0].attributes().len(), 5);
+        let diff = diff_documents(&old, &new, &options).unwrap();
+        assert!(!diff.has_differences());
+
+        // Different values, scalars and arrays, and strings are still
+        // different.
+        let new = node(vec![
+            Attribute::SingleI32(2),
+            Attribute::ArrayF32(vec![0.1]),
+            Attribute::ArrayI32(vec![1, 1]),
+            Attribute::ArrayF64(vec![0.1, 3.0]),
+            Attribute::String("5".to_owned()),
+        ]);
+        let text = diff_text(&old, &new, &options);
+        let lines: Vec<_> = text.lines().map(str::trim).collect();
+        assert_eq!(lines.len(), 5, "{}", text);
+        assert!(lines[0].starts_with("~ Node"), "{}", text);
+        assert!(
+            lines[3].starts_with("#2: 1 of 2 elements differ"),
+            "{}",
+            text
+        );
+        assert!(!text.contains("#3:"), "{}", text);
+    }
+}
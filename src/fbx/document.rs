@@ -0,0 +1,566 @@
+//! In-memory FBX document.
+
+use std::io;
+
+use fbxcel::low::{v7400::FbxFooter, FbxVersion};
+
+use crate::fbx::{lazy::LazySource, ArrayStorage, Attribute, AttributeLocation, LoadedAttributes};
+
+/// Node ID.
+///
+/// This is an index of the node in the document, and nodes are indexed in
+/// order of appearance in the file (i.e. preorder).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// Creates a new `NodeId` from the raw index.
+    pub fn new(index: usize) -> Self {
+        NodeId(index)
+    }
+
+    /// Returns the raw index.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// FBX node.
+#[derive(Debug, Clone)]
+pub struct Node {
+    /// Node name.
+    name: String,
+    /// Node attributes.
+    attributes: Vec<Attribute>,
+    /// Parent node (`None` for top-level nodes).
+    parent: Option<NodeId>,
+    /// Child nodes.
+    children: Vec<NodeId>,
+    /// Beginning byte offset of the node.
+    start_offset: u64,
+    /// End byte offset of the node.
+    ///
+    /// "End offset" means a next byte of the last byte of the node.
+    end_offset: u64,
+    /// Beginning and end byte offsets of the attributes (FBX binary only).
+    attribute_offsets: Option<(u64, u64)>,
+    /// On-disk storage of the attributes (FBX binary only).
+    ///
+    /// This is empty if unknown or the node has no array attributes.
+    array_storages: Vec<Option<ArrayStorage>>,
+    /// Locations of the attributes (lazily loaded FBX binary only).
+    ///
+    /// This is empty if the node has no deferred attributes.
+    attribute_locations: Vec<AttributeLocation>,
+}
+
+impl Node {
+    /// Returns the node name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the node attributes.
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+
+    /// Returns the parent node.
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    /// Returns the child nodes.
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    /// Returns the beginning byte offset of the node.
+    pub fn start_offset(&self) -> u64 {
+        self.start_offset
+    }
+
+    /// Returns the end byte offset of the node.
+    pub fn end_offset(&self) -> u64 {
+        self.end_offset
+    }
+
+    /// Returns whether the byte offsets of the node are known.
+    ///
+    /// Offsets are unknown for nodes which are not in the loaded file, such
+    /// as duplicated nodes.
+    pub fn has_offsets(&self) -> bool {
+        self.end_offset > self.start_offset
+    }
+
+    /// Returns the beginning and end byte offsets of the attributes.
+    ///
+    /// The node record header is before the attributes, and the child nodes
+    /// (and the node end marker) are after them.
+    /// This is `None` for documents not loaded from FBX binary.
+    pub fn attribute_offsets(&self) -> Option<(u64, u64)> {
+        self.attribute_offsets
+    }
+
+    /// Returns the on-disk storage of the array attributes.
+    ///
+    /// Elements for non-array attributes are `None`, and the slice is empty
+    /// if the storage is unknown.
+    pub fn array_storages(&self) -> &[Option<ArrayStorage>] {
+        &self.array_storages
+    }
+
+    /// Returns the on-disk storage of the given array attribute, if known.
+    pub fn array_storage(&self, index: usize) -> Option<&ArrayStorage> {
+        self.array_storages.get(index).and_then(Option::as_ref)
+    }
+
+    /// Returns the locations of the attributes in the file.
+    ///
+    /// This is empty if the node has no deferred attributes.
+    pub fn attribute_locations(&self) -> &[AttributeLocation] {
+        &self.attribute_locations
+    }
+}
+
+/// Detached subtree of a document.
+///
+/// Nodes are in preorder, and their node IDs are relative to the root.
+#[derive(Debug, Clone)]
+pub(crate) struct Subtree {
+    /// Nodes.
+    nodes: Vec<Node>,
+}
+
+impl Subtree {
+    /// Clears the byte offsets of the nodes, since they are not in the
+    /// loaded file.
+    ///
+    /// Attribute locations are kept to load deferred attributes.
+    pub(crate) fn clear_offsets(&mut self) {
+        for node in &mut self.nodes {
+            node.start_offset = 0;
+            node.end_offset = 0;
+            node.attribute_offsets = None;
+        }
+    }
+}
+
+/// FBX document.
+///
+/// This is a node arena independent of GUI.
+#[derive(Debug, Clone)]
+pub struct Document {
+    /// FBX version.
+    fbx_version: FbxVersion,
+    /// Nodes.
+    nodes: Vec<Node>,
+    /// Top-level nodes.
+    toplevel: Vec<NodeId>,
+    /// FBX footer (if successfully loaded).
+    footer: Option<FbxFooter>,
+    /// Source of deferred attributes (if loaded lazily).
+    lazy: Option<Box<LazySource>>,
+}
+
+impl Document {
+    /// Creates a new empty document.
+    pub fn new(fbx_version: FbxVersion) -> Self {
+        Self {
+            fbx_version,
+            nodes: Vec::new(),
+            toplevel: Vec::new(),
+            footer: None,
+            lazy: None,
+        }
+    }
+
+    /// Returns the FBX version.
+    pub fn fbx_version(&self) -> FbxVersion {
+        self.fbx_version
+    }
+
+    /// Returns the FBX footer.
+    pub fn footer(&self) -> Option<&FbxFooter> {
+        self.footer.as_ref()
+    }
+
+    /// Sets the FBX footer.
+    pub fn set_footer(&mut self, footer: FbxFooter) {
+        self.footer = Some(footer);
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns whether the document has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the node with the given ID.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node does not exist.
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    /// Returns the node with the given ID, if exists.
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id.0)
+    }
+
+    /// Returns the top-level nodes.
+    pub fn toplevel_nodes(&self) -> &[NodeId] {
+        &self.toplevel
+    }
+
+    /// Returns the child nodes of the given node, or top-level nodes for
+    /// `None`.
+    pub fn children(&self, parent: Option<NodeId>) -> &[NodeId] {
+        match parent {
+            Some(id) => &self.nodes[id.0].children,
+            None => &self.toplevel,
+        }
+    }
+
+    /// Returns an iterator of all node IDs in preorder.
+    pub fn node_ids(&self) -> impl DoubleEndedIterator<Item = NodeId> + ExactSizeIterator {
+        (0..self.nodes.len()).map(NodeId)
+    }
+
+    /// Returns an iterator of the given node and its ancestors, from the
+    /// node itself to the top-level node.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(Some(id), move |&id| self.nodes[id.0].parent)
+    }
+
+    /// Returns the slash-separated path of the node from the top-level
+    /// (such as `Objects/Geometry/Vertices`).
+    pub fn path(&self, id: NodeId) -> String {
+        let mut names: Vec<&str> = self.ancestors(id).map(|id| self.node(id).name()).collect();
+        names.reverse();
+        names.join("/")
+    }
+
+    /// Returns the indices of the node and its ancestors among their
+    /// siblings, from the top-level.
+    pub fn index_path(&self, id: NodeId) -> Vec<usize> {
+        let mut indices: Vec<usize> = self
+            .ancestors(id)
+            .map(|id| {
+                self.children(self.nodes[id.0].parent)
+                    .iter()
+                    .position(|&sibling| sibling == id)
+                    .expect("Should never fail: a node should be a child of its parent")
+            })
+            .collect();
+        indices.reverse();
+        indices
+    }
+
+    /// Appends a new node as the last child of the given parent.
+    ///
+    /// The end offset of the node is initialized to the start offset, so it
+    /// should be set by [`set_end_offset`][`Self::set_end_offset`] later.
+    pub fn push_node(
+        &mut self,
+        parent: Option<NodeId>,
+        name: String,
+        attributes: Vec<Attribute>,
+        start_offset: u64,
+    ) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            name,
+            attributes,
+            parent,
+            children: Vec::new(),
+            start_offset,
+            end_offset: start_offset,
+            attribute_offsets: None,
+            array_storages: Vec::new(),
+            attribute_locations: Vec::new(),
+        });
+        match parent {
+            Some(parent) => self.nodes[parent.0].children.push(id),
+            None => self.toplevel.push(id),
+        }
+        id
+    }
+
+    /// Sets the end offset of the given node.
+    pub fn set_end_offset(&mut self, id: NodeId, end_offset: u64) {
+        self.nodes[id.0].end_offset = end_offset;
+    }
+
+    /// Sets the beginning and end offsets of the attributes of the given
+    /// node.
+    pub fn set_attribute_offsets(&mut self, id: NodeId, start: u64, end: u64) {
+        self.nodes[id.0].attribute_offsets = Some((start, end));
+    }
+
+    /// Sets the on-disk storage of the attributes of the given node.
+    pub fn set_array_storages(&mut self, id: NodeId, storages: Vec<Option<ArrayStorage>>) {
+        self.nodes[id.0].array_storages = storages;
+    }
+
+    /// Sets the locations of the attributes of the given node.
+    pub fn set_attribute_locations(&mut self, id: NodeId, locations: Vec<AttributeLocation>) {
+        self.nodes[id.0].attribute_locations = locations;
+    }
+
+    /// Returns whether arrays and binaries are loaded lazily.
+    ///
+    /// If so, [`Node::attributes`] has empty values for them, and
+    /// [`load_attributes`][`Self::load_attributes`] should be used to get the
+    /// actual values.
+    pub fn is_lazy(&self) -> bool {
+        self.lazy.is_some()
+    }
+
+    /// Makes arrays and binaries to be decoded on demand from the given
+    /// source.
+    pub(crate) fn set_lazy_source(&mut self, source: LazySource) {
+        self.lazy = Some(Box::new(source));
+    }
+
+    /// Returns the attributes of the given node, decoding deferred values if
+    /// necessary.
+    pub fn load_attributes(&self, id: NodeId) -> io::Result<LoadedAttributes<'_>> {
+        let node = &self.nodes[id.0];
+        match &self.lazy {
+            Some(lazy) if !node.attribute_locations.is_empty() => lazy
+                .load(id, &node.attributes, &node.attribute_locations)
+                .map(LoadedAttributes::Decoded),
+            _ => Ok(LoadedAttributes::Borrowed(&node.attributes)),
+        }
+    }
+
+    /// Replaces the attribute of the given node, and returns the old value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node or the attribute does not exist.
+    pub fn set_attribute(&mut self, id: NodeId, index: usize, value: Attribute) -> Attribute {
+        self.clear_lazy_cache();
+        std::mem::replace(&mut self.nodes[id.0].attributes[index], value)
+    }
+
+    /// Renames the given node, and returns the old name.
+    pub fn rename_node(&mut self, id: NodeId, name: String) -> String {
+        std::mem::replace(&mut self.nodes[id.0].name, name)
+    }
+
+    /// Returns the parent of the given node and the position among the
+    /// siblings.
+    pub fn position(&self, id: NodeId) -> (Option<NodeId>, usize) {
+        let parent = self.nodes[id.0].parent;
+        let position = self
+            .children(parent)
+            .iter()
+            .position(|&sibling| sibling == id)
+            .expect("Should never fail: a node should be a child of its parent");
+        (parent, position)
+    }
+
+    /// Returns the number of nodes in the subtree of the given node.
+    ///
+    /// Since nodes are in preorder, the subtree is the nodes from `id` to
+    /// `id + subtree_len(id)`.
+    fn subtree_len(&self, id: NodeId) -> usize {
+        1 + self.nodes[id.0]
+            .children
+            .iter()
+            .map(|&child| self.subtree_len(child))
+            .sum::<usize>()
+    }
+
+    /// Returns a copy of the given node and its descendants.
+    pub(crate) fn clone_subtree(&self, id: NodeId) -> Subtree {
+        let range = id.0..(id.0 + self.subtree_len(id));
+        let nodes = self.nodes[range]
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                let mut node = node.clone();
+                node.parent = node.parent.filter(|_| i != 0).map(|p| NodeId(p.0 - id.0));
+                for child in &mut node.children {
+                    child.0 -= id.0;
+                }
+                node
+            })
+            .collect();
+        Subtree { nodes }
+    }
+
+    /// Removes the given node and its descendants, and returns them with the
+    /// parent and the position among the siblings.
+    ///
+    /// Node IDs after the subtree are shifted.
+    pub(crate) fn remove_subtree(&mut self, id: NodeId) -> (Option<NodeId>, usize, Subtree) {
+        let (parent, position) = self.position(id);
+        let subtree = self.clone_subtree(id);
+        let end = id.0 + subtree.nodes.len();
+        self.nodes.drain(id.0..end);
+        self.siblings_mut(parent).remove(position);
+        let len = subtree.nodes.len();
+        self.remap_ids(|n| if n.0 >= end { NodeId(n.0 - len) } else { n });
+        self.clear_lazy_cache();
+        (parent, position, subtree)
+    }
+
+    /// Inserts the subtree as a child of `parent` at the given position, and
+    /// returns the ID of the root.
+    ///
+    /// Node IDs after the inserted subtree are shifted.
+    pub(crate) fn insert_subtree(
+        &mut self,
+        parent: Option<NodeId>,
+        position: usize,
+        subtree: Subtree,
+    ) -> NodeId {
+        let at = match self.children(parent).get(position) {
+            Some(&sibling) => sibling.0,
+            None => parent.map_or(self.nodes.len(), |p| p.0 + self.subtree_len(p)),
+        };
+        let len = subtree.nodes.len();
+        self.remap_ids(|n| if n.0 >= at { NodeId(n.0 + len) } else { n });
+        let nodes = subtree.nodes.into_iter().enumerate().map(|(i, mut node)| {
+            node.parent = if i == 0 {
+                parent
+            } else {
+                node.parent.map(|p| NodeId(p.0 + at))
+            };
+            for child in &mut node.children {
+                child.0 += at;
+            }
+            node
+        });
+        self.nodes.splice(at..at, nodes);
+        self.siblings_mut(parent).insert(position, NodeId(at));
+        self.clear_lazy_cache();
+        NodeId(at)
+    }
+
+    /// Returns the mutable child nodes of the given node, or top-level nodes
+    /// for `None`.
+    fn siblings_mut(&mut self, parent: Option<NodeId>) -> &mut Vec<NodeId> {
+        match parent {
+            Some(id) => &mut self.nodes[id.0].children,
+            None => &mut self.toplevel,
+        }
+    }
+
+    /// Replaces all node IDs in the node relations by `f`.
+    fn remap_ids<F: Fn(NodeId) -> NodeId>(&mut self, f: F) {
+        for node in &mut self.nodes {
+            node.parent = node.parent.map(&f);
+            for child in &mut node.children {
+                *child = f(*child);
+            }
+        }
+        for id in &mut self.toplevel {
+            *id = f(*id);
+        }
+    }
+
+    /// Discards decoded attributes cached for the node IDs.
+    fn clear_lazy_cache(&self) {
+        if let Some(lazy) = &self.lazy {
+            lazy.clear_cache();
+        }
+    }
+
+    /// Returns the innermost node containing the given byte offset.
+    ///
+    /// Nodes without offsets (see [`Node::has_offsets`]) are skipped.
+    pub fn node_at_offset(&self, offset: u64) -> Option<NodeId> {
+        let mut found = None;
+        let mut candidates = &self.toplevel;
+        // Siblings are not searched by bisection, since edited documents may
+        // have nodes without offsets among them.
+        loop {
+            let id = candidates.iter().cloned().find(|&id| {
+                let node = &self.nodes[id.0];
+                node.has_offsets() && node.start_offset <= offset && offset < node.end_offset
+            });
+            let id = match id {
+                Some(id) => id,
+                None => return found,
+            };
+            found = Some(id);
+            candidates = &self.nodes[id.0].children;
+        }
+    }
+}
+
+impl Default for Document {
+    /// Creates an empty FBX 7.4 document.
+    fn default() -> Self {
+        Self::new(FbxVersion::V7_4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fbx::{Edit, EditHistory};
+
+    /// Appends a node spanning the given offsets.
+    fn push(doc: &mut Document, parent: Option<NodeId>, name: &str, range: (u64, u64)) -> NodeId {
+        let id = doc.push_node(parent, name.to_owned(), Vec::new(), range.0);
+        doc.set_end_offset(id, range.1);
+        id
+    }
+
+    /// Creates `A { B, C }, D` at consecutive offsets.
+    fn sample() -> (Document, [NodeId; 4]) {
+        let mut doc = Document::default();
+        let a = push(&mut doc, None, "A", (27, 100));
+        let b = push(&mut doc, Some(a), "B", (40, 60));
+        let c = push(&mut doc, Some(a), "C", (60, 87));
+        let d = push(&mut doc, None, "D", (100, 150));
+        (doc, [a, b, c, d])
+    }
+
+    #[test]
+    fn node_at_offset() {
+        let (doc, [a, b, c, d]) = sample();
+        assert_eq!(doc.node_at_offset(0), None);
+        assert_eq!(doc.node_at_offset(27), Some(a));
+        assert_eq!(doc.node_at_offset(45), Some(b));
+        assert_eq!(doc.node_at_offset(60), Some(c));
+        // Between the last child and the end of the parent.
+        assert_eq!(doc.node_at_offset(90), Some(a));
+        assert_eq!(doc.node_at_offset(149), Some(d));
+        assert_eq!(doc.node_at_offset(150), None);
+    }
+
+    #[test]
+    fn duplicated_nodes_have_no_offsets() {
+        let (mut doc, [a, ..]) = sample();
+        let mut history = EditHistory::new();
+        let copy = history
+            .apply(&mut doc, Edit::Duplicate { node: a })
+            .unwrap()
+            .unwrap();
+        assert!(!doc.node(copy).has_offsets());
+        assert!(doc
+            .node(copy)
+            .children()
+            .iter()
+            .all(|&child| !doc.node(child).has_offsets()));
+        // The copy is placed before `D`, and offsets still find the originals.
+        let names: Vec<_> = [27, 45, 60, 90, 149]
+            .iter()
+            .map(|&offset| doc.node(doc.node_at_offset(offset).unwrap()).name())
+            .collect();
+        assert_eq!(names, ["A", "B", "C", "A", "D"]);
+        assert_ne!(doc.node_at_offset(27), Some(copy));
+    }
+}
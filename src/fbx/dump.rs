@@ -0,0 +1,196 @@
+//! Text dump of FBX node tree.
+
+use std::io::{self, Write};
+
+use crate::fbx::{Document, NodeId};
+
+/// Indent for each depth.
+const INDENT: &str = "  ";
+
+/// Options for [`dump_document`].
+#[derive(Debug, Default, Clone)]
+pub struct DumpOptions {
+    /// Maximum depth of nodes to be dumped.
+    ///
+    /// Top-level nodes are depth 0.
+    /// `None` means unlimited.
+    pub max_depth: Option<usize>,
+    /// Maximum number of array elements to be printed for each attribute.
+    ///
+    /// `None` means unlimited.
+    pub max_array_len: Option<usize>,
+}
+
+/// Writes the indented node tree with attributes to the given writer.
+///
+/// Each node is printed in one line, as the node name followed by its
+/// attributes.
+/// Deferred attributes of lazily loaded documents are decoded here.
+pub fn dump_document<W: Write>(
+    document: &Document,
+    out: &mut W,
+    options: &DumpOptions,
+) -> io::Result<()> {
+    let version = document.fbx_version();
+    writeln!(out, "; FBX {}.{}", version.major(), version.minor())?;
+    for &node_id in document.toplevel_nodes() {
+        dump_node(document, node_id, 0, out, options)?;
+    }
+    Ok(())
+}
+
+/// Writes the given node and its descendants.
+fn dump_node<W: Write>(
+    document: &Document,
+    node_id: NodeId,
+    depth: usize,
+    out: &mut W,
+    options: &DumpOptions,
+) -> io::Result<()> {
+    let node = document.node(node_id);
+    let attrs = document.load_attributes(node_id)?;
+    let indent = INDENT.repeat(depth);
+    write!(out, "{}{}:", indent, node.name())?;
+    for (i, attr) in attrs.iter().enumerate() {
+        let sep = if i == 0 { " " } else { ", " };
+        write!(
+            out,
+            "{}({}) {}",
+            sep,
+            attr.type_string(),
+            attr.value_string_oneline(options.max_array_len)
+        )?;
+    }
+    writeln!(out)?;
+
+    let children = node.children();
+    if children.is_empty() {
+        return Ok(());
+    }
+    if options.max_depth.map_or(false, |max| depth >= max) {
+        writeln!(
+            out,
+            "{}{}... ({} child nodes omitted)",
+            indent,
+            INDENT,
+            children.len()
+        )?;
+        return Ok(());
+    }
+    for &child in children {
+        dump_node(document, child, depth + 1, out, options)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fbxcel::low::FbxVersion;
+
+    use crate::fbx::{load_file_lazy, save_binary_file, Attribute, WriteOptions};
+
+    /// Creates `Objects/Geometry/Vertices` and `Objects/Model`.
+    fn sample() -> Document {
+        let mut doc = Document::new(FbxVersion::V7_4);
+        let objects = doc.push_node(None, "Objects".to_owned(), vec![], 0);
+        let geometry = doc.push_node(
+            Some(objects),
+            "Geometry".to_owned(),
+            vec![
+                Attribute::SingleI64(1234),
+                Attribute::String("Cube\u{0}\u{1}Geometry".to_owned()),
+            ],
+            0,
+        );
+        doc.push_node(
+            Some(geometry),
+            "Vertices".to_owned(),
+            vec![Attribute::ArrayF64(vec![0.5, 1.0, -2.0, 3.0])],
+            0,
+        );
+        doc.push_node(
+            Some(objects),
+            "Model".to_owned(),
+            vec![Attribute::SingleBool(true)],
+            0,
+        );
+        doc
+    }
+
+    /// Returns the dump of the document.
+    fn dump(doc: &Document, options: &DumpOptions) -> String {
+        let mut out = Vec::new();
+        dump_document(doc, &mut out, options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn format() {
+        let expected = "\
+; FBX 7.4
+Objects:
+  Geometry: (i64) 1234, (String) \"Cube\\0\\u{1}Geometry\"
+    Vertices: ([f64]) [0.5, 1, -2, 3]
+  Model: (bool) true
+";
+        assert_eq!(dump(&sample(), &DumpOptions::default()), expected);
+    }
+
+    #[test]
+    fn max_array_len() {
+        let options = DumpOptions {
+            max_array_len: Some(2),
+            ..Default::default()
+        };
+        assert!(dump(&sample(), &options)
+            .contains("    Vertices: ([f64]) [0.5, 1, ...] (4 elements)\n"));
+
+        let options = DumpOptions {
+            max_array_len: Some(0),
+            ..Default::default()
+        };
+        assert!(dump(&sample(), &options).contains("    Vertices: ([f64]) [...] (4 elements)\n"));
+
+        // Arrays within the limit are not truncated.
+        let options = DumpOptions {
+            max_array_len: Some(4),
+            ..Default::default()
+        };
+        assert!(dump(&sample(), &options).contains("    Vertices: ([f64]) [0.5, 1, -2, 3]\n"));
+    }
+
+    #[test]
+    fn max_depth() {
+        let options = DumpOptions {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let expected = "\
+; FBX 7.4
+Objects:
+  Geometry: (i64) 1234, (String) \"Cube\\0\\u{1}Geometry\"
+    ... (1 child nodes omitted)
+  Model: (bool) true
+";
+        assert_eq!(dump(&sample(), &options), expected);
+    }
+
+    #[test]
+    fn lazy_document() {
+        let doc = sample();
+        let path =
+            std::env::temp_dir().join(format!("fbx-tree-view-dump-{}.fbx", std::process::id()));
+        save_binary_file(&doc, &path, &WriteOptions::default()).unwrap();
+        let lazy = load_file_lazy(&path, 0, |_| true)
+            .document
+            .expect("document should be loaded");
+        assert!(lazy.is_lazy());
+
+        // Deferred arrays should be dumped as well.
+        let options = DumpOptions::default();
+        assert_eq!(dump(&lazy, &options), dump(&doc, &options));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,563 @@
+//! Editing of FBX documents with undo history.
+
+use std::{error, fmt};
+
+use crate::fbx::{document::Subtree, Attribute, Document, NodeId};
+
+/// Edit of a document.
+#[derive(Debug, Clone)]
+pub enum Edit {
+    /// Replaces the attribute value.
+    ///
+    /// The new value should have the same type as the old one.
+    /// Arrays and binaries are not editable (see [`Attribute::is_editable`]).
+    SetAttribute {
+        /// Node.
+        node: NodeId,
+        /// Attribute index.
+        index: usize,
+        /// New value.
+        value: Attribute,
+    },
+    /// Renames the node.
+    Rename {
+        /// Node.
+        node: NodeId,
+        /// New name.
+        name: String,
+    },
+    /// Removes the node and its descendants.
+    Remove {
+        /// Node.
+        node: NodeId,
+    },
+    /// Duplicates the node and its descendants, and places the copy next to
+    /// the node.
+    Duplicate {
+        /// Node.
+        node: NodeId,
+    },
+}
+
+/// Error of editing.
+#[derive(Debug, Clone)]
+pub enum EditError {
+    /// The node does not exist.
+    NoSuchNode(NodeId),
+    /// The attribute does not exist.
+    NoSuchAttribute(NodeId, usize),
+    /// The new attribute value has a different type.
+    TypeMismatch {
+        /// Type of the current value.
+        expected: String,
+        /// Type of the new value.
+        found: String,
+    },
+    /// The attribute is an array or binary, which is not editable.
+    NotEditable(NodeId, usize),
+    /// The node name is empty.
+    EmptyName,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::NoSuchNode(node) => write!(f, "node {} does not exist", node.index()),
+            EditError::NoSuchAttribute(node, index) => write!(
+                f,
+                "attribute #{} of node {} does not exist",
+                index,
+                node.index()
+            ),
+            EditError::TypeMismatch { expected, found } => {
+                write!(f, "type mismatch: expected {}, found {}", expected, found)
+            }
+            EditError::NotEditable(node, index) => write!(
+                f,
+                "attribute #{} of node {} is not editable",
+                index,
+                node.index()
+            ),
+            EditError::EmptyName => write!(f, "node name is empty"),
+        }
+    }
+}
+
+impl error::Error for EditError {}
+
+/// Change recorded in the history.
+///
+/// This is an [`Edit`] in the form which can be reverted.
+#[derive(Debug, Clone)]
+enum Change {
+    /// Replaces the attribute value.
+    SetAttribute {
+        /// Node.
+        node: NodeId,
+        /// Attribute index.
+        index: usize,
+        /// New value.
+        value: Attribute,
+    },
+    /// Renames the node.
+    Rename {
+        /// Node.
+        node: NodeId,
+        /// New name.
+        name: String,
+    },
+    /// Removes the node and its descendants.
+    Remove {
+        /// Node.
+        node: NodeId,
+    },
+    /// Inserts the subtree.
+    Insert {
+        /// Parent node.
+        parent: Option<NodeId>,
+        /// Position among the siblings.
+        position: usize,
+        /// Nodes to be inserted.
+        subtree: Subtree,
+    },
+}
+
+impl Change {
+    /// Applies the change, and returns the inverse change and the node
+    /// affected.
+    ///
+    /// The affected node is `None` if a top-level node is removed.
+    fn apply(self, document: &mut Document) -> (Change, Option<NodeId>) {
+        match self {
+            Change::SetAttribute { node, index, value } => {
+                let value = document.set_attribute(node, index, value);
+                (Change::SetAttribute { node, index, value }, Some(node))
+            }
+            Change::Rename { node, name } => {
+                let name = document.rename_node(node, name);
+                (Change::Rename { node, name }, Some(node))
+            }
+            Change::Remove { node } => {
+                let (parent, position, subtree) = document.remove_subtree(node);
+                let inverse = Change::Insert {
+                    parent,
+                    position,
+                    subtree,
+                };
+                (inverse, parent)
+            }
+            Change::Insert {
+                parent,
+                position,
+                subtree,
+            } => {
+                let node = document.insert_subtree(parent, position, subtree);
+                (Change::Remove { node }, Some(node))
+            }
+        }
+    }
+}
+
+/// Undo and redo history of edits.
+///
+/// This also tracks whether the document is modified since it was saved.
+/// Edits should be applied only through the history, since the recorded
+/// changes refer to the nodes by IDs.
+#[derive(Debug, Clone)]
+pub struct EditHistory {
+    /// Inverse changes to undo, the last is the newest.
+    undo: Vec<Change>,
+    /// Changes to redo, the last is the newest undone.
+    redo: Vec<Change>,
+    /// Length of `undo` when saved, or `None` if the saved state is
+    /// discarded from the history.
+    saved: Option<usize>,
+}
+
+impl EditHistory {
+    /// Creates a new empty history for an unmodified document.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies the edit, and returns the node affected.
+    ///
+    /// The affected node is the new copy for [`Edit::Duplicate`], the parent
+    /// for [`Edit::Remove`] (or `None` for top-level nodes), and the edited
+    /// node otherwise.
+    ///
+    /// Edits which change nothing (such as setting the current value) are
+    /// not recorded, and do not mark the document modified.
+    pub fn apply(
+        &mut self,
+        document: &mut Document,
+        edit: Edit,
+    ) -> Result<Option<NodeId>, EditError> {
+        let change = match edit {
+            Edit::SetAttribute { node, index, value } => {
+                let current = document
+                    .get(node)
+                    .ok_or(EditError::NoSuchNode(node))?
+                    .attributes()
+                    .get(index)
+                    .ok_or(EditError::NoSuchAttribute(node, index))?;
+                // Arrays may be deferred placeholders of lazily loaded
+                // documents, which are decoded from the file instead.
+                if !current.is_editable() {
+                    return Err(EditError::NotEditable(node, index));
+                }
+                if std::mem::discriminant(current) != std::mem::discriminant(&value) {
+                    return Err(EditError::TypeMismatch {
+                        expected: current.type_string().to_owned(),
+                        found: value.type_string().to_owned(),
+                    });
+                }
+                if *current == value {
+                    return Ok(Some(node));
+                }
+                Change::SetAttribute { node, index, value }
+            }
+            Edit::Rename { node, name } => {
+                let current = document.get(node).ok_or(EditError::NoSuchNode(node))?;
+                if name.is_empty() {
+                    return Err(EditError::EmptyName);
+                }
+                if current.name() == name {
+                    return Ok(Some(node));
+                }
+                Change::Rename { node, name }
+            }
+            Edit::Remove { node } => {
+                document.get(node).ok_or(EditError::NoSuchNode(node))?;
+                Change::Remove { node }
+            }
+            Edit::Duplicate { node } => {
+                document.get(node).ok_or(EditError::NoSuchNode(node))?;
+                let (parent, position) = document.position(node);
+                let mut subtree = document.clone_subtree(node);
+                subtree.clear_offsets();
+                Change::Insert {
+                    parent,
+                    position: position + 1,
+                    subtree,
+                }
+            }
+        };
+        let (inverse, affected) = change.apply(document);
+        if self.saved > Some(self.undo.len()) {
+            // The saved state is in the redo history to be discarded.
+            self.saved = None;
+        }
+        self.undo.push(inverse);
+        self.redo.clear();
+        Ok(affected)
+    }
+
+    /// Reverts the last edit, and returns the node affected.
+    ///
+    /// Returns `None` if there is nothing to undo.
+    pub fn undo(&mut self, document: &mut Document) -> Option<NodeId> {
+        let (inverse, affected) = self.undo.pop()?.apply(document);
+        self.redo.push(inverse);
+        affected
+    }
+
+    /// Applies the last reverted edit again, and returns the node affected.
+    ///
+    /// Returns `None` if there is nothing to redo.
+    pub fn redo(&mut self, document: &mut Document) -> Option<NodeId> {
+        let (inverse, affected) = self.redo.pop()?.apply(document);
+        self.undo.push(inverse);
+        affected
+    }
+
+    /// Returns whether there are edits to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Returns whether there are edits to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Returns whether the document is modified since it was saved (or
+    /// loaded).
+    pub fn is_modified(&self) -> bool {
+        self.saved != Some(self.undo.len())
+    }
+
+    /// Marks the current state as saved.
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.undo.len());
+    }
+
+    /// Clears the history, for a newly loaded document.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            saved: Some(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fbx::{dump_document, DumpOptions};
+
+    /// Creates a small document.
+    ///
+    /// ```text
+    /// Header
+    /// Objects
+    ///     Model: 1, "Cube\x00\x01Model"
+    ///         Version: 232
+    ///     Geometry: 2
+    ///         Vertices: [0.0, 1.0]
+    /// ```
+    fn sample() -> Document {
+        let mut doc = Document::default();
+        doc.push_node(None, "Header".to_owned(), vec![], 0);
+        let objects = doc.push_node(None, "Objects".to_owned(), vec![], 0);
+        let model = doc.push_node(
+            Some(objects),
+            "Model".to_owned(),
+            vec![
+                Attribute::SingleI64(1),
+                Attribute::String("Cube\u{0}\u{1}Model".to_owned()),
+            ],
+            0,
+        );
+        doc.push_node(
+            Some(model),
+            "Version".to_owned(),
+            vec![Attribute::SingleI32(232)],
+            0,
+        );
+        let geometry = doc.push_node(
+            Some(objects),
+            "Geometry".to_owned(),
+            vec![Attribute::SingleI64(2)],
+            0,
+        );
+        doc.push_node(
+            Some(geometry),
+            "Vertices".to_owned(),
+            vec![Attribute::ArrayF64(vec![0.0, 1.0])],
+            0,
+        );
+        doc
+    }
+
+    /// Returns the dump of the document.
+    fn dump(doc: &Document) -> String {
+        let mut out = Vec::new();
+        dump_document(doc, &mut out, &DumpOptions::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Returns the node with the given path.
+    fn find(doc: &Document, path: &str) -> NodeId {
+        doc.node_ids()
+            .find(|&id| doc.path(id) == path)
+            .unwrap_or_else(|| panic!("no node {}", path))
+    }
+
+    #[test]
+    fn apply_undo_redo() {
+        let mut doc = sample();
+        let original = dump(&doc);
+        let mut history = EditHistory::new();
+        assert!(!history.is_modified());
+        assert!(!history.can_undo());
+
+        // Node IDs are looked up after each edit, since structural edits
+        // change them.
+        let model = find(&doc, "Objects/Model");
+        let edit = Edit::SetAttribute {
+            node: model,
+            index: 1,
+            value: Attribute::String("Sphere\u{0}\u{1}Model".to_owned()),
+        };
+        history.apply(&mut doc, edit).unwrap();
+        let edit = Edit::Rename {
+            node: find(&doc, "Objects/Model/Version"),
+            name: "Revision".to_owned(),
+        };
+        history.apply(&mut doc, edit).unwrap();
+        history
+            .apply(&mut doc, Edit::Duplicate { node: model })
+            .unwrap();
+        let edit = Edit::Remove {
+            node: find(&doc, "Objects/Geometry"),
+        };
+        history.apply(&mut doc, edit).unwrap();
+        let edited = dump(&doc);
+        assert!(history.is_modified());
+        let objects = find(&doc, "Objects");
+        let names: Vec<_> = doc
+            .children(Some(objects))
+            .iter()
+            .map(|&id| doc.node(id).name())
+            .collect();
+        assert_eq!(names, ["Model", "Model"]);
+        assert!(edited.contains("Revision"));
+        assert!(!edited.contains("Geometry"));
+
+        while history.can_undo() {
+            history.undo(&mut doc);
+        }
+        assert_eq!(dump(&doc), original);
+        assert!(!history.is_modified());
+
+        while history.can_redo() {
+            history.redo(&mut doc);
+        }
+        assert_eq!(dump(&doc), edited);
+        assert!(history.is_modified());
+    }
+
+    #[test]
+    fn affected_nodes() {
+        let mut doc = sample();
+        let mut history = EditHistory::new();
+        let model = find(&doc, "Objects/Model");
+        let copy = history
+            .apply(&mut doc, Edit::Duplicate { node: model })
+            .unwrap()
+            .unwrap();
+        assert_ne!(copy, model);
+        assert_eq!(doc.path(copy), "Objects/Model");
+        assert_eq!(doc.position(copy).1, doc.position(model).1 + 1);
+
+        let parent = history
+            .apply(&mut doc, Edit::Remove { node: copy })
+            .unwrap();
+        assert_eq!(parent, Some(find(&doc, "Objects")));
+
+        let header = find(&doc, "Header");
+        let parent = history
+            .apply(&mut doc, Edit::Remove { node: header })
+            .unwrap();
+        assert_eq!(parent, None);
+    }
+
+    #[test]
+    fn saved_marker() {
+        let mut doc = sample();
+        let mut history = EditHistory::new();
+        let version = find(&doc, "Objects/Model/Version");
+        let set = |value| Edit::SetAttribute {
+            node: version,
+            index: 0,
+            value: Attribute::SingleI32(value),
+        };
+
+        history.apply(&mut doc, set(1)).unwrap();
+        history.mark_saved();
+        assert!(!history.is_modified());
+        history.undo(&mut doc);
+        assert!(history.is_modified());
+        history.redo(&mut doc);
+        assert!(!history.is_modified());
+
+        // Discarding the saved state from the redo history.
+        history.undo(&mut doc);
+        history.apply(&mut doc, set(2)).unwrap();
+        assert!(history.is_modified());
+        history.undo(&mut doc);
+        assert!(history.is_modified());
+
+        history.clear();
+        assert!(!history.is_modified());
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn no_op_edits_are_not_recorded() {
+        let mut doc = sample();
+        let mut history = EditHistory::new();
+        let version = find(&doc, "Objects/Model/Version");
+        history
+            .apply(
+                &mut doc,
+                Edit::SetAttribute {
+                    node: version,
+                    index: 0,
+                    value: Attribute::SingleI32(232),
+                },
+            )
+            .unwrap();
+        history
+            .apply(
+                &mut doc,
+                Edit::Rename {
+                    node: version,
+                    name: "Version".to_owned(),
+                },
+            )
+            .unwrap();
+        assert!(!history.can_undo());
+        assert!(!history.is_modified());
+    }
+
+    #[test]
+    fn invalid_edits() {
+        let mut doc = sample();
+        let mut history = EditHistory::new();
+        let version = find(&doc, "Objects/Model/Version");
+        let res = history.apply(
+            &mut doc,
+            Edit::SetAttribute {
+                node: version,
+                index: 0,
+                value: Attribute::SingleI64(1),
+            },
+        );
+        assert!(matches!(res, Err(EditError::TypeMismatch { .. })));
+        let res = history.apply(
+            &mut doc,
+            Edit::SetAttribute {
+                node: version,
+                index: 1,
+                value: Attribute::SingleI32(1),
+            },
+        );
+        assert!(matches!(res, Err(EditError::NoSuchAttribute(_, 1))));
+        let vertices = find(&doc, "Objects/Geometry/Vertices");
+        let res = history.apply(
+            &mut doc,
+            Edit::SetAttribute {
+                node: vertices,
+                index: 0,
+                value: Attribute::ArrayF64(vec![2.0, 3.0]),
+            },
+        );
+        assert!(matches!(res, Err(EditError::NotEditable(_, 0))));
+        let res = history.apply(
+            &mut doc,
+            Edit::Rename {
+                node: version,
+                name: String::new(),
+            },
+        );
+        assert!(matches!(res, Err(EditError::EmptyName)));
+        let res = history.apply(
+            &mut doc,
+            Edit::Remove {
+                node: NodeId::new(100),
+            },
+        );
+        assert!(matches!(res, Err(EditError::NoSuchNode(_))));
+        assert!(!history.can_undo());
+    }
+}
@@ -0,0 +1,216 @@
+//! Embedded files in binary attributes.
+
+use crate::fbx::{mesh::child_by_name, Attribute, Document, NodeId};
+
+/// Image format of embedded files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// PNG.
+    Png,
+    /// JPEG.
+    Jpeg,
+    /// Truevision TGA.
+    Tga,
+    /// Windows bitmap.
+    Bmp,
+    /// DirectDraw surface.
+    Dds,
+}
+
+impl ImageFormat {
+    /// Detects the image format from the file content.
+    ///
+    /// TGA has no signature, so it is detected by the TGA 2.0 footer or
+    /// plausible header fields.
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if bytes.starts_with(b"\xff\xd8\xff") {
+            Some(ImageFormat::Jpeg)
+        } else if bytes.starts_with(b"DDS ") {
+            Some(ImageFormat::Dds)
+        } else if bytes.starts_with(b"BM") && bytes.len() >= 26 {
+            Some(ImageFormat::Bmp)
+        } else if is_tga(bytes) {
+            Some(ImageFormat::Tga)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the name of the format.
+    pub fn name(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "PNG",
+            ImageFormat::Jpeg => "JPEG",
+            ImageFormat::Tga => "TGA",
+            ImageFormat::Bmp => "BMP",
+            ImageFormat::Dds => "DDS",
+        }
+    }
+
+    /// Returns the usual file extension of the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Tga => "tga",
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Dds => "dds",
+        }
+    }
+}
+
+/// Checks whether the content looks like a TGA image.
+fn is_tga(bytes: &[u8]) -> bool {
+    if bytes.ends_with(b"TRUEVISION-XFILE.\0") {
+        return true;
+    }
+    if bytes.len() < 18 {
+        return false;
+    }
+    let color_map_type = bytes[1];
+    let image_type = bytes[2];
+    let width = u16::from_le_bytes([bytes[12], bytes[13]]);
+    let height = u16::from_le_bytes([bytes[14], bytes[15]]);
+    let pixel_depth = bytes[16];
+    color_map_type <= 1
+        && matches!(image_type, 1 | 2 | 3 | 9 | 10 | 11)
+        && width != 0
+        && height != 0
+        && matches!(pixel_depth, 8 | 15 | 16 | 24 | 32)
+}
+
+/// Returns the file name of the embedded content of the given node.
+///
+/// This is the `RelativeFilename` or `Filename` of the parent node (such as
+/// `Video`) of the `Content` node, without directories.
+pub fn embedded_file_name(document: &Document, content: NodeId) -> Option<String> {
+    let parent = document.node(content).parent()?;
+    ["RelativeFilename", "Filename"]
+        .iter()
+        .filter_map(|&name| child_by_name(document, parent, name))
+        .filter_map(|child| match document.node(child).attributes().first() {
+            Some(Attribute::String(s)) => Some(s.as_str()),
+            _ => None,
+        })
+        // Paths may be written with Windows separators.
+        .filter_map(|path| path.rsplit(['/', '\\']).next())
+        .find(|name| !name.is_empty())
+        .map(ToOwned::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a TGA header of the given image type and pixel depth.
+    fn tga_header(image_type: u8, pixel_depth: u8) -> Vec<u8> {
+        let mut header = vec![0; 18];
+        header[2] = image_type;
+        header[12..16].copy_from_slice(&[64, 0, 32, 0]);
+        header[16] = pixel_depth;
+        header
+    }
+
+    #[test]
+    fn signatures() {
+        let sniff = |bytes: &[u8]| ImageFormat::sniff(bytes);
+        assert_eq!(
+            sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some(ImageFormat::Png)
+        );
+        assert_eq!(
+            sniff(b"\xff\xd8\xff\xe0\0\x10JFIF"),
+            Some(ImageFormat::Jpeg)
+        );
+        assert_eq!(sniff(b"DDS |\0\0\0"), Some(ImageFormat::Dds));
+        assert_eq!(
+            sniff(&[&b"BM"[..], &[0; 24]].concat()),
+            Some(ImageFormat::Bmp)
+        );
+        // Too short for the BMP headers.
+        assert_eq!(sniff(b"BM\0\0"), None);
+        assert_eq!(sniff(b""), None);
+        assert_eq!(sniff(b"\x89PNG"), None);
+    }
+
+    #[test]
+    fn tga_header_heuristic() {
+        let sniff = |bytes: &[u8]| ImageFormat::sniff(bytes);
+        assert_eq!(sniff(&tga_header(2, 24)), Some(ImageFormat::Tga));
+        assert_eq!(sniff(&tga_header(10, 32)), Some(ImageFormat::Tga));
+        assert_eq!(sniff(&tga_header(4, 24)), None);
+        assert_eq!(sniff(&tga_header(2, 12)), None);
+        let mut zero_width = tga_header(2, 24);
+        zero_width[12] = 0;
+        assert_eq!(sniff(&zero_width), None);
+        assert_eq!(sniff(&tga_header(2, 24)[..17]), None);
+
+        // Random bytes are rejected.
+        let mut state = 0x1234_5678_u32;
+        for _ in 0..1000 {
+            let bytes: Vec<u8> = (0..64)
+                .map(|_| {
+                    state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    (state >> 16) as u8
+                })
+                .collect();
+            assert_eq!(sniff(&bytes), None, "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn tga_footer() {
+        // The header is broken, but the TGA 2.0 footer is found.
+        let mut bytes = tga_header(0, 0);
+        bytes.extend_from_slice(&[0; 8]);
+        bytes.extend_from_slice(b"TRUEVISION-XFILE.\0");
+        assert_eq!(ImageFormat::sniff(&bytes), Some(ImageFormat::Tga));
+    }
+
+    #[test]
+    fn file_names() {
+        let file_name = |attrs: &[(&str, &str)]| {
+            let mut doc = Document::default();
+            let video = doc.push_node(None, "Video".to_owned(), vec![], 0);
+            for &(name, path) in attrs {
+                doc.push_node(
+                    Some(video),
+                    name.to_owned(),
+                    vec![Attribute::String(path.to_owned())],
+                    0,
+                );
+            }
+            let content = doc.push_node(
+                Some(video),
+                "Content".to_owned(),
+                vec![Attribute::Binary(Vec::new())],
+                0,
+            );
+            embedded_file_name(&doc, content)
+        };
+        assert_eq!(
+            file_name(&[("RelativeFilename", r"..\textures\wood.png")]),
+            Some("wood.png".to_owned())
+        );
+        assert_eq!(
+            file_name(&[("Filename", "/home/user/textures/wood.jpg")]),
+            Some("wood.jpg".to_owned())
+        );
+        // `RelativeFilename` is preferred.
+        assert_eq!(
+            file_name(&[
+                ("Filename", r"C:\textures\abs.tga"),
+                ("RelativeFilename", "textures/rel.tga"),
+            ]),
+            Some("rel.tga".to_owned())
+        );
+        // Paths ending with separators have no file names.
+        assert_eq!(
+            file_name(&[("RelativeFilename", r"textures\"), ("Filename", "wood.dds"),]),
+            Some("wood.dds".to_owned())
+        );
+        assert_eq!(file_name(&[("RelativeFilename", "")]), None);
+    }
+}
@@ -0,0 +1,253 @@
+//! Filter of FBX nodes.
+
+use crate::fbx::{Attribute, Document, NodeId};
+
+/// Node filter.
+///
+/// The pattern is a slash-separated path of node name globs, optionally
+/// followed by `=` and a glob for the first attribute.
+/// Globs support `*` (any string) and `?` (any character), and are case
+/// sensitive.
+/// A `**` component matches any number of nodes (including none).
+///
+/// For example, `Properties70/P=Lcl Translation` matches `P` nodes under
+/// `Properties70` nodes whose first attribute is `"Lcl Translation"`, and
+/// `Objects/Geometry` matches `Geometry` nodes directly under `Objects`, and
+/// `Objects/**/Vertices` matches `Vertices` nodes anywhere under `Objects`.
+#[derive(Debug, Clone)]
+pub struct NodeFilter {
+    /// Globs for the node names, from the ancestor to the node itself.
+    path: Vec<String>,
+    /// Glob for the first attribute.
+    first_attr: Option<String>,
+}
+
+impl NodeFilter {
+    /// Creates a new filter from the given pattern.
+    ///
+    /// Returns `None` if the pattern is empty.
+    pub fn new(pattern: &str) -> Option<Self> {
+        let (path, first_attr) = match pattern.find('=') {
+            Some(pos) => (
+                &pattern[..pos],
+                Some(pattern[(pos + 1)..].trim().to_owned()),
+            ),
+            None => (pattern, None),
+        };
+        let path: Vec<String> = path
+            .split('/')
+            .map(str::trim)
+            .filter(|component| !component.is_empty())
+            .map(ToOwned::to_owned)
+            .collect();
+        if path.is_empty() && first_attr.is_none() {
+            return None;
+        }
+
+        Some(Self { path, first_attr })
+    }
+
+    /// Returns whether the given node matches the filter.
+    pub fn matches(&self, document: &Document, id: NodeId) -> bool {
+        let names: Vec<&str> = document
+            .ancestors(id)
+            .map(|id| document.node(id).name())
+            .collect();
+        if !path_match(&self.path, &names) {
+            return false;
+        }
+        let glob = match &self.first_attr {
+            Some(v) => v,
+            None => return true,
+        };
+        match document.node(id).attributes().first() {
+            Some(Attribute::String(s)) => glob_match(glob, s),
+            Some(attr) => glob_match(glob, &attr.value_string_oneline(None)),
+            None => false,
+        }
+    }
+
+    /// Returns the visibility of each node, indexed by the node ID.
+    ///
+    /// Matching nodes, their ancestors and their descendants are visible.
+    pub fn visible_nodes(&self, document: &Document) -> Vec<bool> {
+        let mut visible = vec![false; document.len()];
+        // Whether the node is a matching node or its descendant.
+        let mut in_matched = vec![false; document.len()];
+        // Node IDs are preorder, so parents are always visited before
+        // their children.
+        for id in document.node_ids() {
+            let parent = document.node(id).parent();
+            if parent.map_or(false, |parent| in_matched[parent.index()]) {
+                in_matched[id.index()] = true;
+                visible[id.index()] = true;
+            } else if self.matches(document, id) {
+                in_matched[id.index()] = true;
+                for ancestor in document.ancestors(id) {
+                    if visible[ancestor.index()] {
+                        break;
+                    }
+                    visible[ancestor.index()] = true;
+                }
+            }
+        }
+        visible
+    }
+}
+
+/// Returns whether the end of the path matches the globs.
+///
+/// `names` are the node names from the node itself to the top-level node.
+fn path_match(globs: &[String], names: &[&str]) -> bool {
+    let (glob, rest) = match globs.split_last() {
+        Some(v) => v,
+        None => return true,
+    };
+    if glob == "**" {
+        return (0..=names.len()).any(|skip| path_match(rest, &names[skip..]));
+    }
+    match names.split_first() {
+        Some((name, ancestors)) => glob_match(glob, name) && path_match(rest, ancestors),
+        None => false,
+    }
+}
+
+/// Returns whether the text matches the glob.
+fn glob_match(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut g, mut t) = (0, 0);
+    // Position of the last `*` in the glob, and the text position it
+    // matched until.
+    let mut backtrack = None;
+    while t < text.len() {
+        match glob.get(g) {
+            Some('*') => {
+                backtrack = Some((g, t));
+                g += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                g += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_g, star_t)) => {
+                    backtrack = Some((star_g, star_t + 1));
+                    g = star_g + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates the following tree:
+    ///
+    /// ```text
+    /// Objects
+    ///     Model
+    ///         Properties70
+    ///             P "Lcl Translation"
+    ///             P "Lcl Rotation"
+    ///     Geometry
+    ///         Vertices
+    /// Connections
+    /// ```
+    fn sample() -> Document {
+        let mut doc = Document::default();
+        let string = |s: &str| vec![Attribute::String(s.to_owned())];
+        let objects = doc.push_node(None, "Objects".to_owned(), vec![], 0);
+        let model = doc.push_node(Some(objects), "Model".to_owned(), vec![], 0);
+        let props = doc.push_node(Some(model), "Properties70".to_owned(), vec![], 0);
+        doc.push_node(Some(props), "P".to_owned(), string("Lcl Translation"), 0);
+        doc.push_node(Some(props), "P".to_owned(), string("Lcl Rotation"), 0);
+        let geometry = doc.push_node(
+            Some(objects),
+            "Geometry".to_owned(),
+            vec![Attribute::SingleI64(42)],
+            0,
+        );
+        doc.push_node(Some(geometry), "Vertices".to_owned(), vec![], 0);
+        doc.push_node(None, "Connections".to_owned(), vec![], 0);
+        doc
+    }
+
+    /// Returns the indices of the nodes matching the pattern.
+    fn matching(doc: &Document, pattern: &str) -> Vec<usize> {
+        let filter = NodeFilter::new(pattern).unwrap();
+        doc.node_ids()
+            .filter(|&id| filter.matches(doc, id))
+            .map(NodeId::index)
+            .collect()
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("Lcl *", "Lcl Translation"));
+        assert!(glob_match("*tion", "Lcl Rotation"));
+        assert!(glob_match("L?l*n", "Lcl Translation"));
+        assert!(glob_match("*a*a*", "banana"));
+        assert!(!glob_match("?", ""));
+        assert!(!glob_match("lcl*", "Lcl Translation"));
+        assert!(!glob_match("Lcl", "Lcl Translation"));
+    }
+
+    #[test]
+    fn paths() {
+        let doc = sample();
+        assert!(NodeFilter::new(" / ").is_none());
+        assert_eq!(matching(&doc, "P"), [3, 4]);
+        assert_eq!(matching(&doc, "Objects/Geometry"), [5]);
+        assert_eq!(matching(&doc, "Objects/*"), [1, 5]);
+        assert_eq!(matching(&doc, "Objects/*/Vertices"), [6]);
+        assert_eq!(matching(&doc, "Objects/Vertices"), []);
+        assert_eq!(matching(&doc, "??????ties70"), [2]);
+    }
+
+    #[test]
+    fn double_star() {
+        let doc = sample();
+        assert_eq!(matching(&doc, "Objects/**/P"), [3, 4]);
+        assert_eq!(matching(&doc, "Objects/**/Geometry"), [5]);
+        assert_eq!(matching(&doc, "Model/**/Vertices"), []);
+        assert_eq!(matching(&doc, "Objects/**"), [0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(matching(&doc, "Model/**/**/P"), [3, 4]);
+    }
+
+    #[test]
+    fn first_attribute() {
+        let doc = sample();
+        assert_eq!(matching(&doc, "Properties70/P=Lcl Translation"), [3]);
+        assert_eq!(matching(&doc, "P=Lcl*"), [3, 4]);
+        assert_eq!(matching(&doc, "P = *Rotation"), [4]);
+        // Non-string attributes are compared with their string
+        // representation, and nodes without attributes do not match.
+        assert_eq!(matching(&doc, "=42"), [5]);
+        assert_eq!(matching(&doc, "Objects="), []);
+    }
+
+    #[test]
+    fn visible_nodes() {
+        let doc = sample();
+        let filter = NodeFilter::new("P=Lcl Rotation").unwrap();
+        // Ancestors of the matching node are visible, but its siblings are
+        // not.
+        assert_eq!(
+            filter.visible_nodes(&doc),
+            [true, true, true, false, true, false, false, false]
+        );
+        // Descendants of the matching node are visible.
+        let filter = NodeFilter::new("Geometry").unwrap();
+        assert_eq!(
+            filter.visible_nodes(&doc),
+            [true, false, false, false, false, true, true, false]
+        );
+    }
+}
@@ -0,0 +1,296 @@
+//! JSON export of FBX node tree.
+
+use std::io::{self, Write};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+use crate::fbx::{Attribute, Document, NodeId};
+
+/// Indent for each depth.
+const INDENT: &str = "  ";
+
+/// Options for [`export_json`].
+#[derive(Debug, Default, Clone)]
+pub struct JsonOptions {
+    /// Maximum number of array elements to be written for each attribute.
+    ///
+    /// Binaries are not truncated.
+    /// `None` means unlimited.
+    pub max_array_len: Option<usize>,
+}
+
+/// Writes the node tree as JSON to the given writer.
+///
+/// The output is an object with the FBX version (such as `7400`) and the
+/// top-level nodes:
+///
+/// ```json
+/// {
+///   "fbx_version": 7400,
+///   "nodes": [
+///     {
+///       "name": "Vertices",
+///       "attributes": [
+///         {"type": "[f64]", "len": 3, "value": [0, 1, 2]}
+///       ],
+///       "children": []
+///     }
+///   ]
+/// }
+/// ```
+///
+/// Each attribute has the type name by [`Attribute::type_string`] and the
+/// value.
+/// Arrays and binaries also have the number of elements as `len`, and
+/// truncated arrays have `"truncated": true`.
+/// Binaries are base64-encoded strings.
+/// Non-finite floating point numbers are written as `null`, since JSON
+/// cannot represent them.
+pub fn export_json<W: Write>(
+    document: &Document,
+    out: &mut W,
+    options: &JsonOptions,
+) -> io::Result<()> {
+    let version = document.fbx_version();
+    writeln!(out, "{{")?;
+    writeln!(
+        out,
+        "{}\"fbx_version\": {},",
+        INDENT,
+        version.major() * 1000 + version.minor() * 100
+    )?;
+    write!(out, "{}\"nodes\": ", INDENT)?;
+    write_nodes(document, document.toplevel_nodes(), 1, out, options)?;
+    writeln!(out)?;
+    writeln!(out, "}}")?;
+    Ok(())
+}
+
+/// Writes the array of the given nodes.
+fn write_nodes<W: Write>(
+    document: &Document,
+    nodes: &[NodeId],
+    depth: usize,
+    out: &mut W,
+    options: &JsonOptions,
+) -> io::Result<()> {
+    if nodes.is_empty() {
+        return write!(out, "[]");
+    }
+    writeln!(out, "[")?;
+    for (i, &node_id) in nodes.iter().enumerate() {
+        write_node(document, node_id, depth + 1, out, options)?;
+        if i + 1 != nodes.len() {
+            write!(out, ",")?;
+        }
+        writeln!(out)?;
+    }
+    write!(out, "{}]", INDENT.repeat(depth))
+}
+
+/// Writes the given node and its descendants.
+fn write_node<W: Write>(
+    document: &Document,
+    node_id: NodeId,
+    depth: usize,
+    out: &mut W,
+    options: &JsonOptions,
+) -> io::Result<()> {
+    let node = document.node(node_id);
+    let attrs = document.load_attributes(node_id)?;
+    let indent = INDENT.repeat(depth);
+    writeln!(out, "{}{{", indent)?;
+    write!(out, "{}{}\"name\": ", indent, INDENT)?;
+    write_string(out, node.name())?;
+    writeln!(out, ",")?;
+
+    write!(out, "{}{}\"attributes\": [", indent, INDENT)?;
+    for (i, attr) in attrs.iter().enumerate() {
+        if i != 0 {
+            write!(out, ",")?;
+        }
+        write!(out, "\n{}{}{}", indent, INDENT, INDENT)?;
+        write_attribute(out, attr, options)?;
+    }
+    if !attrs.is_empty() {
+        write!(out, "\n{}{}", indent, INDENT)?;
+    }
+    writeln!(out, "],")?;
+
+    write!(out, "{}{}\"children\": ", indent, INDENT)?;
+    write_nodes(document, node.children(), depth + 1, out, options)?;
+    write!(out, "\n{}}}", indent)
+}
+
+/// Writes the attribute as an object.
+fn write_attribute<W: Write>(
+    out: &mut W,
+    attr: &Attribute,
+    options: &JsonOptions,
+) -> io::Result<()> {
+    write!(out, "{{\"type\": ")?;
+    write_string(out, attr.type_string())?;
+    if let Some(len) = attr.array_len() {
+        write!(out, ", \"len\": {}", len)?;
+    }
+    write!(out, ", \"value\": ")?;
+    let limit = options.max_array_len;
+    let truncated = match attr {
+        Attribute::SingleBool(v) => write!(out, "{}", v).and(Ok(false))?,
+        Attribute::SingleI16(v) => write!(out, "{}", v).and(Ok(false))?,
+        Attribute::SingleI32(v) => write!(out, "{}", v).and(Ok(false))?,
+        Attribute::SingleI64(v) => write!(out, "{}", v).and(Ok(false))?,
+        Attribute::SingleF32(v) => write_float(out, *v).and(Ok(false))?,
+        Attribute::SingleF64(v) => write_float(out, *v).and(Ok(false))?,
+        Attribute::ArrayBool(arr) => write_array(out, arr, limit, |out, v| write!(out, "{}", v))?,
+        Attribute::ArrayI32(arr) => write_array(out, arr, limit, |out, v| write!(out, "{}", v))?,
+        Attribute::ArrayI64(arr) => write_array(out, arr, limit, |out, v| write!(out, "{}", v))?,
+        Attribute::ArrayF32(arr) => write_array(out, arr, limit, |out, &v| write_float(out, v))?,
+        Attribute::ArrayF64(arr) => write_array(out, arr, limit, |out, &v| write_float(out, v))?,
+        Attribute::String(s) => write_string(out, s).and(Ok(false))?,
+        // Binaries are not truncated.
+        Attribute::Binary(bytes) => write!(out, "\"{}\"", BASE64.encode(bytes)).and(Ok(false))?,
+    };
+    if truncated {
+        write!(out, ", \"truncated\": true")?;
+    }
+    write!(out, "}}")
+}
+
+/// Writes at most `limit` elements of the array, and returns whether the
+/// array is truncated.
+fn write_array<W, T, F>(
+    out: &mut W,
+    arr: &[T],
+    limit: Option<usize>,
+    mut write_element: F,
+) -> io::Result<bool>
+where
+    W: Write,
+    F: FnMut(&mut W, &T) -> io::Result<()>,
+{
+    let limit = limit.unwrap_or(arr.len()).min(arr.len());
+    write!(out, "[")?;
+    for (i, v) in arr[..limit].iter().enumerate() {
+        if i != 0 {
+            write!(out, ", ")?;
+        }
+        write_element(out, v)?;
+    }
+    write!(out, "]")?;
+    Ok(limit < arr.len())
+}
+
+/// Writes the floating point number, or `null` if it is not finite.
+fn write_float<W, T>(out: &mut W, v: T) -> io::Result<()>
+where
+    W: Write,
+    T: Copy + Into<f64> + std::fmt::Display,
+{
+    if v.into().is_finite() {
+        write!(out, "{}", v)
+    } else {
+        write!(out, "null")
+    }
+}
+
+/// Writes the string as a quoted and escaped JSON string.
+fn write_string<W: Write>(out: &mut W, s: &str) -> io::Result<()> {
+    write!(out, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            '\n' => write!(out, "\\n")?,
+            '\r' => write!(out, "\\r")?,
+            '\t' => write!(out, "\\t")?,
+            c if c <= '\x1f' => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    write!(out, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fbxcel::low::FbxVersion;
+
+    /// Creates `Geometry/Vertices` with attributes of several types.
+    fn sample() -> Document {
+        let mut doc = Document::new(FbxVersion::V7_4);
+        let geometry = doc.push_node(
+            None,
+            "Geometry".to_owned(),
+            vec![
+                Attribute::SingleI64(1234),
+                Attribute::String("a\"b\\c\n\u{1}".to_owned()),
+                Attribute::Binary(vec![0, 1, 2]),
+            ],
+            0,
+        );
+        doc.push_node(
+            Some(geometry),
+            "Vertices".to_owned(),
+            vec![
+                Attribute::ArrayF64(vec![0.5, f64::NAN, -2.0]),
+                Attribute::SingleBool(true),
+            ],
+            0,
+        );
+        doc
+    }
+
+    /// Returns the JSON of the document.
+    fn export(doc: &Document, options: &JsonOptions) -> String {
+        let mut out = Vec::new();
+        export_json(doc, &mut out, options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn format() {
+        let expected = r#"{
+  "fbx_version": 7400,
+  "nodes": [
+    {
+      "name": "Geometry",
+      "attributes": [
+        {"type": "i64", "value": 1234},
+        {"type": "String", "value": "a\"b\\c\n\u0001"},
+        {"type": "[u8]", "len": 3, "value": "AAEC"}
+      ],
+      "children": [
+        {
+          "name": "Vertices",
+          "attributes": [
+            {"type": "[f64]", "len": 3, "value": [0.5, null, -2]},
+            {"type": "bool", "value": true}
+          ],
+          "children": []
+        }
+      ]
+    }
+  ]
+}
+"#;
+        assert_eq!(export(&sample(), &JsonOptions::default()), expected);
+    }
+
+    #[test]
+    fn max_array_len() {
+        let options = JsonOptions {
+            max_array_len: Some(1),
+        };
+        let json = export(&sample(), &options);
+        assert!(json.contains(r#"{"type": "[f64]", "len": 3, "value": [0.5], "truncated": true}"#));
+        // Binaries are not truncated.
+        assert!(json.contains(r#"{"type": "[u8]", "len": 3, "value": "AAEC"}"#));
+
+        let options = JsonOptions {
+            max_array_len: Some(3),
+        };
+        assert!(!export(&sample(), &options).contains("truncated"));
+    }
+}
@@ -0,0 +1,605 @@
+//! Layer elements of mesh geometry.
+
+use std::fmt;
+
+use crate::fbx::{
+    mesh::{child_by_name, child_f64_array, child_i64_array},
+    Attribute, Document, Mesh, NodeId,
+};
+
+/// Known layer elements: node name, data node name, index node name, and
+/// number of components of each element.
+const KNOWN_ELEMENTS: &[(&str, &str, &str, usize)] = &[
+    ("LayerElementNormal", "Normals", "NormalsIndex", 3),
+    ("LayerElementBinormal", "Binormals", "BinormalsIndex", 3),
+    ("LayerElementTangent", "Tangents", "TangentsIndex", 3),
+    ("LayerElementUV", "UV", "UVIndex", 2),
+    ("LayerElementColor", "Colors", "ColorIndex", 4),
+    ("LayerElementMaterial", "Materials", "", 1),
+    ("LayerElementSmoothing", "Smoothing", "", 1),
+    ("LayerElementPolygonGroup", "PolygonGroup", "", 1),
+    ("LayerElementVisibility", "Visibility", "", 1),
+    ("LayerElementEdgeCrease", "EdgeCrease", "", 1),
+];
+
+/// Mapping mode (`MappingInformationType`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingMode {
+    /// One element for each polygon vertex.
+    ByPolygonVertex,
+    /// One element for each control point (`ByVertice` or `ByControlPoint`).
+    ByVertex,
+    /// One element for each polygon.
+    ByPolygon,
+    /// One element for each edge.
+    ByEdge,
+    /// One element for the whole mesh.
+    AllSame,
+}
+
+impl MappingMode {
+    /// Parses the mapping mode.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ByPolygonVertex" => Some(MappingMode::ByPolygonVertex),
+            "ByVertice" | "ByVertex" | "ByControlPoint" => Some(MappingMode::ByVertex),
+            "ByPolygon" => Some(MappingMode::ByPolygon),
+            "ByEdge" => Some(MappingMode::ByEdge),
+            "AllSame" => Some(MappingMode::AllSame),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MappingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MappingMode::ByPolygonVertex => "ByPolygonVertex",
+            MappingMode::ByVertex => "ByVertice",
+            MappingMode::ByPolygon => "ByPolygon",
+            MappingMode::ByEdge => "ByEdge",
+            MappingMode::AllSame => "AllSame",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Reference mode (`ReferenceInformationType`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceMode {
+    /// Data is indexed by the mapping index directly.
+    Direct,
+    /// Data is indexed by the index array (`IndexToDirect` or `Index`).
+    IndexToDirect,
+}
+
+impl ReferenceMode {
+    /// Parses the reference mode.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Direct" => Some(ReferenceMode::Direct),
+            "IndexToDirect" | "Index" => Some(ReferenceMode::IndexToDirect),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ReferenceMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ReferenceMode::Direct => "Direct",
+            ReferenceMode::IndexToDirect => "IndexToDirect",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Element resolved for a polygon vertex.
+#[derive(Debug, Clone)]
+pub struct ResolvedElement {
+    /// Polygon vertex index.
+    pub polygon_vertex: usize,
+    /// Polygon index.
+    pub polygon: usize,
+    /// Control point (vertex) index.
+    pub vertex: i64,
+    /// Index by the mapping mode.
+    pub mapping_index: usize,
+    /// Index into the data array, or `None` if the index array is too short.
+    pub data_index: Option<i64>,
+    /// Element value, or `None` if the data index is out of range.
+    pub value: Option<Vec<f64>>,
+}
+
+/// Layer element decoded from a `LayerElement*` node.
+#[derive(Debug, Clone)]
+pub struct LayerElement {
+    /// Layer index.
+    layer: Option<i64>,
+    /// Name of the element set (`Name` child node).
+    name: String,
+    /// Mapping mode.
+    mapping: Option<MappingMode>,
+    /// Reference mode.
+    reference: Option<ReferenceMode>,
+    /// Name of the data node.
+    data_name: String,
+    /// Number of components of each element.
+    components: usize,
+    /// Number of elements in the data array.
+    num_data: usize,
+    /// Length of the index array.
+    num_indices: Option<usize>,
+    /// Elements resolved for each polygon vertex.
+    resolved: Vec<ResolvedElement>,
+    /// Problems found while decoding.
+    issues: Vec<String>,
+}
+
+impl LayerElement {
+    /// Decodes the given layer element node.
+    ///
+    /// Returns `None` if the node is not a `LayerElement*` node under a
+    /// mesh geometry.
+    pub fn from_node(document: &Document, node_id: NodeId) -> Option<Self> {
+        let node = document.node(node_id);
+        if !node.name().starts_with("LayerElement") {
+            return None;
+        }
+        let mesh = Mesh::from_geometry(document, node.parent()?)?;
+        let mut issues = Vec::new();
+
+        let layer = match node.attributes().first() {
+            Some(&Attribute::SingleI32(v)) => Some(i64::from(v)),
+            Some(&Attribute::SingleI64(v)) => Some(v),
+            _ => None,
+        };
+        let child_string = |name: &str| {
+            child_by_name(document, node_id, name).and_then(|child| {
+                match document.node(child).attributes().first() {
+                    Some(Attribute::String(s)) => Some(s.clone()),
+                    _ => None,
+                }
+            })
+        };
+        let name = child_string("Name").unwrap_or_default();
+        let mapping_name = child_string("MappingInformationType");
+        let mapping = mapping_name.as_deref().and_then(MappingMode::from_name);
+        if mapping.is_none() {
+            issues.push(format!(
+                "Unsupported `MappingInformationType`: {:?}",
+                mapping_name.unwrap_or_default()
+            ));
+        }
+        let reference_name = child_string("ReferenceInformationType");
+        let reference = reference_name.as_deref().and_then(ReferenceMode::from_name);
+        if reference.is_none() {
+            issues.push(format!(
+                "Unsupported `ReferenceInformationType`: {:?}",
+                reference_name.unwrap_or_default()
+            ));
+        }
+
+        let (data_name, index_name, components) = data_layout(document, node_id);
+        let data = child_f64_array(document, node_id, &data_name).unwrap_or_default();
+        if data.len() % components != 0 {
+            issues.push(format!(
+                "`{}` has {} elements, which is not a multiple of {}",
+                data_name,
+                data.len(),
+                components
+            ));
+        }
+        let num_data = data.len() / components;
+        let mut indices = child_i64_array(document, node_id, &index_name);
+        if node.name() == "LayerElementMaterial" {
+            // `Materials` is the index array to the connected materials, so
+            // it is treated as data.
+            indices = None;
+        } else if reference == Some(ReferenceMode::IndexToDirect) && indices.is_none() {
+            issues.push(format!(
+                "`{}` is required for `IndexToDirect` but not found",
+                index_name
+            ));
+        }
+        let use_indices = reference == Some(ReferenceMode::IndexToDirect) && indices.is_some();
+
+        // Check the lengths.
+        let expected_len = mapping.and_then(|mapping| match mapping {
+            MappingMode::ByPolygonVertex => Some(mesh.num_polygon_vertices()),
+            MappingMode::ByVertex => Some(mesh.vertices().len()),
+            MappingMode::ByPolygon => Some(mesh.polygons().len()),
+            MappingMode::AllSame => Some(1),
+            MappingMode::ByEdge => None,
+        });
+        let (actual_name, actual_len) = match &indices {
+            Some(indices) if use_indices => (index_name.as_str(), indices.len()),
+            _ => (data_name.as_str(), num_data),
+        };
+        if let Some(expected_len) = expected_len {
+            // `AllSame` elements may have extra data.
+            let is_short = actual_len < expected_len;
+            let is_long = actual_len > expected_len && mapping != Some(MappingMode::AllSame);
+            if is_short || is_long {
+                issues.push(format!(
+                    "Length mismatch: `{}` has {} elements but {} are expected for `{}`",
+                    actual_name,
+                    actual_len,
+                    expected_len,
+                    mapping.expect("Should never fail: expected length is known")
+                ));
+            }
+        }
+        if mapping == Some(MappingMode::ByEdge) {
+            issues.push("`ByEdge` mapping is not resolved to polygon vertices".to_owned());
+        }
+
+        // Resolve elements for each polygon vertex.
+        let mut resolved = Vec::new();
+        if let Some(mapping) = mapping.filter(|&m| m != MappingMode::ByEdge) {
+            let mut num_index_errors = 0;
+            let mut num_data_errors = 0;
+            for (polygon_index, polygon) in mesh.polygons().iter().enumerate() {
+                for (i, &vertex) in polygon.vertices().iter().enumerate() {
+                    let polygon_vertex = polygon.first_polygon_vertex() + i;
+                    let mapping_index = match mapping {
+                        MappingMode::ByPolygonVertex => polygon_vertex,
+                        MappingMode::ByVertex => vertex as usize,
+                        MappingMode::ByPolygon => polygon_index,
+                        MappingMode::AllSame | MappingMode::ByEdge => 0,
+                    };
+                    let data_index = match &indices {
+                        Some(indices) if use_indices => indices.get(mapping_index).cloned(),
+                        _ => Some(mapping_index as i64),
+                    };
+                    let value = data_index
+                        .filter(|&index| index >= 0 && (index as usize) < num_data)
+                        .map(|index| {
+                            let start = index as usize * components;
+                            data[start..(start + components)].to_vec()
+                        });
+                    match (data_index, &value) {
+                        (None, _) => num_index_errors += 1,
+                        (Some(_), None) => num_data_errors += 1,
+                        _ => {}
+                    }
+                    resolved.push(ResolvedElement {
+                        polygon_vertex,
+                        polygon: polygon_index,
+                        vertex,
+                        mapping_index,
+                        data_index,
+                        value,
+                    });
+                }
+            }
+            if num_index_errors != 0 {
+                issues.push(format!(
+                    "{} polygon vertices have no index in `{}`",
+                    num_index_errors, index_name
+                ));
+            }
+            if num_data_errors != 0 {
+                issues.push(format!(
+                    "{} polygon vertices refer to out-of-range elements of `{}`",
+                    num_data_errors, data_name
+                ));
+            }
+        }
+
+        Some(Self {
+            layer,
+            name,
+            mapping,
+            reference,
+            data_name,
+            components,
+            num_data,
+            num_indices: indices.as_ref().map(Vec::len),
+            resolved,
+            issues,
+        })
+    }
+
+    /// Returns the layer index.
+    pub fn layer(&self) -> Option<i64> {
+        self.layer
+    }
+
+    /// Returns the name of the element set (such as UV set name).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the mapping mode.
+    pub fn mapping(&self) -> Option<MappingMode> {
+        self.mapping
+    }
+
+    /// Returns the reference mode.
+    pub fn reference(&self) -> Option<ReferenceMode> {
+        self.reference
+    }
+
+    /// Returns the name of the data node (such as `Normals`).
+    pub fn data_name(&self) -> &str {
+        &self.data_name
+    }
+
+    /// Returns the number of components of each element.
+    pub fn components(&self) -> usize {
+        self.components
+    }
+
+    /// Returns the number of elements in the data array.
+    pub fn num_data(&self) -> usize {
+        self.num_data
+    }
+
+    /// Returns the length of the index array, if exists.
+    pub fn num_indices(&self) -> Option<usize> {
+        self.num_indices
+    }
+
+    /// Returns the elements resolved for each polygon vertex.
+    pub fn resolved(&self) -> &[ResolvedElement] {
+        &self.resolved
+    }
+
+    /// Returns the problems found while decoding.
+    pub fn issues(&self) -> &[String] {
+        &self.issues
+    }
+}
+
+/// Returns the data node name, index node name and number of components
+/// of the layer element.
+fn data_layout(document: &Document, node_id: NodeId) -> (String, String, usize) {
+    let node_name = document.node(node_id).name();
+    if let Some(&(_, data, index, components)) =
+        KNOWN_ELEMENTS.iter().find(|(name, ..)| *name == node_name)
+    {
+        return (data.to_owned(), index.to_owned(), components);
+    }
+
+    // Unknown element: use the first array which is not an index.
+    let data = document
+        .node(node_id)
+        .children()
+        .iter()
+        .map(|&child| document.node(child))
+        .find(|child| {
+            !child.name().ends_with("Index")
+                && child
+                    .attributes()
+                    .first()
+                    .map_or(false, |attr| attr.array_len().is_some())
+        })
+        .map_or_else(String::new, |child| child.name().to_owned());
+    let index = format!("{}Index", data);
+    (data, index, 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Appends a child node with a single attribute.
+    fn push(doc: &mut Document, parent: NodeId, name: &str, attr: Attribute) -> NodeId {
+        doc.push_node(Some(parent), name.to_owned(), vec![attr], 0)
+    }
+
+    /// Creates a geometry of two triangles (`0, 1, 2` and `1, 3, 2`) with
+    /// the given layer element, and returns the document and the layer
+    /// element node.
+    fn geometry(
+        element: &str,
+        mapping: &str,
+        reference: &str,
+        data_name: &str,
+        data: Attribute,
+    ) -> (Document, NodeId) {
+        let mut doc = Document::default();
+        let objects = doc.push_node(None, "Objects".to_owned(), vec![], 0);
+        let geometry = doc.push_node(
+            Some(objects),
+            "Geometry".to_owned(),
+            vec![
+                Attribute::SingleI64(1),
+                Attribute::String("\u{0}\u{1}Geometry".to_owned()),
+                Attribute::String("Mesh".to_owned()),
+            ],
+            0,
+        );
+        push(
+            &mut doc,
+            geometry,
+            "Vertices",
+            Attribute::ArrayF64(vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0,
+            ]),
+        );
+        push(
+            &mut doc,
+            geometry,
+            "PolygonVertexIndex",
+            Attribute::ArrayI32(vec![0, 1, -3, 1, 3, -3]),
+        );
+        let element = doc.push_node(
+            Some(geometry),
+            element.to_owned(),
+            vec![Attribute::SingleI32(0)],
+            0,
+        );
+        let string = |s: &str| Attribute::String(s.to_owned());
+        push(&mut doc, element, "MappingInformationType", string(mapping));
+        push(
+            &mut doc,
+            element,
+            "ReferenceInformationType",
+            string(reference),
+        );
+        push(&mut doc, element, data_name, data);
+        (doc, element)
+    }
+
+    #[test]
+    fn bool_data() {
+        let (doc, element) = geometry(
+            "LayerElementVisibility",
+            "ByPolygon",
+            "Direct",
+            "Visibility",
+            Attribute::ArrayBool(vec![true, false]),
+        );
+        let element = LayerElement::from_node(&doc, element).unwrap();
+        assert_eq!(element.num_data(), 2);
+        assert!(element.issues().is_empty(), "{:?}", element.issues());
+        let values: Vec<_> = element
+            .resolved()
+            .iter()
+            .map(|resolved| resolved.value.clone())
+            .collect();
+        let visible = Some(vec![1.0]);
+        let hidden = Some(vec![0.0]);
+        assert_eq!(
+            values,
+            [
+                visible.clone(),
+                visible.clone(),
+                visible,
+                hidden.clone(),
+                hidden.clone(),
+                hidden
+            ]
+        );
+    }
+
+    #[test]
+    fn float_data() {
+        let (doc, element) = geometry(
+            "LayerElementUV",
+            "ByVertice",
+            "Direct",
+            "UV",
+            Attribute::ArrayF64(vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0]),
+        );
+        let element = LayerElement::from_node(&doc, element).unwrap();
+        assert_eq!(element.components(), 2);
+        assert!(element.issues().is_empty(), "{:?}", element.issues());
+        assert_eq!(element.resolved()[4].value, Some(vec![1.0, 1.0]));
+    }
+
+    /// Returns the data indices and the values of the resolved elements.
+    fn resolved(element: &LayerElement) -> Vec<(Option<i64>, Option<Vec<f64>>)> {
+        element
+            .resolved()
+            .iter()
+            .map(|resolved| (resolved.data_index, resolved.value.clone()))
+            .collect()
+    }
+
+    /// Creates a `ByPolygonVertex` UV element with three UVs and the given
+    /// `UVIndex`.
+    fn indexed_uv(indices: Option<Vec<i32>>) -> LayerElement {
+        let (mut doc, element) = geometry(
+            "LayerElementUV",
+            "ByPolygonVertex",
+            "IndexToDirect",
+            "UV",
+            Attribute::ArrayF64(vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0]),
+        );
+        if let Some(indices) = indices {
+            push(&mut doc, element, "UVIndex", Attribute::ArrayI32(indices));
+        }
+        LayerElement::from_node(&doc, element).unwrap()
+    }
+
+    #[test]
+    fn index_to_direct() {
+        let element = indexed_uv(Some(vec![0, 1, 2, 1, 2, 0]));
+        assert_eq!(element.reference(), Some(ReferenceMode::IndexToDirect));
+        assert_eq!(element.num_data(), 3);
+        assert_eq!(element.num_indices(), Some(6));
+        assert!(element.issues().is_empty(), "{:?}", element.issues());
+        let resolved = resolved(&element);
+        assert_eq!(resolved[3], (Some(1), Some(vec![1.0, 0.0])));
+        assert_eq!(resolved[4], (Some(2), Some(vec![0.0, 1.0])));
+        assert_eq!(element.resolved()[4].polygon, 1);
+        assert_eq!(element.resolved()[4].vertex, 3);
+    }
+
+    #[test]
+    fn out_of_range_indices() {
+        let element = indexed_uv(Some(vec![0, 1, 2, 3, -1, 0]));
+        let resolved = resolved(&element);
+        assert_eq!(resolved[3], (Some(3), None));
+        assert_eq!(resolved[4], (Some(-1), None));
+        assert_eq!(resolved[5], (Some(0), Some(vec![0.0, 0.0])));
+        assert_eq!(
+            element.issues(),
+            ["2 polygon vertices refer to out-of-range elements of `UV`"]
+        );
+    }
+
+    #[test]
+    fn short_index_array() {
+        let element = indexed_uv(Some(vec![0, 1, 2, 1]));
+        let resolved = resolved(&element);
+        assert_eq!(resolved[4], (None, None));
+        assert_eq!(element.issues().len(), 2, "{:?}", element.issues());
+        assert!(element.issues()[0].starts_with("Length mismatch: `UVIndex` has 4 elements but 6"));
+        assert_eq!(
+            element.issues()[1],
+            "2 polygon vertices have no index in `UVIndex`"
+        );
+    }
+
+    #[test]
+    fn missing_index_array() {
+        let element = indexed_uv(None);
+        assert!(element.issues()[0].contains("`UVIndex` is required"));
+        // The data is used directly, and it is too short.
+        assert!(element.issues()[1].starts_with("Length mismatch: `UV` has 3 elements but 6"));
+        assert_eq!(resolved(&element)[2], (Some(2), Some(vec![0.0, 1.0])));
+    }
+
+    #[test]
+    fn by_polygon_vertex_length_mismatch() {
+        let (doc, element) = geometry(
+            "LayerElementNormal",
+            "ByPolygonVertex",
+            "Direct",
+            "Normals",
+            Attribute::ArrayF32([0.0, 0.0, 1.0].repeat(5)),
+        );
+        let element = LayerElement::from_node(&doc, element).unwrap();
+        assert_eq!(element.components(), 3);
+        let resolved = resolved(&element);
+        assert_eq!(resolved[4], (Some(4), Some(vec![0.0, 0.0, 1.0])));
+        assert_eq!(resolved[5], (Some(5), None));
+        assert_eq!(element.issues().len(), 2, "{:?}", element.issues());
+        assert!(element.issues()[0].starts_with("Length mismatch: `Normals` has 5 elements but 6"));
+    }
+
+    #[test]
+    fn all_same() {
+        // `Materials` is data even with `IndexToDirect`, and extra elements
+        // are allowed.
+        let (doc, element) = geometry(
+            "LayerElementMaterial",
+            "AllSame",
+            "IndexToDirect",
+            "Materials",
+            Attribute::ArrayI32(vec![3, 4]),
+        );
+        let element = LayerElement::from_node(&doc, element).unwrap();
+        assert!(element.issues().is_empty(), "{:?}", element.issues());
+        assert_eq!(element.num_indices(), None);
+        let resolved = resolved(&element);
+        assert_eq!(resolved.len(), 6);
+        assert!(resolved
+            .iter()
+            .all(|resolved| *resolved == (Some(0), Some(vec![3.0]))));
+    }
+}
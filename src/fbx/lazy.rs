@@ -0,0 +1,566 @@
+//! Lazy loading of attributes.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    ops::Deref,
+    sync::Arc,
+    time::SystemTime,
+};
+
+use fbxcel::pull_parser::{self as fbxbin, Result};
+
+use crate::fbx::{Attribute, NodeId};
+
+/// Default capacity of the attribute cache in bytes.
+pub const DEFAULT_CACHE_CAPACITY: u64 = 256 * 1024 * 1024;
+
+/// Maximum number of bytes allocated before reading the data of the length
+/// stored in the file.
+const MAX_PREALLOCATED_BYTES: usize = 1 << 20;
+
+/// Location of an attribute in FBX binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttributeLocation {
+    /// Type code.
+    type_code: u8,
+    /// Byte offset of the type code.
+    offset: u64,
+    /// Byte length of the attribute, including the type code.
+    len: u64,
+}
+
+impl AttributeLocation {
+    /// Creates a new `AttributeLocation`.
+    pub fn new(type_code: u8, offset: u64, len: u64) -> Self {
+        Self {
+            type_code,
+            offset,
+            len,
+        }
+    }
+
+    /// Returns the type code (such as `b'd'` for `[f64]`).
+    pub fn type_code(&self) -> u8 {
+        self.type_code
+    }
+
+    /// Returns the byte offset of the attribute.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the byte length of the attribute, including the type code.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns whether the attribute has no bytes.
+    ///
+    /// This is always `false` for valid locations, since they include the
+    /// type code.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// FBX 7.4 attribute loader which defers arrays and binaries.
+///
+/// Scalars and strings are loaded as is, since they are small and used to
+/// index objects and properties.
+/// Arrays and binaries are loaded as empty values of the same type, and
+/// they should be decoded later with [`decode_attribute`].
+#[derive(Debug, Clone)]
+pub struct DeferredAttributeLoader;
+
+impl fbxbin::v7400::LoadAttribute for DeferredAttributeLoader {
+    type Output = Attribute;
+
+    fn expecting(&self) -> String {
+        "any attributes".to_owned()
+    }
+
+    fn load_bool(self, v: bool) -> Result<Self::Output> {
+        Ok(Attribute::SingleBool(v))
+    }
+    fn load_i16(self, v: i16) -> Result<Self::Output> {
+        Ok(Attribute::SingleI16(v))
+    }
+    fn load_i32(self, v: i32) -> Result<Self::Output> {
+        Ok(Attribute::SingleI32(v))
+    }
+    fn load_i64(self, v: i64) -> Result<Self::Output> {
+        Ok(Attribute::SingleI64(v))
+    }
+    fn load_f32(self, v: f32) -> Result<Self::Output> {
+        Ok(Attribute::SingleF32(v))
+    }
+    fn load_f64(self, v: f64) -> Result<Self::Output> {
+        Ok(Attribute::SingleF64(v))
+    }
+    // The parser skips the unread array data.
+    fn load_seq_bool(
+        self,
+        _: impl Iterator<Item = Result<bool>>,
+        _: usize,
+    ) -> Result<Self::Output> {
+        Ok(Attribute::ArrayBool(Vec::new()))
+    }
+    fn load_seq_i32(self, _: impl Iterator<Item = Result<i32>>, _: usize) -> Result<Self::Output> {
+        Ok(Attribute::ArrayI32(Vec::new()))
+    }
+    fn load_seq_i64(self, _: impl Iterator<Item = Result<i64>>, _: usize) -> Result<Self::Output> {
+        Ok(Attribute::ArrayI64(Vec::new()))
+    }
+    fn load_seq_f32(self, _: impl Iterator<Item = Result<f32>>, _: usize) -> Result<Self::Output> {
+        Ok(Attribute::ArrayF32(Vec::new()))
+    }
+    fn load_seq_f64(self, _: impl Iterator<Item = Result<f64>>, _: usize) -> Result<Self::Output> {
+        Ok(Attribute::ArrayF64(Vec::new()))
+    }
+    fn load_binary(self, _: impl io::Read, _: u64) -> Result<Self::Output> {
+        Ok(Attribute::Binary(Vec::new()))
+    }
+    fn load_string(self, mut reader: impl io::Read, len: u64) -> Result<Self::Output> {
+        // The length is not trusted, since the file may be broken.
+        let mut buf = String::with_capacity((len as usize).min(MAX_PREALLOCATED_BYTES));
+        reader.read_to_string(&mut buf)?;
+        Ok(Attribute::String(buf))
+    }
+}
+
+/// Returns whether the attribute is deferred by [`DeferredAttributeLoader`].
+pub fn is_deferred(attr: &Attribute) -> bool {
+    matches!(
+        attr,
+        Attribute::ArrayBool(_)
+            | Attribute::ArrayI32(_)
+            | Attribute::ArrayI64(_)
+            | Attribute::ArrayF32(_)
+            | Attribute::ArrayF64(_)
+            | Attribute::Binary(_)
+    )
+}
+
+/// Decodes the attribute at the given location.
+pub fn decode_attribute<R: Read + Seek>(
+    reader: &mut R,
+    location: &AttributeLocation,
+) -> io::Result<Attribute> {
+    reader.seek(SeekFrom::Start(location.offset))?;
+    let type_code = read_bytes::<1, _>(reader)?[0];
+    if type_code != location.type_code {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Attribute type code mismatch at offset {}: expected {:#04x}, found {:#04x}",
+                location.offset, location.type_code, type_code
+            ),
+        ));
+    }
+    let attr = match type_code {
+        b'C' => Attribute::SingleBool(read_bytes::<1, _>(reader)?[0] & 1 != 0),
+        b'Y' => Attribute::SingleI16(i16::from_le_bytes(read_bytes(reader)?)),
+        b'I' => Attribute::SingleI32(i32::from_le_bytes(read_bytes(reader)?)),
+        b'L' => Attribute::SingleI64(i64::from_le_bytes(read_bytes(reader)?)),
+        b'F' => Attribute::SingleF32(f32::from_le_bytes(read_bytes(reader)?)),
+        b'D' => Attribute::SingleF64(f64::from_le_bytes(read_bytes(reader)?)),
+        b'S' => {
+            let bytes = read_prefixed_bytes(reader)?;
+            let s = String::from_utf8(bytes)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            Attribute::String(s)
+        }
+        b'R' => Attribute::Binary(read_prefixed_bytes(reader)?),
+        b'b' => Attribute::ArrayBool(decode_array(reader, |[v]| v & 1 != 0)?),
+        b'i' => Attribute::ArrayI32(decode_array(reader, i32::from_le_bytes)?),
+        b'l' => Attribute::ArrayI64(decode_array(reader, i64::from_le_bytes)?),
+        b'f' => Attribute::ArrayF32(decode_array(reader, f32::from_le_bytes)?),
+        b'd' => Attribute::ArrayF64(decode_array(reader, f64::from_le_bytes)?),
+        v => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Unknown attribute type code {:#04x} at offset {}",
+                    v, location.offset
+                ),
+            ));
+        }
+    };
+    Ok(attr)
+}
+
+/// Decodes the array attribute after the type code.
+fn decode_array<R, T, F, const N: usize>(reader: &mut R, element: F) -> io::Result<Vec<T>>
+where
+    R: Read,
+    F: Fn([u8; N]) -> T,
+{
+    let num_elements = u32::from_le_bytes(read_bytes(reader)?) as usize;
+    let encoding = u32::from_le_bytes(read_bytes(reader)?);
+    let on_disk_len = u32::from_le_bytes(read_bytes(reader)?);
+    let len = num_elements.checked_mul(N).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Array of {} elements is too large", num_elements),
+        )
+    })?;
+    if encoding == 0 && len != on_disk_len as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Raw array has {} bytes but expected {} elements of {} bytes",
+                on_disk_len, num_elements, N
+            ),
+        ));
+    }
+    // The element count is not trusted, since the file may be broken.
+    let mut data = Vec::with_capacity(len.min(MAX_PREALLOCATED_BYTES));
+    let mut compressed = reader.take(u64::from(on_disk_len));
+    match encoding {
+        0 => compressed.read_to_end(&mut data)?,
+        // Reading one extra byte is enough to detect too long data.
+        1 => libflate::zlib::Decoder::new(compressed)?
+            .take(len as u64 + 1)
+            .read_to_end(&mut data)?,
+        v => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown array encoding {}", v),
+            ));
+        }
+    };
+    if data.len() != len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Array has {} bytes but expected {} elements of {} bytes",
+                data.len(),
+                num_elements,
+                N
+            ),
+        ));
+    }
+    Ok(data
+        .chunks_exact(N)
+        .map(|chunk| {
+            let mut bytes = [0; N];
+            bytes.copy_from_slice(chunk);
+            element(bytes)
+        })
+        .collect())
+}
+
+/// Reads the byte array prefixed by the `u32` length.
+fn read_prefixed_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let len = u32::from_le_bytes(read_bytes(reader)?);
+    let mut buf = Vec::with_capacity((len as usize).min(MAX_PREALLOCATED_BYTES));
+    reader.take(u64::from(len)).read_to_end(&mut buf)?;
+    if buf.len() != len as usize {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(buf)
+}
+
+/// Reads the given number of bytes.
+fn read_bytes<const N: usize, R: Read>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Attributes of a node, possibly decoded on demand.
+#[derive(Debug, Clone)]
+pub enum LoadedAttributes<'a> {
+    /// Attributes stored in the document.
+    Borrowed(&'a [Attribute]),
+    /// Attributes decoded from the file.
+    Decoded(Arc<Vec<Attribute>>),
+}
+
+impl Deref for LoadedAttributes<'_> {
+    type Target = [Attribute];
+
+    fn deref(&self) -> &[Attribute] {
+        match self {
+            LoadedAttributes::Borrowed(attrs) => attrs,
+            LoadedAttributes::Decoded(attrs) => attrs,
+        }
+    }
+}
+
+/// Source file of lazily loaded attributes.
+///
+/// The file is kept open, so replacing the file (for example, by
+/// [`save_binary_file`][`crate::fbx::save_binary_file`]) does not affect the
+/// document.
+/// Modifications of the file in place are detected by the length and the
+/// modification time, and decoding fails after that.
+#[derive(Debug, Clone)]
+pub(crate) struct LazySource {
+    /// FBX binary file.
+    file: Arc<File>,
+    /// Byte length of the file at loading.
+    len: u64,
+    /// Modification time of the file at loading, if available.
+    modified: Option<SystemTime>,
+    /// Cache of decoded attributes.
+    cache: RefCell<AttributeCache>,
+}
+
+impl LazySource {
+    /// Creates a new `LazySource`.
+    pub(crate) fn new(file: File, cache_capacity: u64) -> io::Result<Self> {
+        let metadata = file.metadata()?;
+        Ok(Self {
+            file: Arc::new(file),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            cache: RefCell::new(AttributeCache::new(cache_capacity)),
+        })
+    }
+
+    /// Returns the attributes of the node, decoding the deferred ones.
+    ///
+    /// `locations` should have the same length as `attrs`.
+    pub(crate) fn load(
+        &self,
+        node_id: NodeId,
+        attrs: &[Attribute],
+        locations: &[AttributeLocation],
+    ) -> io::Result<Arc<Vec<Attribute>>> {
+        if let Some(attrs) = self.cache.borrow_mut().get(node_id) {
+            return Ok(attrs);
+        }
+        let metadata = self.file.metadata()?;
+        if metadata.len() != self.len || metadata.modified().ok() != self.modified {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the source file has been modified since loading",
+            ));
+        }
+        let mut reader = io::BufReader::new(&*self.file);
+        let decoded = attrs
+            .iter()
+            .zip(locations)
+            .map(|(attr, location)| {
+                if is_deferred(attr) {
+                    decode_attribute(&mut reader, location)
+                } else {
+                    Ok(attr.clone())
+                }
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let size = decoded.iter().map(approximate_size).sum();
+        let decoded = Arc::new(decoded);
+        self.cache
+            .borrow_mut()
+            .insert(node_id, decoded.clone(), size);
+        Ok(decoded)
+    }
+
+    /// Discards the cached attributes.
+    ///
+    /// This should be called when the nodes are edited, since the cache is
+    /// indexed by node IDs and has copies of non-deferred attributes.
+    pub(crate) fn clear_cache(&self) {
+        self.cache.borrow_mut().clear();
+    }
+}
+
+/// Returns the approximate byte size of the attribute in memory.
+fn approximate_size(attr: &Attribute) -> u64 {
+    let element_size = match attr {
+        Attribute::ArrayI32(_) | Attribute::ArrayF32(_) => 4,
+        Attribute::ArrayI64(_) | Attribute::ArrayF64(_) => 8,
+        Attribute::String(s) => return s.len() as u64,
+        _ => 1,
+    };
+    attr.array_len().map_or(8, |len| len as u64 * element_size)
+}
+
+/// Entry of the attribute cache.
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    /// Decoded attributes.
+    attrs: Arc<Vec<Attribute>>,
+    /// Approximate byte size of the attributes.
+    size: u64,
+    /// Tick of the last use.
+    last_used: u64,
+}
+
+/// LRU cache of decoded attributes, bounded by the total byte size.
+#[derive(Debug, Clone)]
+struct AttributeCache {
+    /// Entries.
+    entries: HashMap<NodeId, CacheEntry>,
+    /// Capacity in bytes.
+    capacity: u64,
+    /// Total byte size of the entries.
+    used: u64,
+    /// Current tick.
+    tick: u64,
+}
+
+impl AttributeCache {
+    /// Creates a new empty cache.
+    fn new(capacity: u64) -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity,
+            used: 0,
+            tick: 0,
+        }
+    }
+
+    /// Returns the cached attributes and marks them as recently used.
+    fn get(&mut self, node_id: NodeId) -> Option<Arc<Vec<Attribute>>> {
+        self.tick += 1;
+        let entry = self.entries.get_mut(&node_id)?;
+        entry.last_used = self.tick;
+        Some(entry.attrs.clone())
+    }
+
+    /// Removes all entries.
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.used = 0;
+    }
+
+    /// Inserts the attributes, evicting least recently used entries.
+    ///
+    /// The new entry is kept even if it alone exceeds the capacity.
+    fn insert(&mut self, node_id: NodeId, attrs: Arc<Vec<Attribute>>, size: u64) {
+        while self.used + size > self.capacity {
+            let lru = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(&id, _)| id);
+            match lru.and_then(|id| self.entries.remove(&id)) {
+                Some(evicted) => self.used -= evicted.size,
+                None => break,
+            }
+        }
+        self.tick += 1;
+        self.used += size;
+        let entry = CacheEntry {
+            attrs,
+            size,
+            last_used: self.tick,
+        };
+        if let Some(old) = self.entries.insert(node_id, entry) {
+            self.used -= old.size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    /// Decodes the attribute bytes starting with the type code.
+    fn decode(bytes: &[u8]) -> io::Result<Attribute> {
+        let location = AttributeLocation::new(bytes[0], 0, bytes.len() as u64);
+        decode_attribute(&mut io::Cursor::new(bytes), &location)
+    }
+
+    /// Returns the bytes of the array attribute with the given header.
+    fn array(type_code: u8, num_elements: u32, encoding: u32, data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![type_code];
+        bytes.extend_from_slice(&num_elements.to_le_bytes());
+        bytes.extend_from_slice(&encoding.to_le_bytes());
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    /// Compresses the data with zlib.
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut encoder = libflate::zlib::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(data).unwrap();
+        encoder.finish().into_result().unwrap()
+    }
+
+    #[test]
+    fn arrays() {
+        let data: Vec<u8> = [1i32, -2, 3].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let expected = Attribute::ArrayI32(vec![1, -2, 3]);
+        assert_eq!(decode(&array(b'i', 3, 0, &data)).unwrap(), expected);
+        assert_eq!(decode(&array(b'i', 3, 1, &zlib(&data))).unwrap(), expected);
+    }
+
+    #[test]
+    fn broken_array_lengths() {
+        let data = 1.5f64.to_le_bytes();
+        for bytes in &[
+            // Raw data shorter than the elements.
+            array(b'd', u32::MAX, 0, &data),
+            // Compressed data shorter or longer than the elements.
+            array(b'd', u32::MAX, 1, &zlib(&data)),
+            array(b'd', 2, 1, &zlib(&data)),
+            array(b'd', 0, 1, &zlib(&data)),
+            // Unknown encoding.
+            array(b'd', u32::MAX, 2, &data),
+        ] {
+            let err = decode(bytes).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{}", err);
+        }
+    }
+
+    #[test]
+    fn broken_string_length() {
+        let mut bytes = vec![b'S'];
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(b"Model");
+        let err = decode(&bytes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn changed_source_file() {
+        use crate::fbx::{load_file_lazy, save_binary_file, Document, WriteOptions};
+
+        let mut doc = Document::new(fbxcel::low::FbxVersion::V7_4);
+        let vertices = doc.push_node(
+            None,
+            "Vertices".to_owned(),
+            vec![Attribute::ArrayF64(vec![0.5, 1.0])],
+            0,
+        );
+        let path =
+            std::env::temp_dir().join(format!("fbx-tree-view-lazy-{}.fbx", std::process::id()));
+        save_binary_file(&doc, &path, &WriteOptions::default()).unwrap();
+        // No cache, so that the attributes are decoded every time.
+        let lazy = load_file_lazy(&path, 0, |_| true)
+            .document
+            .expect("document should be loaded");
+        assert!(lazy.is_lazy());
+
+        // Replacing the file does not affect the lazy document.
+        doc.set_attribute(vertices, 0, Attribute::ArrayF64(vec![2.0; 100]));
+        save_binary_file(&doc, &path, &WriteOptions::default()).unwrap();
+        assert_eq!(
+            &*lazy.load_attributes(vertices).unwrap(),
+            [Attribute::ArrayF64(vec![0.5, 1.0])]
+        );
+
+        // Modifying the file in place is detected.
+        let lazy = load_file_lazy(&path, 0, |_| true)
+            .document
+            .expect("document should be loaded");
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(&[0; 16]))
+            .unwrap();
+        let err = lazy.load_attributes(vertices).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,299 @@
+//! Mesh geometry.
+
+use crate::fbx::{Attribute, Document, NodeId};
+
+/// Polygon.
+#[derive(Debug, Clone)]
+pub struct Polygon {
+    /// Index of the first polygon vertex in `PolygonVertexIndex`.
+    first_polygon_vertex: usize,
+    /// Decoded vertex indices.
+    vertices: Vec<i64>,
+    /// Whether the polygon has out-of-range vertex indices.
+    out_of_range: bool,
+}
+
+impl Polygon {
+    /// Creates a new polygon.
+    fn new(first_polygon_vertex: usize, vertices: Vec<i64>, num_vertices: usize) -> Self {
+        let out_of_range = vertices
+            .iter()
+            .any(|&v| v < 0 || v as u64 >= num_vertices as u64);
+        Self {
+            first_polygon_vertex,
+            vertices,
+            out_of_range,
+        }
+    }
+
+    /// Returns the index of the first polygon vertex in
+    /// `PolygonVertexIndex`.
+    pub fn first_polygon_vertex(&self) -> usize {
+        self.first_polygon_vertex
+    }
+
+    /// Returns the decoded vertex indices.
+    pub fn vertices(&self) -> &[i64] {
+        &self.vertices
+    }
+
+    /// Returns whether the polygon has out-of-range vertex indices.
+    pub fn has_out_of_range_index(&self) -> bool {
+        self.out_of_range
+    }
+
+    /// Returns the number of triangles after triangulation as a fan.
+    pub fn num_triangles(&self) -> usize {
+        self.vertices.len().saturating_sub(2)
+    }
+}
+
+/// Mesh decoded from a `Geometry` node.
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    /// Vertex positions.
+    vertices: Vec<[f64; 3]>,
+    /// Polygons.
+    polygons: Vec<Polygon>,
+    /// Number of polygon vertices (length of `PolygonVertexIndex`).
+    num_polygon_vertices: usize,
+    /// Problems found while decoding.
+    issues: Vec<String>,
+}
+
+impl Mesh {
+    /// Decodes the mesh of the given `Geometry` node.
+    ///
+    /// Returns `None` if the node is not a `Geometry` node with `Vertices`.
+    pub fn from_geometry(document: &Document, geometry: NodeId) -> Option<Self> {
+        let node = document.node(geometry);
+        if node.name() != "Geometry" {
+            return None;
+        }
+        let mut issues = Vec::new();
+
+        let raw_vertices = child_f64_array(document, geometry, "Vertices")?;
+        if raw_vertices.len() % 3 != 0 {
+            issues.push(format!(
+                "`Vertices` has {} elements, which is not a multiple of 3",
+                raw_vertices.len()
+            ));
+        }
+        let vertices: Vec<_> = raw_vertices
+            .chunks_exact(3)
+            .map(|v| [v[0], v[1], v[2]])
+            .collect();
+
+        let indices = child_i64_array(document, geometry, "PolygonVertexIndex");
+        if indices.is_none() {
+            issues.push("`PolygonVertexIndex` is not found".to_owned());
+        }
+        let indices = indices.unwrap_or_default();
+        let mut polygons = Vec::new();
+        let mut current = Vec::new();
+        let mut first_polygon_vertex = 0;
+        for (i, &index) in indices.iter().enumerate() {
+            // The last index of each polygon is stored as bitwise NOT.
+            let is_end = index < 0;
+            current.push(if is_end { !index } else { index });
+            if is_end {
+                polygons.push(Polygon::new(
+                    first_polygon_vertex,
+                    std::mem::take(&mut current),
+                    vertices.len(),
+                ));
+                first_polygon_vertex = i + 1;
+            }
+        }
+        if !current.is_empty() {
+            issues.push(format!(
+                "The last polygon (from polygon vertex {}) is not terminated by a negative index",
+                first_polygon_vertex
+            ));
+            polygons.push(Polygon::new(first_polygon_vertex, current, vertices.len()));
+        }
+
+        let num_out_of_range = polygons.iter().filter(|p| p.out_of_range).count();
+        if num_out_of_range != 0 {
+            issues.push(format!(
+                "{} polygons have out-of-range vertex indices (number of vertices: {})",
+                num_out_of_range,
+                vertices.len()
+            ));
+        }
+        let num_degenerate = polygons.iter().filter(|p| p.vertices.len() < 3).count();
+        if num_degenerate != 0 {
+            issues.push(format!(
+                "{} polygons have less than 3 vertices",
+                num_degenerate
+            ));
+        }
+
+        Some(Self {
+            vertices,
+            polygons,
+            num_polygon_vertices: indices.len(),
+            issues,
+        })
+    }
+
+    /// Returns the vertex positions.
+    pub fn vertices(&self) -> &[[f64; 3]] {
+        &self.vertices
+    }
+
+    /// Returns the polygons.
+    pub fn polygons(&self) -> &[Polygon] {
+        &self.polygons
+    }
+
+    /// Returns the number of polygon vertices (length of
+    /// `PolygonVertexIndex`).
+    pub fn num_polygon_vertices(&self) -> usize {
+        self.num_polygon_vertices
+    }
+
+    /// Returns the number of triangles after triangulation as fans.
+    pub fn num_triangles(&self) -> usize {
+        self.polygons.iter().map(Polygon::num_triangles).sum()
+    }
+
+    /// Returns the problems found while decoding.
+    pub fn issues(&self) -> &[String] {
+        &self.issues
+    }
+}
+
+/// Returns the first child node with the given name.
+pub(crate) fn child_by_name(document: &Document, parent: NodeId, name: &str) -> Option<NodeId> {
+    document
+        .node(parent)
+        .children()
+        .iter()
+        .cloned()
+        .find(|&child| document.node(child).name() == name)
+}
+
+/// Returns the first attribute of the given child node as `f64` array.
+///
+/// Booleans (such as `Visibility` of `LayerElementVisibility`) are 0 or 1.
+pub(crate) fn child_f64_array(document: &Document, parent: NodeId, name: &str) -> Option<Vec<f64>> {
+    let child = child_by_name(document, parent, name)?;
+    match document.load_attributes(child).ok()?.first()? {
+        Attribute::ArrayF64(arr) => Some(arr.clone()),
+        Attribute::ArrayBool(arr) => Some(arr.iter().map(|&v| f64::from(u8::from(v))).collect()),
+        Attribute::ArrayF32(arr) => Some(arr.iter().map(|&v| f64::from(v)).collect()),
+        Attribute::ArrayI32(arr) => Some(arr.iter().map(|&v| f64::from(v)).collect()),
+        Attribute::ArrayI64(arr) => Some(arr.iter().map(|&v| v as f64).collect()),
+        _ => None,
+    }
+}
+
+/// Returns the first attribute of the given child node as `i64` array.
+pub(crate) fn child_i64_array(document: &Document, parent: NodeId, name: &str) -> Option<Vec<i64>> {
+    let child = child_by_name(document, parent, name)?;
+    match document.load_attributes(child).ok()?.first()? {
+        Attribute::ArrayI32(arr) => Some(arr.iter().map(|&v| i64::from(v)).collect()),
+        Attribute::ArrayI64(arr) => Some(arr.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes a geometry with the given vertices and indices.
+    fn mesh(vertices: Vec<f64>, indices: Vec<i32>) -> Mesh {
+        let mut doc = Document::default();
+        let geometry = doc.push_node(None, "Geometry".to_owned(), vec![], 0);
+        doc.push_node(
+            Some(geometry),
+            "Vertices".to_owned(),
+            vec![Attribute::ArrayF64(vertices)],
+            0,
+        );
+        doc.push_node(
+            Some(geometry),
+            "PolygonVertexIndex".to_owned(),
+            vec![Attribute::ArrayI32(indices)],
+            0,
+        );
+        Mesh::from_geometry(&doc, geometry).unwrap()
+    }
+
+    /// Returns a quad's vertices.
+    fn quad() -> Vec<f64> {
+        vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0]
+    }
+
+    #[test]
+    fn polygons() {
+        // A quad and a triangle, terminated by bitwise NOT.
+        let mesh = mesh(quad(), vec![0, 1, 2, !3, 0, 2, !3]);
+        assert_eq!(mesh.vertices().len(), 4);
+        assert_eq!(mesh.vertices()[2], [1.0, 1.0, 0.0]);
+        assert_eq!(mesh.polygons().len(), 2);
+        assert_eq!(mesh.polygons()[0].vertices(), [0, 1, 2, 3]);
+        assert_eq!(mesh.polygons()[0].first_polygon_vertex(), 0);
+        assert_eq!(mesh.polygons()[1].vertices(), [0, 2, 3]);
+        assert_eq!(mesh.polygons()[1].first_polygon_vertex(), 4);
+        assert_eq!(mesh.num_polygon_vertices(), 7);
+        assert_eq!(mesh.num_triangles(), 3);
+        assert!(mesh.issues().is_empty(), "{:?}", mesh.issues());
+    }
+
+    #[test]
+    fn unterminated_last_polygon() {
+        let mesh = mesh(quad(), vec![0, 1, !2, 0, 2, 3]);
+        assert_eq!(mesh.polygons().len(), 2);
+        assert_eq!(mesh.polygons()[1].vertices(), [0, 2, 3]);
+        assert_eq!(mesh.issues().len(), 1);
+        assert!(mesh.issues()[0].contains("not terminated"));
+    }
+
+    #[test]
+    fn out_of_range_indices() {
+        let mesh = mesh(quad(), vec![0, 1, !4, 5, 2, !3, 0, 1, !2]);
+        let flags: Vec<_> = mesh
+            .polygons()
+            .iter()
+            .map(Polygon::has_out_of_range_index)
+            .collect();
+        assert_eq!(flags, [true, true, false]);
+        assert_eq!(mesh.issues().len(), 1);
+        assert!(mesh.issues()[0].starts_with("2 polygons"));
+
+        // Decoded indices are never negative, but they are checked anyway.
+        assert!(Polygon::new(0, vec![0, -1, 2], 4).has_out_of_range_index());
+        assert!(!Polygon::new(0, vec![0, 3, 2], 4).has_out_of_range_index());
+    }
+
+    #[test]
+    fn degenerate_polygons() {
+        let mesh = mesh(quad(), vec![!0, 1, !2, 0, 1, !2]);
+        let sizes: Vec<_> = mesh.polygons().iter().map(|p| p.vertices().len()).collect();
+        assert_eq!(sizes, [1, 2, 3]);
+        assert_eq!(mesh.num_triangles(), 1);
+        assert_eq!(mesh.issues().len(), 1);
+        assert!(mesh.issues()[0].contains("less than 3 vertices"));
+    }
+
+    #[test]
+    fn broken_vertices() {
+        let mut vertices = quad();
+        vertices.push(2.0);
+        let mesh = mesh(vertices, vec![0, 1, !2]);
+        assert_eq!(mesh.vertices().len(), 4);
+        assert!(mesh.issues()[0].contains("not a multiple of 3"));
+    }
+
+    #[test]
+    fn not_geometry() {
+        let mut doc = Document::default();
+        let model = doc.push_node(None, "Model".to_owned(), vec![], 0);
+        assert!(Mesh::from_geometry(&doc, model).is_none());
+        let geometry = doc.push_node(None, "Geometry".to_owned(), vec![], 0);
+        assert!(Mesh::from_geometry(&doc, geometry).is_none());
+    }
+}
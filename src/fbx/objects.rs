@@ -0,0 +1,405 @@
+//! Index of FBX objects and connections.
+
+use std::collections::HashMap;
+
+use crate::fbx::{Attribute, Document, NodeId};
+
+/// Object.
+///
+/// This is a child node of the top-level `Objects` node.
+#[derive(Debug, Clone)]
+pub struct Object {
+    /// Object ID.
+    id: i64,
+    /// Node of the object.
+    node: NodeId,
+    /// Object name.
+    name: String,
+    /// Object class (such as `Model` and `Geometry`).
+    class: String,
+    /// Object subclass (such as `Mesh`).
+    subclass: String,
+    /// Indices of connections whose source is the object.
+    parents: Vec<usize>,
+    /// Indices of connections whose destination is the object.
+    children: Vec<usize>,
+}
+
+impl Object {
+    /// Returns the object ID.
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// Returns the node of the object.
+    pub fn node(&self) -> NodeId {
+        self.node
+    }
+
+    /// Returns the object name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the object class.
+    ///
+    /// This is the class in the name attribute (`Name\x00\x01Class`), or the
+    /// node name if the attribute has no class.
+    pub fn class(&self) -> &str {
+        &self.class
+    }
+
+    /// Returns the object subclass.
+    pub fn subclass(&self) -> &str {
+        &self.subclass
+    }
+}
+
+/// Connection between objects (or properties of objects).
+///
+/// This is a `C` node under the top-level `Connections` node.
+#[derive(Debug, Clone)]
+pub struct Connection {
+    /// Node of the connection.
+    node: NodeId,
+    /// Connection type (such as `OO` and `OP`).
+    kind: String,
+    /// Source (child) object ID.
+    source: i64,
+    /// Destination (parent) object ID.
+    destination: i64,
+    /// Property name of the source, for `PO` and `PP` connections.
+    source_property: Option<String>,
+    /// Property name of the destination, for `OP` and `PP` connections.
+    destination_property: Option<String>,
+}
+
+impl Connection {
+    /// Returns the node of the connection.
+    pub fn node(&self) -> NodeId {
+        self.node
+    }
+
+    /// Returns the connection type (such as `OO` and `OP`).
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Returns the source (child) object ID.
+    pub fn source(&self) -> i64 {
+        self.source
+    }
+
+    /// Returns the destination (parent) object ID.
+    ///
+    /// `0` means the root of the scene.
+    pub fn destination(&self) -> i64 {
+        self.destination
+    }
+
+    /// Returns the property name of the source, for `PO` and `PP`
+    /// connections.
+    pub fn source_property(&self) -> Option<&str> {
+        self.source_property.as_deref()
+    }
+
+    /// Returns the property name of the destination, for `OP` and `PP`
+    /// connections.
+    pub fn destination_property(&self) -> Option<&str> {
+        self.destination_property.as_deref()
+    }
+}
+
+/// Index of objects and connections of a document.
+#[derive(Debug, Default, Clone)]
+pub struct ObjectIndex {
+    /// Objects in order of appearance.
+    objects: Vec<Object>,
+    /// Object indices by object ID.
+    by_id: HashMap<i64, usize>,
+    /// Connections in order of appearance.
+    connections: Vec<Connection>,
+}
+
+impl ObjectIndex {
+    /// Creates an index of objects and connections of the given document.
+    ///
+    /// Nodes with unexpected attributes are ignored.
+    pub fn new(document: &Document) -> Self {
+        let mut index = Self::default();
+        let toplevel = |name: &'static str| {
+            document
+                .toplevel_nodes()
+                .iter()
+                .cloned()
+                .filter(move |&id| document.node(id).name() == name)
+        };
+
+        for objects in toplevel("Objects") {
+            for &node_id in document.node(objects).children() {
+                let node = document.node(node_id);
+                let id = match node.attributes().first() {
+                    Some(&Attribute::SingleI64(id)) => id,
+                    _ => continue,
+                };
+                let (name, class) = match node.attributes().get(1) {
+                    Some(Attribute::String(s)) => split_object_name(s),
+                    _ => ("", ""),
+                };
+                let class = if class.is_empty() { node.name() } else { class };
+                let subclass = match node.attributes().get(2) {
+                    Some(Attribute::String(s)) => s.as_str(),
+                    _ => "",
+                };
+                index.by_id.insert(id, index.objects.len());
+                index.objects.push(Object {
+                    id,
+                    node: node_id,
+                    name: name.to_owned(),
+                    class: class.to_owned(),
+                    subclass: subclass.to_owned(),
+                    parents: Vec::new(),
+                    children: Vec::new(),
+                });
+            }
+        }
+
+        for connections in toplevel("Connections") {
+            for &node_id in document.node(connections).children() {
+                let node = document.node(node_id);
+                if node.name() != "C" {
+                    continue;
+                }
+                let connection = match parse_connection(node_id, node.attributes()) {
+                    Some(v) => v,
+                    None => continue,
+                };
+                let conn_index = index.connections.len();
+                if let Some(&obj) = index.by_id.get(&connection.source) {
+                    index.objects[obj].parents.push(conn_index);
+                }
+                if let Some(&obj) = index.by_id.get(&connection.destination) {
+                    index.objects[obj].children.push(conn_index);
+                }
+                index.connections.push(connection);
+            }
+        }
+
+        index
+    }
+
+    /// Returns the objects in order of appearance.
+    pub fn objects(&self) -> &[Object] {
+        &self.objects
+    }
+
+    /// Returns the object with the given ID.
+    pub fn object(&self, id: i64) -> Option<&Object> {
+        self.by_id.get(&id).map(|&index| &self.objects[index])
+    }
+
+    /// Returns the connections in order of appearance.
+    pub fn connections(&self) -> &[Connection] {
+        &self.connections
+    }
+
+    /// Returns the connections to the parents (destinations) of the object.
+    pub fn parents<'a>(&'a self, object: &'a Object) -> impl Iterator<Item = &'a Connection> {
+        object.parents.iter().map(move |&i| &self.connections[i])
+    }
+
+    /// Returns the connections to the children (sources) of the object.
+    pub fn children<'a>(&'a self, object: &'a Object) -> impl Iterator<Item = &'a Connection> {
+        object.children.iter().map(move |&i| &self.connections[i])
+    }
+}
+
+/// Splits the object name attribute into the name and the class.
+///
+/// FBX binary uses `Name\x00\x01Class`.
+pub(crate) fn split_object_name(s: &str) -> (&str, &str) {
+    match s.find("\u{0}\u{1}") {
+        Some(pos) => (&s[..pos], &s[(pos + 2)..]),
+        None => (s, ""),
+    }
+}
+
+/// Parses the attributes of a `C` node.
+fn parse_connection(node: NodeId, attrs: &[Attribute]) -> Option<Connection> {
+    let string = |index: usize| match attrs.get(index) {
+        Some(Attribute::String(s)) => Some(s.clone()),
+        _ => None,
+    };
+    let id = |index: usize| match attrs.get(index) {
+        Some(&Attribute::SingleI64(v)) => Some(v),
+        Some(&Attribute::SingleI32(v)) => Some(i64::from(v)),
+        _ => None,
+    };
+    let kind = string(0)?;
+    let (source_property, destination_property) = match kind.as_str() {
+        "OP" => (None, string(3)),
+        "PO" => (string(3), None),
+        "PP" => (string(3), string(4)),
+        _ => (None, None),
+    };
+    Some(Connection {
+        node,
+        source: id(1)?,
+        destination: id(2)?,
+        kind,
+        source_property,
+        destination_property,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a string attribute.
+    fn string(s: &str) -> Attribute {
+        Attribute::String(s.to_owned())
+    }
+
+    /// Creates a document with the given objects and connections.
+    fn document(objects: &[(&str, i64, &str)], connections: &[Vec<Attribute>]) -> Document {
+        let mut doc = Document::default();
+        let parent = doc.push_node(None, "Objects".to_owned(), vec![], 0);
+        for &(class, id, name) in objects {
+            doc.push_node(
+                Some(parent),
+                class.to_owned(),
+                vec![Attribute::SingleI64(id), string(name), string("Mesh")],
+                0,
+            );
+        }
+        let parent = doc.push_node(None, "Connections".to_owned(), vec![], 0);
+        for attrs in connections {
+            doc.push_node(Some(parent), "C".to_owned(), attrs.clone(), 0);
+        }
+        doc
+    }
+
+    /// Returns the attributes of a connection.
+    fn connection(kind: &str, source: i64, destination: i64, props: &[&str]) -> Vec<Attribute> {
+        let mut attrs = vec![
+            string(kind),
+            Attribute::SingleI64(source),
+            Attribute::SingleI64(destination),
+        ];
+        attrs.extend(props.iter().map(|&s| string(s)));
+        attrs
+    }
+
+    #[test]
+    fn objects() {
+        let doc = document(
+            &[
+                ("Model", 10, "Cube\u{0}\u{1}Model"),
+                ("Geometry", 20, "NoClass"),
+            ],
+            &[],
+        );
+        let index = ObjectIndex::new(&doc);
+        assert_eq!(index.objects().len(), 2);
+        let model = index.object(10).unwrap();
+        assert_eq!(model.name(), "Cube");
+        assert_eq!(model.class(), "Model");
+        assert_eq!(model.subclass(), "Mesh");
+        // The node name is used if the name has no class.
+        let geometry = index.object(20).unwrap();
+        assert_eq!(geometry.name(), "NoClass");
+        assert_eq!(geometry.class(), "Geometry");
+        assert!(index.object(30).is_none());
+    }
+
+    #[test]
+    fn connection_kinds() {
+        let doc = document(
+            &[],
+            &[
+                connection("OO", 1, 2, &[]),
+                connection("OP", 1, 2, &["DiffuseColor"]),
+                connection("PO", 1, 2, &["Lcl Translation"]),
+                connection("PP", 1, 2, &["d|X", "Lcl Translation"]),
+            ],
+        );
+        let index = ObjectIndex::new(&doc);
+        let props: Vec<_> = index
+            .connections()
+            .iter()
+            .map(|c| (c.kind(), c.source_property(), c.destination_property()))
+            .collect();
+        assert_eq!(
+            props,
+            [
+                ("OO", None, None),
+                ("OP", None, Some("DiffuseColor")),
+                ("PO", Some("Lcl Translation"), None),
+                ("PP", Some("d|X"), Some("Lcl Translation")),
+            ]
+        );
+        assert!(index
+            .connections()
+            .iter()
+            .all(|c| c.source() == 1 && c.destination() == 2));
+    }
+
+    #[test]
+    fn broken_connections() {
+        let doc = document(
+            &[],
+            &[
+                // i32 IDs are accepted.
+                vec![
+                    string("OO"),
+                    Attribute::SingleI32(1),
+                    Attribute::SingleI32(2),
+                ],
+                vec![string("OO"), Attribute::SingleI64(1)],
+                vec![Attribute::SingleI64(1), Attribute::SingleI64(2)],
+                vec![string("OO"), string("1"), Attribute::SingleI64(2)],
+            ],
+        );
+        let index = ObjectIndex::new(&doc);
+        assert_eq!(index.connections().len(), 1);
+        assert_eq!(index.connections()[0].destination(), 2);
+    }
+
+    #[test]
+    fn parents_and_children() {
+        let doc = document(
+            &[
+                ("Model", 10, "Cube\u{0}\u{1}Model"),
+                ("Geometry", 20, "Cube\u{0}\u{1}Geometry"),
+                ("Material", 30, "Red\u{0}\u{1}Material"),
+            ],
+            &[
+                // The root is not an object.
+                connection("OO", 10, 0, &[]),
+                connection("OO", 20, 10, &[]),
+                connection("OO", 30, 10, &[]),
+                // Unknown IDs.
+                connection("OO", 99, 10, &[]),
+                connection("OO", 20, 98, &[]),
+            ],
+        );
+        let index = ObjectIndex::new(&doc);
+        assert_eq!(index.connections().len(), 5);
+        assert!(index.object(0).is_none());
+
+        let model = index.object(10).unwrap();
+        let parents: Vec<_> = index.parents(model).map(Connection::destination).collect();
+        assert_eq!(parents, [0]);
+        let children: Vec<_> = index.children(model).map(Connection::source).collect();
+        assert_eq!(children, [20, 30, 99]);
+
+        let geometry = index.object(20).unwrap();
+        let parents: Vec<_> = index
+            .parents(geometry)
+            .map(Connection::destination)
+            .collect();
+        assert_eq!(parents, [10, 98]);
+        assert_eq!(index.children(geometry).count(), 0);
+    }
+}
@@ -0,0 +1,300 @@
+//! Properties in `Properties70` nodes.
+
+use crate::fbx::{Attribute, Document, NodeId};
+
+/// Property.
+///
+/// This is a `P` node in a `Properties70` node, which has attributes
+/// `name, type, label, flags, values...`.
+#[derive(Debug, Clone)]
+pub struct Property<'a> {
+    /// Node of the property.
+    node: NodeId,
+    /// Property name.
+    name: &'a str,
+    /// Property type (such as `Lcl Translation` and `double`).
+    type_name: &'a str,
+    /// Property label (subtype, such as `Number`).
+    label: &'a str,
+    /// Property flags (such as `A+U`).
+    flags: &'a str,
+    /// Values.
+    values: &'a [Attribute],
+}
+
+impl<'a> Property<'a> {
+    /// Parses the given `P` node.
+    ///
+    /// Returns `None` if the node does not have string attributes for the
+    /// name, type, label and flags.
+    pub fn from_node(document: &'a Document, node: NodeId) -> Option<Self> {
+        let attrs = document.node(node).attributes();
+        let string = |index: usize| match attrs.get(index) {
+            Some(Attribute::String(s)) => Some(s.as_str()),
+            _ => None,
+        };
+        Some(Self {
+            node,
+            name: string(0)?,
+            type_name: string(1)?,
+            label: string(2)?,
+            flags: string(3)?,
+            values: &attrs[4..],
+        })
+    }
+
+    /// Returns the node of the property.
+    pub fn node(&self) -> NodeId {
+        self.node
+    }
+
+    /// Returns the property name.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the property type.
+    pub fn type_name(&self) -> &'a str {
+        self.type_name
+    }
+
+    /// Returns the property label (subtype).
+    pub fn label(&self) -> &'a str {
+        self.label
+    }
+
+    /// Returns the raw property flags.
+    pub fn flags(&self) -> &'a str {
+        self.flags
+    }
+
+    /// Returns the decoded property flags, such as `animatable, animated`.
+    pub fn flags_description(&self) -> String {
+        let mut descriptions = Vec::new();
+        let mut chars = self.flags.chars().peekable();
+        while let Some(c) = chars.next() {
+            let description = match c {
+                'A' => "animatable",
+                '+' => "animated",
+                'U' => "user-defined",
+                'H' => "hidden",
+                'L' => {
+                    // Lock may be followed by the mask of locked components.
+                    let mut mask = String::new();
+                    while let Some(&digit) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                        mask.push(digit);
+                        chars.next();
+                    }
+                    if !mask.is_empty() {
+                        descriptions.push(format!("locked ({})", mask));
+                        continue;
+                    }
+                    "locked"
+                }
+                'M' => "muted",
+                c => {
+                    descriptions.push(format!("unknown ({:?})", c));
+                    continue;
+                }
+            };
+            descriptions.push(description.to_owned());
+        }
+        descriptions.join(", ")
+    }
+
+    /// Returns the values.
+    pub fn values(&self) -> &'a [Attribute] {
+        self.values
+    }
+
+    /// Returns the values as a comma-separated string.
+    ///
+    /// Vector values (such as `Lcl Rotation`) are shown together.
+    pub fn values_string(&self) -> String {
+        self.values
+            .iter()
+            .map(|attr| attr.value_string_oneline(Some(16)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Returns the properties in the given `Properties70` node.
+///
+/// Child nodes which are not valid properties are ignored.
+pub fn properties(document: &Document, properties70: NodeId) -> Vec<Property<'_>> {
+    document
+        .node(properties70)
+        .children()
+        .iter()
+        .filter(|&&child| document.node(child).name() == "P")
+        .filter_map(|&child| Property::from_node(document, child))
+        .collect()
+}
+
+/// Returns the `Properties70` node of the property template for the object
+/// having the given `Properties70` node.
+///
+/// The template is `Definitions/ObjectType/PropertyTemplate/Properties70`
+/// where the `ObjectType` has the same name as the object node (such as
+/// `Model`).
+/// If the object type has several templates (such as `FbxSurfacePhong` and
+/// `FbxSurfaceLambert` for `Material`), the one for the class of the object
+/// is chosen, or the first one if none of them matches.
+/// Returns `None` if the given node is not the properties of an object, or
+/// the template is not found.
+pub fn template_properties(document: &Document, properties70: NodeId) -> Option<NodeId> {
+    let object = document.node(properties70).parent()?;
+    let objects = document.node(object).parent()?;
+    if document.node(objects).name() != "Objects" || document.node(objects).parent().is_some() {
+        return None;
+    }
+    let object_type = document.node(object).name();
+
+    let child_named = |parent: NodeId, name: &'static str| {
+        document
+            .node(parent)
+            .children()
+            .iter()
+            .cloned()
+            .filter(move |&child| document.node(child).name() == name)
+    };
+    let string_attr = |id: NodeId, index: usize| match document.node(id).attributes().get(index) {
+        Some(Attribute::String(s)) => s.as_str(),
+        _ => "",
+    };
+    // Pairs of the template class name and the properties.
+    let templates: Vec<(&str, NodeId)> = document
+        .toplevel_nodes()
+        .iter()
+        .cloned()
+        .filter(|&id| document.node(id).name() == "Definitions")
+        .flat_map(|definitions| child_named(definitions, "ObjectType"))
+        .filter(|&id| string_attr(id, 0) == object_type)
+        .flat_map(|object_type| child_named(object_type, "PropertyTemplate"))
+        .flat_map(|template| {
+            child_named(template, "Properties70")
+                .map(move |props| (string_attr(template, 0), props))
+        })
+        .collect();
+
+    // Candidates of the class, in order of preference: the shading model of
+    // materials (such as `Phong`), the subclass (such as `Light`), and the
+    // class in the object name.
+    let shading_model = child_named(object, "ShadingModel")
+        .next()
+        .map(|id| string_attr(id, 0));
+    let object_name = string_attr(object, 1);
+    let object_class = object_name
+        .find("\u{0}\u{1}")
+        .map(|sep| &object_name[(sep + 2)..]);
+    let classes = shading_model
+        .into_iter()
+        .chain(Some(string_attr(object, 2)))
+        .chain(object_class)
+        .filter(|class| !class.is_empty());
+    // Template names have prefixes such as `Fbx` and `FbxSurface`.
+    let matches = |template: &str, class: &str| {
+        template
+            .to_ascii_lowercase()
+            .ends_with(&class.to_ascii_lowercase())
+    };
+    classes
+        .filter_map(|class| {
+            templates
+                .iter()
+                .find(|(template, _)| matches(template, class))
+        })
+        .chain(templates.first())
+        .map(|&(_, props)| props)
+        .next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Appends a node with the given string attributes.
+    fn push(doc: &mut Document, parent: Option<NodeId>, name: &str, attrs: &[&str]) -> NodeId {
+        let attrs = attrs
+            .iter()
+            .map(|&s| Attribute::String(s.to_owned()))
+            .collect();
+        doc.push_node(parent, name.to_owned(), attrs, 0)
+    }
+
+    /// Creates a document with templates, and returns the document and the
+    /// `Properties70` nodes of the templates and the objects.
+    fn sample() -> (Document, Vec<NodeId>, Vec<NodeId>) {
+        let mut doc = Document::default();
+        let definitions = push(&mut doc, None, "Definitions", &[]);
+        let mut templates = Vec::new();
+        for &(object_type, classes) in &[
+            ("Material", &["FbxSurfacePhong", "FbxSurfaceLambert"][..]),
+            ("NodeAttribute", &["FbxCamera", "FbxLight"][..]),
+        ] {
+            let object_type = push(&mut doc, Some(definitions), "ObjectType", &[object_type]);
+            for &class in classes {
+                let template = push(&mut doc, Some(object_type), "PropertyTemplate", &[class]);
+                templates.push(push(&mut doc, Some(template), "Properties70", &[]));
+            }
+        }
+
+        let objects = push(&mut doc, None, "Objects", &[]);
+        let mut props = Vec::new();
+        for &(object_type, name, subclass, shading_model) in &[
+            (
+                "Material",
+                "Lambert1\u{0}\u{1}Material",
+                "",
+                Some("lambert"),
+            ),
+            ("Material", "Phong1\u{0}\u{1}Material", "", Some("Phong")),
+            ("Material", "Unknown\u{0}\u{1}Material", "", Some("Toon")),
+            ("NodeAttribute", "\u{0}\u{1}NodeAttribute", "Light", None),
+            ("NodeAttribute", "\u{0}\u{1}NodeAttribute", "Camera", None),
+        ] {
+            let object = doc.push_node(
+                Some(objects),
+                object_type.to_owned(),
+                vec![
+                    Attribute::SingleI64(1),
+                    Attribute::String(name.to_owned()),
+                    Attribute::String(subclass.to_owned()),
+                ],
+                0,
+            );
+            if let Some(shading_model) = shading_model {
+                push(&mut doc, Some(object), "ShadingModel", &[shading_model]);
+            }
+            props.push(push(&mut doc, Some(object), "Properties70", &[]));
+        }
+        (doc, templates, props)
+    }
+
+    #[test]
+    fn template_for_class() {
+        let (doc, templates, props) = sample();
+        let found: Vec<_> = props
+            .iter()
+            .map(|&id| template_properties(&doc, id))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                Some(templates[1]),
+                Some(templates[0]),
+                // Falls back to the first template.
+                Some(templates[0]),
+                Some(templates[3]),
+                Some(templates[2]),
+            ]
+        );
+    }
+
+    #[test]
+    fn not_object_properties() {
+        let (doc, templates, _) = sample();
+        assert_eq!(template_properties(&doc, templates[0]), None);
+    }
+}
@@ -0,0 +1,248 @@
+//! Search of FBX nodes.
+
+use crate::fbx::{Attribute, Document, NodeId};
+
+/// Search query.
+///
+/// A node matches the query if any of the following holds:
+///
+/// * the node name contains the query (case insensitive),
+/// * the query contains `/` and the node path ends with it
+///   (e.g. `Objects/Geometry/Vertices` or `Geometry/Vertices`), or
+/// * any attribute of the node matches the query
+///   (see [`matches_attribute`][`Self::matches_attribute`]).
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    /// Lowercased query text.
+    text: String,
+    /// Path components, if the query looks like a path.
+    path: Option<Vec<String>>,
+    /// Integer value of the query.
+    int: Option<i64>,
+    /// Floating point value of the query.
+    float: Option<f64>,
+    /// Floating point value of the query, parsed as `f32`.
+    ///
+    /// This is compared with `f32` values, since widened `f32` values
+    /// (such as `0.1f32`) are not equal to the `f64` parsed from the same
+    /// text.
+    float32: Option<f32>,
+}
+
+impl SearchQuery {
+    /// Creates a new query.
+    ///
+    /// Returns `None` if the query is empty.
+    pub fn new(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        let path = if text.contains('/') {
+            let components: Vec<String> = text
+                .split('/')
+                .filter(|component| !component.is_empty())
+                .map(str::to_lowercase)
+                .collect();
+            Some(components).filter(|components| !components.is_empty())
+        } else {
+            None
+        };
+
+        Some(Self {
+            text: text.to_lowercase(),
+            path,
+            int: text.parse().ok(),
+            float: text.parse().ok(),
+            float32: text.parse().ok(),
+        })
+    }
+
+    /// Returns whether the given node matches the query.
+    ///
+    /// Deferred attributes of lazily loaded documents are decoded here, and
+    /// attributes failed to be decoded do not match.
+    pub fn matches_node(&self, document: &Document, id: NodeId) -> bool {
+        document.node(id).name().to_lowercase().contains(&self.text)
+            || self.matches_path(document, id)
+            || document.load_attributes(id).map_or(false, |attrs| {
+                attrs.iter().any(|attr| self.matches_attribute(attr))
+            })
+    }
+
+    /// Returns whether the path of the given node matches the query.
+    fn matches_path(&self, document: &Document, id: NodeId) -> bool {
+        let components = match &self.path {
+            Some(v) => v,
+            None => return false,
+        };
+        let mut ancestors = document.ancestors(id);
+        components.iter().rev().all(|component| {
+            ancestors.next().map_or(false, |id| {
+                document.node(id).name().to_lowercase() == *component
+            })
+        })
+    }
+
+    /// Returns whether the given attribute matches the query.
+    ///
+    /// Strings match if they contain the query (case insensitive).
+    /// Numbers (including array elements) match if they are equal to the
+    /// query.
+    pub fn matches_attribute(&self, attr: &Attribute) -> bool {
+        match *attr {
+            Attribute::SingleBool(v) => self.matches_int(i64::from(v)),
+            Attribute::SingleI16(v) => self.matches_int(i64::from(v)),
+            Attribute::SingleI32(v) => self.matches_int(i64::from(v)),
+            Attribute::SingleI64(v) => self.matches_int(v),
+            Attribute::SingleF32(v) => self.matches_f32(v),
+            Attribute::SingleF64(v) => self.matches_float(v),
+            Attribute::ArrayBool(ref arr) => arr.iter().any(|&v| self.matches_int(i64::from(v))),
+            Attribute::ArrayI32(ref arr) => arr.iter().any(|&v| self.matches_int(i64::from(v))),
+            Attribute::ArrayI64(ref arr) => arr.iter().any(|&v| self.matches_int(v)),
+            Attribute::ArrayF32(ref arr) => arr.iter().any(|&v| self.matches_f32(v)),
+            Attribute::ArrayF64(ref arr) => arr.iter().any(|&v| self.matches_float(v)),
+            Attribute::String(ref s) => s.to_lowercase().contains(&self.text),
+            Attribute::Binary(_) => false,
+        }
+    }
+
+    /// Returns whether the given integer is equal to the query.
+    fn matches_int(&self, v: i64) -> bool {
+        match (self.int, self.float) {
+            (Some(query), _) => v == query,
+            (None, Some(query)) => v as f64 == query,
+            (None, None) => false,
+        }
+    }
+
+    /// Returns whether the given floating point number is equal to the query.
+    fn matches_float(&self, v: f64) -> bool {
+        self.float == Some(v)
+    }
+
+    /// Returns whether the given `f32` number is equal to the query.
+    fn matches_f32(&self, v: f32) -> bool {
+        self.float32 == Some(v)
+    }
+
+    /// Returns all nodes matching the query, in preorder.
+    pub fn search(&self, document: &Document) -> Vec<NodeId> {
+        document
+            .node_ids()
+            .filter(|&id| self.matches_node(document, id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates `Objects/Geometry/{Vertices, Name}` and `Objects/Model`.
+    fn sample() -> (Document, [NodeId; 5]) {
+        let mut doc = Document::default();
+        let objects = doc.push_node(None, "Objects".to_owned(), vec![], 0);
+        let geometry = doc.push_node(
+            Some(objects),
+            "Geometry".to_owned(),
+            vec![Attribute::SingleI64(1234), Attribute::SingleI32(-7)],
+            0,
+        );
+        let vertices = doc.push_node(
+            Some(geometry),
+            "Vertices".to_owned(),
+            vec![Attribute::ArrayF64(vec![0.5, 0.1])],
+            0,
+        );
+        let name = doc.push_node(
+            Some(geometry),
+            "Name".to_owned(),
+            vec![Attribute::String("Cube\u{0}\u{1}Geometry".to_owned())],
+            0,
+        );
+        let model = doc.push_node(
+            Some(objects),
+            "Model".to_owned(),
+            vec![
+                Attribute::SingleF32(2.5),
+                Attribute::ArrayF32(vec![0.1, 0.3]),
+            ],
+            0,
+        );
+        (doc, [objects, geometry, vertices, name, model])
+    }
+
+    /// Returns the nodes matching the query.
+    fn search(doc: &Document, text: &str) -> Vec<NodeId> {
+        SearchQuery::new(text).unwrap().search(doc)
+    }
+
+    #[test]
+    fn empty_query() {
+        assert!(SearchQuery::new("  ").is_none());
+    }
+
+    #[test]
+    fn node_name() {
+        let (doc, [_, geometry, vertices, name, _]) = sample();
+        // `Name` matches by the string attribute.
+        assert_eq!(search(&doc, "GEOM"), [geometry, name]);
+        assert_eq!(search(&doc, "vert"), [vertices]);
+    }
+
+    #[test]
+    fn node_path() {
+        let (doc, [_, _, vertices, ..]) = sample();
+        assert_eq!(search(&doc, "Objects/Geometry/Vertices"), [vertices]);
+        assert_eq!(search(&doc, "geometry/vertices"), [vertices]);
+        assert_eq!(search(&doc, "Model/Vertices"), []);
+        // The path should match the end of the node path.
+        assert_eq!(
+            search(&doc, "Objects/Geometry/"),
+            search(&doc, "Objects/Geometry")
+        );
+    }
+
+    #[test]
+    fn string_attributes() {
+        let (doc, [.., name, _]) = sample();
+        assert_eq!(search(&doc, "cube"), [name]);
+    }
+
+    #[test]
+    fn numeric_attributes() {
+        let (doc, [_, geometry, vertices, _, model]) = sample();
+        // i64 and i32.
+        assert_eq!(search(&doc, "1234"), [geometry]);
+        assert_eq!(search(&doc, "-7"), [geometry]);
+        // f32 and f64, as single values and array elements.
+        assert_eq!(search(&doc, "2.5"), [model]);
+        assert_eq!(search(&doc, "0.5"), [vertices]);
+        assert_eq!(search(&doc, "0.1"), [vertices, model]);
+        assert_eq!(search(&doc, "0.3"), [model]);
+        // Integers match floating point numbers with the same value.
+        assert_eq!(search(&doc, "-7.0"), [geometry]);
+        assert_eq!(search(&doc, "0.2"), []);
+    }
+
+    #[test]
+    fn lazy_document() {
+        use crate::fbx::{load_file_lazy, save_binary_file, WriteOptions};
+
+        let (doc, _) = sample();
+        let path =
+            std::env::temp_dir().join(format!("fbx-tree-view-search-{}.fbx", std::process::id()));
+        save_binary_file(&doc, &path, &WriteOptions::default()).unwrap();
+        let lazy = load_file_lazy(&path, 0, |_| true)
+            .document
+            .expect("document should be loaded");
+        assert!(lazy.is_lazy());
+
+        // Elements of deferred arrays should match.
+        for text in ["0.5", "0.1", "0.3", "cube", "-7"] {
+            assert_eq!(search(&lazy, text), search(&doc, text), "query: {}", text);
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,371 @@
+//! On-disk storage of array attributes.
+
+use std::io::{self, Read, Seek};
+
+use crate::fbx::{lazy::is_deferred, Attribute, AttributeLocation, Document};
+
+/// Array encoding value for uncompressed arrays.
+const ENCODING_RAW: u32 = 0;
+/// Array encoding value for zlib-compressed arrays.
+const ENCODING_ZLIB: u32 = 1;
+
+/// On-disk storage of an array attribute in FBX binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArrayStorage {
+    /// Encoding (0 for raw, 1 for zlib).
+    encoding: u32,
+    /// Byte length of the (possibly compressed) array data in the file.
+    on_disk_len: u32,
+    /// Number of elements.
+    num_elements: u32,
+    /// Byte size of an element.
+    element_size: u32,
+}
+
+impl ArrayStorage {
+    /// Returns the raw encoding value.
+    pub fn encoding(&self) -> u32 {
+        self.encoding
+    }
+
+    /// Returns the name of the encoding.
+    pub fn encoding_name(&self) -> String {
+        match self.encoding {
+            ENCODING_RAW => "raw".to_owned(),
+            ENCODING_ZLIB => "zlib".to_owned(),
+            v => format!("unknown ({})", v),
+        }
+    }
+
+    /// Returns whether the array is compressed.
+    pub fn is_compressed(&self) -> bool {
+        self.encoding != ENCODING_RAW
+    }
+
+    /// Returns the byte length of the array data in the file.
+    pub fn on_disk_len(&self) -> u64 {
+        u64::from(self.on_disk_len)
+    }
+
+    /// Returns the number of elements.
+    pub fn num_elements(&self) -> u64 {
+        u64::from(self.num_elements)
+    }
+
+    /// Returns the byte length of the uncompressed array data.
+    pub fn decoded_len(&self) -> u64 {
+        u64::from(self.num_elements) * u64::from(self.element_size)
+    }
+}
+
+/// Statistics of array attributes in a document.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ArrayStatistics {
+    /// Number of arrays.
+    num_arrays: u64,
+    /// Number of compressed arrays.
+    num_compressed: u64,
+    /// Total byte length of the array data in the file.
+    on_disk_len: u64,
+    /// Total byte length of the uncompressed array data.
+    decoded_len: u64,
+}
+
+impl ArrayStatistics {
+    /// Collects the statistics of the given document.
+    pub fn new(document: &Document) -> Self {
+        let mut stats = Self::default();
+        let storages = document
+            .node_ids()
+            .flat_map(|id| document.node(id).array_storages())
+            .filter_map(Option::as_ref);
+        for storage in storages {
+            stats.num_arrays += 1;
+            if storage.is_compressed() {
+                stats.num_compressed += 1;
+            }
+            stats.on_disk_len += storage.on_disk_len();
+            stats.decoded_len += storage.decoded_len();
+        }
+        stats
+    }
+
+    /// Returns the number of arrays.
+    pub fn num_arrays(&self) -> u64 {
+        self.num_arrays
+    }
+
+    /// Returns the number of compressed arrays.
+    pub fn num_compressed(&self) -> u64 {
+        self.num_compressed
+    }
+
+    /// Returns the total byte length of the array data in the file.
+    pub fn on_disk_len(&self) -> u64 {
+        self.on_disk_len
+    }
+
+    /// Returns the total byte length of the uncompressed array data.
+    pub fn decoded_len(&self) -> u64 {
+        self.decoded_len
+    }
+
+    /// Returns the ratio of the on-disk length to the uncompressed length.
+    ///
+    /// Returns `None` if there is no array data.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        if self.decoded_len == 0 {
+            None
+        } else {
+            Some(self.on_disk_len as f64 / self.decoded_len as f64)
+        }
+    }
+}
+
+/// Reads the storage information of array attributes from the FBX binary,
+/// and sets them to the document.
+///
+/// If the document is loaded lazily, the locations of deferred attributes are
+/// also set.
+///
+/// The document should be loaded from the same FBX binary, since the
+/// attribute offsets of nodes are used.
+pub fn load_array_storage<R: Read + Seek>(reader: R, document: &mut Document) -> io::Result<()> {
+    let mut reader = io::BufReader::new(reader);
+    let mut position = reader.stream_position()?;
+    let lazy = document.is_lazy();
+
+    for node_id in document.node_ids() {
+        let node = document.node(node_id);
+        let has_deferred = lazy && node.attributes().iter().any(is_deferred);
+        if !has_deferred && !node.attributes().iter().any(is_array) {
+            continue;
+        }
+        let start = match node.attribute_offsets() {
+            Some((start, _)) => start,
+            None => continue,
+        };
+        seek_to(&mut reader, &mut position, start)?;
+
+        let mut storages = Vec::with_capacity(node.attributes().len());
+        let mut locations = Vec::with_capacity(node.attributes().len());
+        for _ in node.attributes() {
+            let attr_offset = position;
+            let type_code = read_bytes::<1, _>(&mut reader)?[0];
+            position += 1;
+            let (storage, skip) = match type_code {
+                b'C' => (None, 1),
+                b'Y' => (None, 2),
+                b'I' | b'F' => (None, 4),
+                b'L' | b'D' => (None, 8),
+                b'S' | b'R' => {
+                    let len = u32::from_le_bytes(read_bytes::<4, _>(&mut reader)?);
+                    position += 4;
+                    (None, u64::from(len))
+                }
+                b'b' | b'i' | b'f' | b'l' | b'd' => {
+                    let header = read_bytes::<12, _>(&mut reader)?;
+                    position += 12;
+                    let field = |i: usize| {
+                        u32::from_le_bytes([
+                            header[i * 4],
+                            header[i * 4 + 1],
+                            header[i * 4 + 2],
+                            header[i * 4 + 3],
+                        ])
+                    };
+                    let element_size = match type_code {
+                        b'b' => 1,
+                        b'i' | b'f' => 4,
+                        _ => 8,
+                    };
+                    let storage = ArrayStorage {
+                        num_elements: field(0),
+                        encoding: field(1),
+                        on_disk_len: field(2),
+                        element_size,
+                    };
+                    (Some(storage), u64::from(storage.on_disk_len))
+                }
+                v => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Unknown attribute type code {:#04x} at offset {}",
+                            v,
+                            position - 1
+                        ),
+                    ));
+                }
+            };
+            storages.push(storage);
+            let next = position + skip;
+            locations.push(AttributeLocation::new(
+                type_code,
+                attr_offset,
+                next - attr_offset,
+            ));
+            seek_to(&mut reader, &mut position, next)?;
+        }
+        document.set_array_storages(node_id, storages);
+        if has_deferred {
+            document.set_attribute_locations(node_id, locations);
+        }
+    }
+
+    Ok(())
+}
+
+/// Seeks to the given offset, and updates the current position.
+fn seek_to<R: Seek>(
+    reader: &mut io::BufReader<R>,
+    position: &mut u64,
+    target: u64,
+) -> io::Result<()> {
+    // Relative seek keeps the buffer if the target is near.
+    reader.seek_relative(target as i64 - *position as i64)?;
+    *position = target;
+    Ok(())
+}
+
+/// Returns whether the attribute is an array.
+fn is_array(attr: &Attribute) -> bool {
+    matches!(
+        attr,
+        Attribute::ArrayBool(_)
+            | Attribute::ArrayI32(_)
+            | Attribute::ArrayI64(_)
+            | Attribute::ArrayF32(_)
+            | Attribute::ArrayF64(_)
+    )
+}
+
+/// Reads the given number of bytes.
+fn read_bytes<const N: usize, R: Read>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use fbxcel::low::FbxVersion;
+
+    use crate::fbx::{load_binary, write_binary, ArrayCompression, WriteOptions};
+
+    /// Number of elements of the sample arrays.
+    const ARRAY_LEN: usize = 1000;
+
+    /// Creates a document with compressible arrays and a scalar.
+    fn sample() -> Document {
+        let mut doc = Document::new(FbxVersion::V7_4);
+        let objects = doc.push_node(None, "Objects".to_owned(), vec![], 0);
+        doc.push_node(
+            Some(objects),
+            "Arrays".to_owned(),
+            vec![
+                Attribute::SingleI64(42),
+                Attribute::ArrayBool(vec![true; ARRAY_LEN]),
+                Attribute::ArrayI32((0..ARRAY_LEN as i32).map(|i| i % 4).collect()),
+                Attribute::ArrayF64(vec![0.5; ARRAY_LEN]),
+            ],
+            0,
+        );
+        doc.push_node(
+            Some(objects),
+            "Empty".to_owned(),
+            vec![Attribute::ArrayF32(Vec::new())],
+            0,
+        );
+        doc
+    }
+
+    /// Writes the sample with the given compression and loads it back.
+    fn written(compression: ArrayCompression) -> Document {
+        let options = WriteOptions {
+            fbx_version: None,
+            compression,
+        };
+        let written = write_binary(&sample(), io::Cursor::new(Vec::new()), &options).unwrap();
+        let result = load_binary(io::Cursor::new(written.into_inner()), |_| true);
+        if let Some(err) = result.error {
+            panic!("failed to load: {}", err);
+        }
+        result.document.expect("document should be loaded")
+    }
+
+    /// Returns the node with the given name.
+    fn find<'a>(doc: &'a Document, name: &str) -> &'a crate::fbx::Node {
+        let id = doc
+            .node_ids()
+            .find(|&id| doc.node(id).name() == name)
+            .unwrap();
+        doc.node(id)
+    }
+
+    #[test]
+    fn array_headers() {
+        let doc = written(ArrayCompression::Zlib);
+        let arrays = find(&doc, "Arrays");
+        assert!(arrays.array_storage(0).is_none());
+        for (index, element_size) in [(1, 1), (2, 4), (3, 8)] {
+            let storage = arrays.array_storage(index).unwrap();
+            assert_eq!(storage.encoding(), ENCODING_ZLIB);
+            assert_eq!(storage.encoding_name(), "zlib");
+            assert!(storage.is_compressed());
+            assert_eq!(storage.num_elements(), ARRAY_LEN as u64);
+            assert_eq!(storage.decoded_len(), ARRAY_LEN as u64 * element_size);
+            assert!(storage.on_disk_len() < storage.decoded_len());
+        }
+
+        let empty = find(&doc, "Empty").array_storage(0).unwrap();
+        assert_eq!(empty.num_elements(), 0);
+        assert_eq!(empty.decoded_len(), 0);
+    }
+
+    #[test]
+    fn raw_array_headers() {
+        let doc = written(ArrayCompression::Raw);
+        let arrays = find(&doc, "Arrays");
+        for index in 1..4 {
+            let storage = arrays.array_storage(index).unwrap();
+            assert_eq!(storage.encoding(), ENCODING_RAW);
+            assert_eq!(storage.encoding_name(), "raw");
+            assert!(!storage.is_compressed());
+            assert_eq!(storage.on_disk_len(), storage.decoded_len());
+        }
+
+        // The attribute data consists of 9 bytes of the scalar, and the type
+        // code, the 12 byte header, and the on-disk data of each array.
+        let (start, end) = arrays.attribute_offsets().unwrap();
+        let arrays_len: u64 = (1..4)
+            .map(|index| arrays.array_storage(index).unwrap().on_disk_len())
+            .sum();
+        assert_eq!(end - start, 9 + 3 * 13 + arrays_len);
+    }
+
+    #[test]
+    fn compression_ratio() {
+        let decoded_len = ARRAY_LEN as u64 * (1 + 4 + 8);
+
+        let stats = ArrayStatistics::new(&written(ArrayCompression::Zlib));
+        assert_eq!(stats.num_arrays(), 4);
+        assert_eq!(stats.decoded_len(), decoded_len);
+        assert!(stats.on_disk_len() < decoded_len);
+        let ratio = stats.compression_ratio().unwrap();
+        assert!(ratio > 0.0 && ratio < 0.5, "ratio: {}", ratio);
+
+        let stats = ArrayStatistics::new(&written(ArrayCompression::Raw));
+        assert_eq!(stats.num_arrays(), 4);
+        assert_eq!(stats.num_compressed(), 0);
+        assert_eq!(stats.on_disk_len(), decoded_len);
+        assert_eq!(stats.compression_ratio(), Some(1.0));
+
+        // Documents not loaded from FBX binary have no storage information.
+        let stats = ArrayStatistics::new(&sample());
+        assert_eq!(stats, ArrayStatistics::default());
+        assert_eq!(stats.compression_ratio(), None);
+    }
+}
@@ -0,0 +1,301 @@
+//! FBX binary writer.
+
+use std::{
+    fs::File,
+    io::{self, Seek, Write},
+    path::Path,
+};
+
+use fbxcel::{
+    low::{v7400::ArrayAttributeEncoding, FbxVersion},
+    writer::v7400::binary::{FbxFooter, FbxFooterPaddingLength, Writer},
+};
+
+use crate::fbx::{Attribute, Document, NodeId};
+
+/// Error type of writing.
+pub type WriteError = fbxcel::writer::v7400::binary::Error;
+
+/// Encoding of array attributes to be written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayCompression {
+    /// Same encoding as the loaded file.
+    ///
+    /// Arrays are uncompressed if the original encoding is unknown (for
+    /// example, loaded from FBX ASCII).
+    #[default]
+    Keep,
+    /// Uncompressed.
+    Raw,
+    /// Zlib compression.
+    Zlib,
+}
+
+/// Options for [`write_binary`].
+#[derive(Debug, Default, Clone)]
+pub struct WriteOptions {
+    /// FBX version to be written.
+    ///
+    /// `None` means the version of the document, or FBX 7.4 if the document
+    /// is older than that.
+    pub fbx_version: Option<FbxVersion>,
+    /// Encoding of array attributes.
+    pub compression: ArrayCompression,
+}
+
+/// Writes the document as FBX binary to the given sink.
+///
+/// Node record offsets and the footer are computed for the written data,
+/// so the output can be loaded again with the same nodes and attributes.
+/// The unknown fields of the original footer are kept if available.
+///
+/// Returns the sink after flushing.
+pub fn write_binary<W: Write + Seek>(
+    document: &Document,
+    sink: W,
+    options: &WriteOptions,
+) -> Result<W, WriteError> {
+    let fbx_version = options
+        .fbx_version
+        .unwrap_or_else(|| document.fbx_version().max(FbxVersion::V7_4));
+    let mut writer = Writer::new(sink, fbx_version)?;
+    for &node_id in document.toplevel_nodes() {
+        write_node(&mut writer, document, node_id, options)?;
+    }
+
+    // Padding is recomputed since the data length may change.
+    let footer = match document.footer() {
+        Some(footer) => FbxFooter {
+            unknown1: Some(&footer.unknown1),
+            padding_len: FbxFooterPaddingLength::Default,
+            unknown2: Some(footer.unknown2),
+            unknown3: Some(&footer.unknown3),
+        },
+        None => FbxFooter::default(),
+    };
+    writer.finalize_and_flush(&footer)
+}
+
+/// Writes the document as FBX binary to the given file.
+///
+/// The document is written to a temporary file in the same directory first,
+/// and it replaces the destination on success.
+/// This allows overwriting the file the document is lazily loaded from.
+pub fn save_binary_file<P: AsRef<Path>>(
+    document: &Document,
+    path: P,
+    options: &WriteOptions,
+) -> Result<(), WriteError> {
+    let path = path.as_ref();
+    let mut temp_name = path.file_name().unwrap_or_default().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let res = File::create(&temp_path)
+        .map_err(Into::into)
+        .and_then(|file| write_binary(document, io::BufWriter::new(file), options))
+        .and_then(|_| std::fs::rename(&temp_path, path).map_err(Into::into));
+    if res.is_err() {
+        // The temporary file is useless on failure.
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    res
+}
+
+/// Writes the given node and its descendants.
+fn write_node<W: Write + Seek>(
+    writer: &mut Writer<W>,
+    document: &Document,
+    node_id: NodeId,
+    options: &WriteOptions,
+) -> Result<(), WriteError> {
+    let node = document.node(node_id);
+    let attrs = document.load_attributes(node_id)?;
+    {
+        let mut attrs_writer = writer.new_node(node.name())?;
+        for (index, attr) in attrs.iter().enumerate() {
+            let encoding = match options.compression {
+                ArrayCompression::Keep => match node.array_storage(index) {
+                    Some(storage) if storage.is_compressed() => ArrayAttributeEncoding::Zlib,
+                    _ => ArrayAttributeEncoding::Direct,
+                },
+                ArrayCompression::Raw => ArrayAttributeEncoding::Direct,
+                ArrayCompression::Zlib => ArrayAttributeEncoding::Zlib,
+            };
+            match attr {
+                Attribute::SingleBool(v) => attrs_writer.append_bool(*v)?,
+                Attribute::SingleI16(v) => attrs_writer.append_i16(*v)?,
+                Attribute::SingleI32(v) => attrs_writer.append_i32(*v)?,
+                Attribute::SingleI64(v) => attrs_writer.append_i64(*v)?,
+                Attribute::SingleF32(v) => attrs_writer.append_f32(*v)?,
+                Attribute::SingleF64(v) => attrs_writer.append_f64(*v)?,
+                Attribute::ArrayBool(arr) => {
+                    attrs_writer.append_arr_bool_from_iter(encoding, arr.iter().cloned())?
+                }
+                Attribute::ArrayI32(arr) => {
+                    attrs_writer.append_arr_i32_from_iter(encoding, arr.iter().cloned())?
+                }
+                Attribute::ArrayI64(arr) => {
+                    attrs_writer.append_arr_i64_from_iter(encoding, arr.iter().cloned())?
+                }
+                Attribute::ArrayF32(arr) => {
+                    attrs_writer.append_arr_f32_from_iter(encoding, arr.iter().cloned())?
+                }
+                Attribute::ArrayF64(arr) => {
+                    attrs_writer.append_arr_f64_from_iter(encoding, arr.iter().cloned())?
+                }
+                Attribute::String(s) => attrs_writer.append_string_direct(s)?,
+                Attribute::Binary(bytes) => attrs_writer.append_binary_direct(bytes)?,
+            }
+        }
+    }
+    for &child in node.children() {
+        write_node(writer, document, child, options)?;
+    }
+    writer.close_node()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fbx::{
+        diff_documents, load_binary, load_file, load_file_lazy, DiffOptions, LoadResult,
+    };
+
+    /// Number of elements of the sample arrays.
+    ///
+    /// Arrays should be large enough to be compressed effectively.
+    const ARRAY_LEN: usize = 100;
+
+    /// Creates a document with attributes of all types.
+    fn sample(fbx_version: FbxVersion) -> Document {
+        let mut doc = Document::new(fbx_version);
+        let header = doc.push_node(None, "FBXHeaderExtension".to_owned(), vec![], 0);
+        doc.push_node(
+            Some(header),
+            "FBXVersion".to_owned(),
+            vec![Attribute::SingleI32(7400)],
+            0,
+        );
+        let objects = doc.push_node(None, "Objects".to_owned(), vec![], 0);
+        doc.push_node(
+            Some(objects),
+            "Scalars".to_owned(),
+            vec![
+                Attribute::SingleBool(true),
+                Attribute::SingleI16(-2),
+                Attribute::SingleI32(3),
+                Attribute::SingleI64(-4_000_000_000),
+                Attribute::SingleF32(0.5),
+                Attribute::SingleF64(-0.25),
+                Attribute::String("Cube\u{0}\u{1}Model".to_owned()),
+                Attribute::Binary(vec![0, 1, 2, 255]),
+            ],
+            0,
+        );
+        doc.push_node(
+            Some(objects),
+            "Arrays".to_owned(),
+            vec![
+                Attribute::ArrayBool((0..ARRAY_LEN).map(|i| i % 3 == 0).collect()),
+                Attribute::ArrayI32((0..ARRAY_LEN as i32).collect()),
+                Attribute::ArrayI64((0..ARRAY_LEN as i64).map(|i| i << 40).collect()),
+                Attribute::ArrayF32((0..ARRAY_LEN).map(|i| i as f32 * 0.5).collect()),
+                Attribute::ArrayF64((0..ARRAY_LEN).map(|i| i as f64 / 3.0).collect()),
+            ],
+            0,
+        );
+        doc.push_node(
+            Some(objects),
+            "Empty".to_owned(),
+            vec![Attribute::ArrayF64(Vec::new())],
+            0,
+        );
+        doc
+    }
+
+    /// Returns the loaded document, panicking on errors.
+    fn loaded(result: LoadResult) -> Document {
+        if let Some(err) = result.error {
+            panic!("failed to load: {}", err);
+        }
+        result.document.expect("document should be loaded")
+    }
+
+    /// Asserts that the documents have no differences.
+    fn assert_same(old: &Document, new: &Document) {
+        let diff = diff_documents(old, new, &DiffOptions::default()).unwrap();
+        assert!(!diff.has_differences());
+    }
+
+    #[test]
+    fn round_trip() {
+        for &fbx_version in &[FbxVersion::V7_4, FbxVersion::V7_5] {
+            for &compression in &[ArrayCompression::Raw, ArrayCompression::Zlib] {
+                let doc = sample(fbx_version);
+                let options = WriteOptions {
+                    fbx_version: None,
+                    compression,
+                };
+                let written = write_binary(&doc, io::Cursor::new(Vec::new()), &options).unwrap();
+                let reader = io::Cursor::new(written.into_inner());
+                let reloaded = loaded(load_binary(reader, |_| true));
+
+                assert_eq!(reloaded.fbx_version(), fbx_version);
+                assert!(reloaded.footer().is_some());
+                assert_same(&doc, &reloaded);
+
+                let arrays = reloaded
+                    .node_ids()
+                    .find(|&id| reloaded.node(id).name() == "Arrays")
+                    .unwrap();
+                let expected = match compression {
+                    ArrayCompression::Zlib => "zlib",
+                    _ => "raw",
+                };
+                for index in 0..5 {
+                    let storage = reloaded.node(arrays).array_storage(index).unwrap();
+                    assert_eq!(storage.encoding_name(), expected);
+                    assert_eq!(storage.num_elements(), ARRAY_LEN as u64);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lazy_and_eager_loading() {
+        let doc = sample(FbxVersion::V7_4);
+        let path =
+            std::env::temp_dir().join(format!("fbx-tree-view-writer-{}.fbx", std::process::id()));
+        let options = WriteOptions {
+            fbx_version: None,
+            compression: ArrayCompression::Zlib,
+        };
+        save_binary_file(&doc, &path, &options).unwrap();
+        let eager = loaded(load_file(&path, |_| true));
+        // A tiny cache to exercise eviction.
+        let lazy = loaded(load_file_lazy(&path, 64, |_| true));
+        assert!(lazy.is_lazy());
+        assert!(!eager.is_lazy());
+        let arrays = lazy
+            .node_ids()
+            .find(|&id| lazy.node(id).name() == "Arrays")
+            .unwrap();
+        assert!(!lazy.node(arrays).attribute_locations().is_empty());
+
+        assert_eq!(eager.len(), lazy.len());
+        for id in eager.node_ids() {
+            let eager_attrs = eager.load_attributes(id).unwrap();
+            let lazy_attrs = lazy.load_attributes(id).unwrap();
+            assert_eq!(&*eager_attrs, &*lazy_attrs, "node {}", eager.path(id));
+        }
+        assert_same(&doc, &lazy);
+
+        // Overwriting the source file of the lazy document.
+        save_binary_file(&lazy, &path, &WriteOptions::default()).unwrap();
+        let reloaded = loaded(load_file(&path, |_| true));
+        assert_same(&doc, &reloaded);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,1105 @@
+//! GUI of the FBX tree viewer.
+
+use std::{
+    cell::RefCell,
+    io::Write,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use gtk::prelude::*;
+use gtk::ScrolledWindow;
+use gtk::{AccelFlags, AccelGroup};
+use gtk::{FileChooserAction, FileChooserDialog, FileFilter};
+use gtk::{Menu, MenuBar, MenuItem};
+use gtk::{Notebook, Orientation, Paned, Window, WindowType};
+
+use crate::{
+    fbx::{
+        detect_file_format, export_json, load_file, load_file_lazy, save_ascii_file,
+        save_binary_file, write_ascii, Document, Edit, EditHistory, Format, JsonOptions,
+        LoadResult, NodeFilter, NodeId, WriteOptions, DEFAULT_CACHE_CAPACITY,
+    },
+    widgets::{
+        FbxAnimationView, FbxArrayView, FbxAttributeTable, FbxDiffView, FbxEmbeddedView,
+        FbxFileSummary, FbxHexView, FbxLayerElementView, FbxMeshPreview, FbxMeshView,
+        FbxNodeSearch, FbxNodeTree, FbxObjectList, FbxPropertyTable, LoadProgress, Logs,
+    },
+};
+
+/// Base of the window title.
+pub const WINDOW_TITLE_BASE: &str = "FBX tree viewer";
+
+/// Creates the main window and runs the GTK main loop.
+///
+/// If `path` is given, the file is opened at startup.
+pub fn run(path: Option<PathBuf>) {
+    gtk::init().expect("Failed to initialize GTK");
+
+    let window_width = 800;
+    let window_height = 600;
+
+    let window = Window::new(WindowType::Toplevel);
+    window.set_title(WINDOW_TITLE_BASE);
+    window.set_default_size(window_width, window_height);
+
+    //
+    // Window root.
+    //
+
+    let root_widget = gtk::Box::new(Orientation::Vertical, 0);
+    window.add(&root_widget);
+    let accel_group = AccelGroup::new();
+    window.add_accel_group(&accel_group);
+
+    //
+    // Menu bar.
+    //
+
+    let menu_bar = MenuBar::new();
+    let menu_file = MenuItem::with_mnemonic("_File");
+    let submenu_file = Menu::new();
+    let menu_file_open = MenuItem::with_mnemonic("_Open FBX file");
+    submenu_file.append(&menu_file_open);
+    let menu_file_save = MenuItem::with_mnemonic("_Save");
+    submenu_file.append(&menu_file_save);
+    let menu_file_compare = MenuItem::with_mnemonic("_Compare with FBX file");
+    submenu_file.append(&menu_file_compare);
+    let menu_file_export_json = MenuItem::with_mnemonic("_Export as JSON");
+    submenu_file.append(&menu_file_export_json);
+    let menu_file_export_ascii = MenuItem::with_mnemonic("Export as FBX _ASCII");
+    submenu_file.append(&menu_file_export_ascii);
+    let menu_file_lazy = gtk::CheckMenuItem::with_mnemonic("_Load arrays lazily");
+    menu_file_lazy.set_tooltip_text(Some(
+        "Decode arrays and binaries on demand, to open very large files",
+    ));
+    submenu_file.append(&menu_file_lazy);
+    submenu_file.append(&gtk::SeparatorMenuItem::new());
+    let menu_file_quit = MenuItem::with_mnemonic("_Quit");
+    submenu_file.append(&menu_file_quit);
+    menu_file.set_submenu(Some(&submenu_file));
+    menu_bar.append(&menu_file);
+    let menu_edit = MenuItem::with_mnemonic("_Edit");
+    let submenu_edit = Menu::new();
+    let menu_edit_find = MenuItem::with_mnemonic("_Find");
+    submenu_edit.append(&menu_edit_find);
+    submenu_edit.append(&gtk::SeparatorMenuItem::new());
+    let menu_edit_undo = MenuItem::with_mnemonic("_Undo");
+    submenu_edit.append(&menu_edit_undo);
+    let menu_edit_redo = MenuItem::with_mnemonic("_Redo");
+    submenu_edit.append(&menu_edit_redo);
+    submenu_edit.append(&gtk::SeparatorMenuItem::new());
+    let menu_edit_rename = MenuItem::with_mnemonic("Re_name node");
+    submenu_edit.append(&menu_edit_rename);
+    let menu_edit_duplicate = MenuItem::with_mnemonic("D_uplicate node");
+    submenu_edit.append(&menu_edit_duplicate);
+    let menu_edit_delete = MenuItem::with_mnemonic("_Delete node");
+    submenu_edit.append(&menu_edit_delete);
+    menu_edit.set_submenu(Some(&submenu_edit));
+    menu_bar.append(&menu_edit);
+    root_widget.pack_start(&menu_bar, false, false, 0);
+
+    {
+        menu_file_open.add_accelerator(
+            "activate",
+            &accel_group,
+            *gdk::keys::constants::O,
+            gdk::ModifierType::CONTROL_MASK,
+            AccelFlags::VISIBLE,
+        );
+        menu_file_save.add_accelerator(
+            "activate",
+            &accel_group,
+            *gdk::keys::constants::S,
+            gdk::ModifierType::CONTROL_MASK,
+            AccelFlags::VISIBLE,
+        );
+        menu_file_quit.add_accelerator(
+            "activate",
+            &accel_group,
+            *gdk::keys::constants::Q,
+            gdk::ModifierType::CONTROL_MASK,
+            AccelFlags::VISIBLE,
+        );
+        menu_edit_find.add_accelerator(
+            "activate",
+            &accel_group,
+            *gdk::keys::constants::F,
+            gdk::ModifierType::CONTROL_MASK,
+            AccelFlags::VISIBLE,
+        );
+        menu_edit_undo.add_accelerator(
+            "activate",
+            &accel_group,
+            *gdk::keys::constants::Z,
+            gdk::ModifierType::CONTROL_MASK,
+            AccelFlags::VISIBLE,
+        );
+        menu_edit_redo.add_accelerator(
+            "activate",
+            &accel_group,
+            *gdk::keys::constants::Z,
+            gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK,
+            AccelFlags::VISIBLE,
+        );
+        menu_edit_rename.add_accelerator(
+            "activate",
+            &accel_group,
+            *gdk::keys::constants::F2,
+            gdk::ModifierType::empty(),
+            AccelFlags::VISIBLE,
+        );
+        menu_edit_duplicate.add_accelerator(
+            "activate",
+            &accel_group,
+            *gdk::keys::constants::D,
+            gdk::ModifierType::CONTROL_MASK,
+            AccelFlags::VISIBLE,
+        );
+    }
+
+    //
+    // FBX tree.
+    //
+
+    let node_tree = FbxNodeTree::new();
+    let scrolled_node_tree = ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
+    scrolled_node_tree.add(node_tree.widget());
+
+    let node_filter_entry = gtk::SearchEntry::new();
+    node_filter_entry.set_placeholder_text(Some("Filter (e.g. Properties70/P=Lcl Translation)"));
+    {
+        let node_tree = node_tree.clone();
+        node_filter_entry.connect_search_changed(move |entry| {
+            node_tree.set_filter(NodeFilter::new(&entry.text()));
+        });
+    }
+
+    let node_tree_box = gtk::Box::new(Orientation::Vertical, 0);
+    node_tree_box.pack_start(&node_filter_entry, false, false, 0);
+    node_tree_box.pack_start(&scrolled_node_tree, true, true, 0);
+
+    //
+    // Node data.
+    //
+
+    let node_attrs = FbxAttributeTable::new();
+    let scrolled_node_attrs = ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
+    scrolled_node_attrs.add(node_attrs.widget());
+
+    node_tree.initialize(&node_attrs);
+
+    //
+    // Node search.
+    //
+
+    let search = FbxNodeSearch::new();
+    search.initialize(&node_tree, &node_attrs);
+    root_widget.pack_start(search.widget(), false, false, 0);
+
+    //
+    // FBX tree and node data.
+    //
+
+    let tree_notebook = Notebook::new();
+    tree_notebook.append_page(&node_tree_box, Some(&gtk::Label::new(Some("Nodes"))));
+
+    //
+    // Objects.
+    //
+
+    let objects = FbxObjectList::new();
+    let scrolled_objects = ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
+    scrolled_objects.add(objects.widget());
+    tree_notebook.append_page(&scrolled_objects, Some(&gtk::Label::new(Some("Objects"))));
+    objects.initialize(&node_tree, &tree_notebook, node_tree_box.upcast_ref());
+
+    //
+    // Diff.
+    //
+
+    let diff = FbxDiffView::new();
+    diff.initialize(&node_tree);
+    tree_notebook.append_page(diff.widget(), Some(&gtk::Label::new(Some("Diff"))));
+
+    //
+    // Properties.
+    //
+
+    let node_props = FbxPropertyTable::new();
+    let scrolled_node_props = ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
+    scrolled_node_props.add(node_props.widget());
+
+    //
+    // Inspectors of the selected node.
+    //
+
+    let inspector_notebook = Notebook::new();
+    inspector_notebook.append_page(
+        &scrolled_node_attrs,
+        Some(&gtk::Label::new(Some("Attributes"))),
+    );
+    {
+        let show_props = {
+            let node_props = node_props.clone();
+            move |document: &Document, node_id: Option<NodeId>| match node_id {
+                Some(node_id) => node_props.show_node(document, node_id),
+                None => {
+                    node_props.clear();
+                    false
+                }
+            }
+        };
+        add_inspector_page(
+            &inspector_notebook,
+            &node_tree,
+            &scrolled_node_props,
+            "Properties",
+            show_props,
+        );
+    }
+
+    //
+    // Mesh.
+    //
+
+    let node_mesh = FbxMeshView::new();
+    {
+        let show_mesh = {
+            let node_mesh = node_mesh.clone();
+            move |document: &Document, node_id: Option<NodeId>| match node_id {
+                Some(node_id) => node_mesh.show_node(document, node_id),
+                None => {
+                    node_mesh.clear();
+                    false
+                }
+            }
+        };
+        add_inspector_page(
+            &inspector_notebook,
+            &node_tree,
+            node_mesh.widget(),
+            "Mesh",
+            show_mesh,
+        );
+    }
+
+    //
+    // Array.
+    //
+
+    let node_array = FbxArrayView::new();
+    {
+        let show_array = {
+            let node_array = node_array.clone();
+            move |document: &Document, node_id: Option<NodeId>| match node_id {
+                Some(node_id) => node_array.show_node(document, node_id),
+                None => {
+                    node_array.clear();
+                    false
+                }
+            }
+        };
+        add_inspector_page(
+            &inspector_notebook,
+            &node_tree,
+            node_array.widget(),
+            "Array",
+            show_array,
+        );
+    }
+
+    //
+    // Layer element.
+    //
+
+    let node_layer_element = FbxLayerElementView::new();
+    {
+        let show_layer_element = {
+            let node_layer_element = node_layer_element.clone();
+            move |document: &Document, node_id: Option<NodeId>| match node_id {
+                Some(node_id) => node_layer_element.show_node(document, node_id),
+                None => {
+                    node_layer_element.clear();
+                    false
+                }
+            }
+        };
+        add_inspector_page(
+            &inspector_notebook,
+            &node_tree,
+            node_layer_element.widget(),
+            "Layer element",
+            show_layer_element,
+        );
+    }
+
+    //
+    // Animation curves.
+    //
+
+    let node_animation = FbxAnimationView::new();
+    {
+        let show_animation = {
+            let node_animation = node_animation.clone();
+            move |document: &Document, node_id: Option<NodeId>| match node_id {
+                Some(node_id) => node_animation.show_node(document, node_id),
+                None => {
+                    node_animation.clear();
+                    false
+                }
+            }
+        };
+        add_inspector_page(
+            &inspector_notebook,
+            &node_tree,
+            node_animation.widget(),
+            "Animation",
+            show_animation,
+        );
+    }
+
+    //
+    // Embedded files.
+    //
+
+    let node_embedded = FbxEmbeddedView::new();
+    {
+        let show_embedded = {
+            let node_embedded = node_embedded.clone();
+            move |document: &Document, node_id: Option<NodeId>| match node_id {
+                Some(node_id) => node_embedded.show_node(document, node_id),
+                None => {
+                    node_embedded.clear();
+                    false
+                }
+            }
+        };
+        add_inspector_page(
+            &inspector_notebook,
+            &node_tree,
+            node_embedded.widget(),
+            "Embedded file",
+            show_embedded,
+        );
+    }
+
+    //
+    // Wireframe preview.
+    //
+
+    let node_preview = FbxMeshPreview::new();
+    node_preview.widget().set_no_show_all(true);
+    {
+        let node_preview = node_preview.clone();
+        node_tree.connect_node_selected(move |document, node_id| {
+            let is_mesh = match node_id {
+                Some(node_id) => node_preview.show_node(document, node_id),
+                None => {
+                    node_preview.clear();
+                    false
+                }
+            };
+            node_preview.widget().set_visible(is_mesh);
+        });
+    }
+
+    let inspector_pane = Paned::new(Orientation::Horizontal);
+    inspector_pane.pack1(&inspector_notebook, true, true);
+    inspector_pane.pack2(node_preview.widget(), true, true);
+
+    let fbx_data_pane = Paned::new(Orientation::Horizontal);
+    fbx_data_pane.add1(&tree_notebook);
+    fbx_data_pane.add2(&inspector_pane);
+    fbx_data_pane.set_position(window_width / 5 * 4);
+
+    //
+    // Warnings and errors.
+    //
+
+    let logs = Logs::new();
+    let scrolled_logs = ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
+    scrolled_logs.add(logs.widget());
+
+    //
+    // Hex view.
+    //
+
+    let hex = FbxHexView::new();
+    hex.initialize(&node_tree);
+
+    //
+    // File summary.
+    //
+
+    let summary = FbxFileSummary::new();
+
+    let bottom_notebook = Notebook::new();
+    bottom_notebook.append_page(&scrolled_logs, Some(&gtk::Label::new(Some("Logs"))));
+    bottom_notebook.append_page(hex.widget(), Some(&gtk::Label::new(Some("Hex"))));
+    bottom_notebook.append_page(summary.widget(), Some(&gtk::Label::new(Some("Summary"))));
+
+    //
+    // Main region of the window.
+    //
+
+    let content_pane = Paned::new(Orientation::Vertical);
+    content_pane.add1(&fbx_data_pane);
+    content_pane.add2(&bottom_notebook);
+    content_pane.set_wide_handle(true);
+    content_pane.set_position(window_height / 5 * 4);
+
+    root_widget.pack_start(&content_pane, true, true, 0);
+
+    //
+    // Loading progress.
+    //
+
+    let progress = LoadProgress::new();
+    progress.initialize();
+    root_widget.pack_start(progress.widget(), false, false, 0);
+
+    window.show_all();
+
+    let widgets = AppWidgets {
+        window: window.clone(),
+        logs,
+        node_tree,
+        node_attrs,
+        search,
+        objects,
+        hex,
+        summary,
+        diff,
+        progress,
+        lazy_loading: menu_file_lazy,
+        edit: Rc::new(RefCell::new(EditState::default())),
+    };
+
+    {
+        let widgets = widgets.clone();
+        window.connect_delete_event(move |_, _| {
+            if !confirm_discard(&widgets) {
+                return glib::Propagation::Stop;
+            }
+            gtk::main_quit();
+            glib::Propagation::Proceed
+        });
+    }
+
+    if let Some(path) = path {
+        load_fbx_file(path, &widgets);
+    }
+
+    {
+        // Open files dropped from file managers.
+        let targets = [gtk::TargetEntry::new(
+            "text/uri-list",
+            gtk::TargetFlags::OTHER_APP,
+            0,
+        )];
+        window.drag_dest_set(gtk::DestDefaults::ALL, &targets, gdk::DragAction::COPY);
+        let widgets = widgets.clone();
+        window.connect_drag_data_received(move |_, _, _, _, data, _, _| {
+            // Only the first file is opened, since the viewer shows one file
+            // at once.
+            let path = data
+                .uris()
+                .iter()
+                .find_map(|uri| glib::filename_from_uri(uri).ok())
+                .map(|(path, _hostname)| path);
+            match path {
+                Some(path) => load_fbx_file(path, &widgets),
+                None => println!("dropped data has no local files"),
+            }
+        });
+    }
+
+    {
+        let search = widgets.search.clone();
+        menu_edit_find.connect_activate(move |_| search.show());
+    }
+    {
+        let widgets = widgets.clone();
+        widgets
+            .node_attrs
+            .clone()
+            .connect_attribute_edited(move |node, index, text| {
+                edit_attribute(&widgets, node, index, text)
+            });
+    }
+    {
+        let widgets = widgets.clone();
+        widgets
+            .node_tree
+            .clone()
+            .connect_node_edit(move |edit| apply_edit(&widgets, edit));
+    }
+    {
+        let widgets = widgets.clone();
+        menu_edit_undo.connect_activate(move |_| undo(&widgets, false));
+    }
+    {
+        let widgets = widgets.clone();
+        menu_edit_redo.connect_activate(move |_| undo(&widgets, true));
+    }
+    {
+        let node_tree = widgets.node_tree.clone();
+        menu_edit_rename.connect_activate(move |_| node_tree.start_rename());
+    }
+    {
+        let widgets = widgets.clone();
+        menu_edit_duplicate.connect_activate(move |_| {
+            if let Some(node) = widgets.node_tree.selected_node() {
+                apply_edit(&widgets, Edit::Duplicate { node });
+            }
+        });
+    }
+    {
+        let widgets = widgets.clone();
+        menu_edit_delete.connect_activate(move |_| {
+            if let Some(node) = widgets.node_tree.selected_node() {
+                apply_edit(&widgets, Edit::Remove { node });
+            }
+        });
+    }
+    {
+        let widgets = widgets.clone();
+        menu_file_save.connect_activate(move |_| save_fbx_file(&widgets));
+    }
+    {
+        let window = window.clone();
+        let document = widgets.node_tree.document().clone();
+        menu_file_export_json.connect_activate(move |_| {
+            export_file(
+                &window,
+                &document.borrow(),
+                "JSON",
+                "export.json",
+                |document, mut out| export_json(document, &mut out, &JsonOptions::default()),
+            );
+        });
+    }
+    {
+        let window = window.clone();
+        let document = widgets.node_tree.document().clone();
+        menu_file_export_ascii.connect_activate(move |_| {
+            export_file(
+                &window,
+                &document.borrow(),
+                "FBX ASCII",
+                "export.fbx",
+                |document, mut out| write_ascii(document, &mut out),
+            );
+        });
+    }
+    {
+        let fbx_file_chooser = create_fbx_file_chooser(&window);
+        fbx_file_chooser.set_title("Compare with FBX file");
+        let widgets = widgets.clone();
+        menu_file_compare.connect_activate(move |_| {
+            if fbx_file_chooser.run() == gtk::ResponseType::Ok {
+                if let Some(filename) = fbx_file_chooser.filename() {
+                    tree_notebook.set_current_page(tree_notebook.page_num(widgets.diff.widget()));
+                    load_comparison_file(filename, &widgets);
+                }
+            }
+            fbx_file_chooser.hide();
+        });
+    }
+    {
+        let fbx_file_chooser = create_fbx_file_chooser(&window);
+        let widgets = widgets.clone();
+        menu_file_open.connect_activate(move |_| {
+            if fbx_file_chooser.run() == gtk::ResponseType::Ok {
+                if let Some(filename) = fbx_file_chooser.filename() {
+                    load_fbx_file(filename, &widgets);
+                }
+            }
+            fbx_file_chooser.hide();
+        });
+    }
+    menu_file_quit.connect_activate(move |_| {
+        if confirm_discard(&widgets) {
+            gtk::main_quit();
+        }
+    });
+
+    gtk::main();
+}
+
+/// Adds an inspector page for the selected node.
+///
+/// `show` is called when the selected node changes, and should return
+/// whether the page is available for the node.
+/// Pages are shown only while they are available, and the newly shown page
+/// is made current, since specialized views are more useful than the raw
+/// attributes.
+fn add_inspector_page<W, F>(
+    notebook: &Notebook,
+    node_tree: &FbxNodeTree,
+    page: &W,
+    title: &str,
+    show: F,
+) where
+    W: IsA<gtk::Widget>,
+    F: Fn(&Document, Option<NodeId>) -> bool + 'static,
+{
+    notebook.append_page(page, Some(&gtk::Label::new(Some(title))));
+    page.set_no_show_all(true);
+
+    let notebook = notebook.clone();
+    let page = page.clone();
+    node_tree.connect_node_selected(move |document, node_id| {
+        if show(document, node_id) {
+            let was_visible = page.is_visible();
+            page.show_all();
+            if !was_visible {
+                notebook.set_current_page(notebook.page_num(&page));
+            }
+        } else {
+            page.hide();
+        }
+    });
+}
+
+fn create_fbx_file_chooser<'a, W: Into<Option<&'a Window>>>(window: W) -> FileChooserDialog {
+    let file_chooser = FileChooserDialog::new(
+        Some("Open FBX file"),
+        window.into(),
+        FileChooserAction::Open,
+    );
+    {
+        let fbx_filter = FileFilter::new();
+        fbx_filter.set_name(Some("FBX files"));
+        fbx_filter.add_pattern("*.fbx");
+        fbx_filter.add_pattern("*.FBX");
+        file_chooser.add_filter(fbx_filter);
+    }
+    {
+        let all_filter = FileFilter::new();
+        all_filter.set_name(Some("All files"));
+        all_filter.add_pattern("*");
+        file_chooser.add_filter(all_filter);
+    }
+    file_chooser.add_buttons(&[
+        ("Cancel", gtk::ResponseType::Cancel),
+        ("Open", gtk::ResponseType::Ok),
+    ]);
+    file_chooser
+}
+
+/// Asks the destination and exports the document by `export`.
+///
+/// `format` is the name of the format shown to the user.
+fn export_file<F>(window: &Window, document: &Document, format: &str, default_name: &str, export: F)
+where
+    F: FnOnce(&Document, &mut dyn Write) -> std::io::Result<()>,
+{
+    if document.is_empty() {
+        println!("No document to export");
+        return;
+    }
+    let file_chooser = FileChooserDialog::new(
+        Some(&format!("Export as {}", format)),
+        Some(window),
+        FileChooserAction::Save,
+    );
+    file_chooser.set_do_overwrite_confirmation(true);
+    file_chooser.set_current_name(default_name);
+    file_chooser.add_buttons(&[
+        ("Cancel", gtk::ResponseType::Cancel),
+        ("Export", gtk::ResponseType::Ok),
+    ]);
+    if file_chooser.run() == gtk::ResponseType::Ok {
+        if let Some(path) = file_chooser.filename() {
+            let res = std::fs::File::create(&path).and_then(|file| {
+                let mut out = std::io::BufWriter::new(file);
+                export(document, &mut out)?;
+                out.flush()
+            });
+            match res {
+                Ok(()) => println!("Exported {} to {}", format, path.display()),
+                Err(e) => println!("Failed to export {} to {}: {}", format, path.display(), e),
+            }
+        }
+    }
+    file_chooser.close();
+}
+
+/// Widgets updated when a file is loaded.
+#[derive(Debug, Clone)]
+pub struct AppWidgets {
+    /// Main window.
+    pub window: Window,
+    /// Warnings and errors.
+    pub logs: Logs,
+    /// Node tree.
+    pub node_tree: FbxNodeTree,
+    /// Attributes of the selected node.
+    pub node_attrs: FbxAttributeTable,
+    /// Node search bar.
+    pub search: FbxNodeSearch,
+    /// Objects list.
+    pub objects: FbxObjectList,
+    /// Hex view of the raw file.
+    pub hex: FbxHexView,
+    /// File summary.
+    pub summary: FbxFileSummary,
+    /// Diff with another file.
+    pub diff: FbxDiffView,
+    /// Loading progress.
+    pub progress: LoadProgress,
+    /// Whether to load arrays and binaries lazily.
+    pub lazy_loading: gtk::CheckMenuItem,
+    /// State of the document being edited.
+    edit: Rc<RefCell<EditState>>,
+}
+
+/// State of the document being edited.
+#[derive(Debug, Default)]
+struct EditState {
+    /// Path of the loaded file.
+    path: Option<PathBuf>,
+    /// Format of the loaded file.
+    format: Option<Format>,
+    /// Whether arrays and binaries are lazily loaded from the file.
+    lazy: bool,
+    /// Whether the whole file is loaded.
+    ///
+    /// Partially loaded documents are not saved, since the missing nodes
+    /// would be lost.
+    complete: bool,
+    /// Edit history.
+    history: EditHistory,
+}
+
+/// Message sent from the loader thread.
+#[derive(Debug)]
+enum LoadMessage {
+    /// The byte position the parser has read until.
+    Progress(u64),
+    /// Loading is finished (successfully or not).
+    Finished(LoadResult),
+}
+
+/// Loads the given FBX file.
+///
+/// The format (binary or ASCII) is detected automatically.
+/// The file is parsed on a background thread, and the loaded document is
+/// rendered to the widgets afterwards.
+///
+/// If the current document has unsaved changes, the user is asked whether to
+/// discard them.
+pub fn load_fbx_file<P: AsRef<Path>>(path: P, widgets: &AppWidgets) {
+    if !confirm_discard(widgets) {
+        return;
+    }
+    let path = path.as_ref().to_owned();
+    println!("FBX file path = {}", path.display());
+    let lazy = widgets.lazy_loading.is_active();
+    {
+        let mut state = widgets.edit.borrow_mut();
+        state.history.clear();
+        state.format = detect_file_format(&path).ok();
+        state.path = Some(path.clone());
+        state.lazy = lazy && state.format == Some(Format::Binary);
+        state.complete = false;
+    }
+    update_title(widgets);
+
+    widgets.logs.clear();
+    widgets.node_tree.clear();
+    widgets.node_attrs.clear();
+    widgets.objects.clear();
+    widgets.summary.clear();
+    widgets.hex.set_file(&path);
+
+    let file_len = std::fs::metadata(&path).map_or(0, |meta| meta.len());
+    let cancel = widgets.progress.start(file_len);
+    let (sender, receiver) = async_channel::bounded(16);
+    {
+        let cancel = cancel.clone();
+        std::thread::spawn(move || load_fbx_file_worker(&path, lazy, &sender, &cancel));
+    }
+
+    let widgets = widgets.clone();
+    glib::MainContext::default().spawn_local(async move {
+        let AppWidgets {
+            logs,
+            node_tree,
+            node_attrs,
+            search,
+            objects,
+            summary,
+            diff,
+            progress,
+            ..
+        } = &widgets;
+        while let Ok(message) = receiver.recv().await {
+            if !progress.is_current(&cancel) {
+                // Another file is being loaded.
+                return;
+            }
+            match message {
+                LoadMessage::Progress(position) => progress.set_position(position),
+                LoadMessage::Finished(result) => {
+                    widgets.edit.borrow_mut().complete =
+                        result.document.is_some() && result.error.is_none();
+                    if let Some(document) = result.document {
+                        let version = document.fbx_version();
+                        println!("FBX version: {}.{}", version.major(), version.minor());
+                        objects.set_document(&document);
+                        summary.set_document(&document);
+                        node_tree.set_document(document);
+                        search.update(node_tree, node_attrs);
+                        diff.refresh(&node_tree.document().borrow());
+                    }
+                    match result.error {
+                        Some(err) => {
+                            println!("Failed to load FBX file: {}", err);
+                            logs.set_store(result.warnings.iter(), Some(err.as_ref()));
+                        }
+                        None => logs.set_store(result.warnings.iter(), None),
+                    }
+                    break;
+                }
+            }
+        }
+        progress.finish(&cancel);
+    });
+}
+
+/// Updates the window title for the loaded file.
+///
+/// The file name is marked with `*` if the document has unsaved changes.
+fn update_title(widgets: &AppWidgets) {
+    let state = widgets.edit.borrow();
+    let title = match &state.path {
+        Some(path) => format!(
+            "{} - {}{}",
+            WINDOW_TITLE_BASE,
+            if state.history.is_modified() { "*" } else { "" },
+            path.display()
+        ),
+        None => WINDOW_TITLE_BASE.to_owned(),
+    };
+    widgets.window.set_title(&title);
+}
+
+/// Shows the error message to the user.
+fn show_error(window: &Window, message: &str) {
+    println!("{}", message);
+    let dialog = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::MODAL,
+        gtk::MessageType::Error,
+        gtk::ButtonsType::Close,
+        message,
+    );
+    dialog.run();
+    dialog.close();
+}
+
+/// Asks the user whether to discard unsaved changes, and returns whether to
+/// proceed.
+fn confirm_discard(widgets: &AppWidgets) -> bool {
+    if !widgets.edit.borrow().history.is_modified() {
+        return true;
+    }
+    let dialog = gtk::MessageDialog::new(
+        Some(&widgets.window),
+        gtk::DialogFlags::MODAL,
+        gtk::MessageType::Question,
+        gtk::ButtonsType::OkCancel,
+        "The document has unsaved changes. Discard them?",
+    );
+    let response = dialog.run();
+    dialog.close();
+    response == gtk::ResponseType::Ok
+}
+
+/// Parses the text as the new value of the attribute, and sets it.
+fn edit_attribute(widgets: &AppWidgets, node: NodeId, index: usize, text: &str) {
+    let value = {
+        let document = widgets.node_tree.document().borrow();
+        match document
+            .get(node)
+            .and_then(|node| node.attributes().get(index))
+        {
+            Some(attr) => attr.parse_value(text),
+            None => return,
+        }
+    };
+    match value {
+        Ok(value) => apply_edit(widgets, Edit::SetAttribute { node, index, value }),
+        Err(err) => show_error(&widgets.window, &format!("Failed to edit: {}", err)),
+    }
+}
+
+/// Applies the edit to the document, and updates the widgets.
+fn apply_edit(widgets: &AppWidgets, edit: Edit) {
+    let res = {
+        let mut document = widgets.node_tree.document().borrow_mut();
+        widgets.edit.borrow_mut().history.apply(&mut document, edit)
+    };
+    match res {
+        Ok(node) => document_edited(widgets, node),
+        Err(err) => show_error(&widgets.window, &format!("Failed to edit: {}", err)),
+    }
+}
+
+/// Reverts the last edit, or applies the last reverted edit again if `redo`
+/// is true.
+fn undo(widgets: &AppWidgets, redo: bool) {
+    let node = {
+        let mut document = widgets.node_tree.document().borrow_mut();
+        let history = &mut widgets.edit.borrow_mut().history;
+        if redo && history.can_redo() {
+            history.redo(&mut document)
+        } else if !redo && history.can_undo() {
+            history.undo(&mut document)
+        } else {
+            println!("Nothing to {}", if redo { "redo" } else { "undo" });
+            return;
+        }
+    };
+    document_edited(widgets, node);
+}
+
+/// Updates the widgets for the edited document, and selects the node
+/// affected.
+fn document_edited(widgets: &AppWidgets, node: Option<NodeId>) {
+    let AppWidgets {
+        node_tree,
+        node_attrs,
+        search,
+        objects,
+        summary,
+        diff,
+        ..
+    } = widgets;
+    node_tree.reload();
+    {
+        let document = node_tree.document().borrow();
+        objects.set_document(&document);
+        summary.set_document(&document);
+    }
+    search.update(node_tree, node_attrs);
+    diff.refresh(&node_tree.document().borrow());
+    if let Some(node) = node {
+        node_tree.select_node(node);
+    }
+    update_title(widgets);
+}
+
+/// Saves the document to the loaded file, in the same format.
+///
+/// Lazily loaded documents are loaded again after saving, since the
+/// locations of the deferred attributes are changed.
+fn save_fbx_file(widgets: &AppWidgets) {
+    let (path, format, lazy) = {
+        let state = widgets.edit.borrow();
+        let path = match &state.path {
+            Some(v) => v.clone(),
+            None => {
+                println!("No document to save");
+                return;
+            }
+        };
+        if !state.complete {
+            show_error(
+                &widgets.window,
+                "The document is not completely loaded, and cannot be saved",
+            );
+            return;
+        }
+        (path, state.format, state.lazy)
+    };
+    let res = {
+        let document = widgets.node_tree.document().borrow();
+        match format {
+            Some(Format::Ascii) => save_ascii_file(&document, &path).map_err(|e| e.to_string()),
+            _ => save_binary_file(&document, &path, &WriteOptions::default())
+                .map_err(|e| e.to_string()),
+        }
+    };
+    if let Err(err) = res {
+        show_error(
+            &widgets.window,
+            &format!("Failed to save {}: {}", path.display(), err),
+        );
+        return;
+    }
+    println!("Saved {}", path.display());
+    widgets.edit.borrow_mut().history.mark_saved();
+    update_title(widgets);
+    if lazy {
+        load_fbx_file(path, widgets);
+    }
+}
+
+/// Loads the given FBX file and compares the current document with it.
+///
+/// The file is parsed on a background thread.
+/// Partially loaded documents are not compared, since the missing nodes
+/// would be shown as differences.
+pub fn load_comparison_file<P: AsRef<Path>>(path: P, widgets: &AppWidgets) {
+    let path = path.as_ref().to_owned();
+    println!("FBX file path to compare = {}", path.display());
+
+    let (sender, receiver) = async_channel::bounded(1);
+    {
+        let path = path.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send_blocking(load_file(path, |_| true));
+        });
+    }
+
+    let node_tree = widgets.node_tree.clone();
+    let diff = widgets.diff.clone();
+    glib::MainContext::default().spawn_local(async move {
+        let result: LoadResult = match receiver.recv().await {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+        for (warning, syn_pos) in &result.warnings {
+            println!("warning: {} (position: {:?})", warning, syn_pos);
+        }
+        match (result.document, result.error) {
+            (_, Some(err)) => println!("Failed to load FBX file to compare: {}", err),
+            (Some(other), None) => {
+                diff.set_other_document(&node_tree.document().borrow(), &path, other)
+            }
+            (None, None) => unreachable!("Should never fail: a document or an error is returned"),
+        }
+    });
+}
+
+/// Loads the given FBX file and sends the result to the receiver.
+///
+/// This is expected to run on a background thread.
+///
+/// If `lazy` is true, arrays and binaries are decoded on demand.
+fn load_fbx_file_worker(
+    path: &Path,
+    lazy: bool,
+    sender: &async_channel::Sender<LoadMessage>,
+    cancel: &AtomicBool,
+) {
+    let progress = |position| {
+        // The receiver may be already dropped if another file is being
+        // loaded.
+        sender
+            .send_blocking(LoadMessage::Progress(position))
+            .is_ok()
+            && !cancel.load(Ordering::Relaxed)
+    };
+    let result = if lazy {
+        load_file_lazy(path, DEFAULT_CACHE_CAPACITY, progress)
+    } else {
+        load_file(path, progress)
+    };
+    let _ = sender.send_blocking(LoadMessage::Finished(result));
+}
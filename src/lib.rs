@@ -0,0 +1,13 @@
+//! FBX tree viewer.
+//!
+//! The [`fbx`] module provides FBX loading and the in-memory document model,
+//! and does not depend on GTK.
+//! GUI modules are available with the `gui` feature, which is enabled by
+//! default.
+#![warn(missing_docs)]
+
+pub mod fbx;
+#[cfg(feature = "gui")]
+pub mod gui;
+#[cfg(feature = "gui")]
+pub mod widgets;
@@ -1,170 +1,409 @@
 //! FBX tree viewer.
 #![warn(missing_docs)]
 
-use gtk::prelude::*;
-use gtk::ScrolledWindow;
-use gtk::{AccelFlags, AccelGroup};
-use gtk::{FileChooserAction, FileChooserDialog, FileFilter};
-use gtk::{Menu, MenuBar, MenuItem};
-use gtk::{Orientation, Paned, Window, WindowType};
-
-use self::{
-    fbx::load_fbx_binary,
-    widgets::{FbxAttributeTable, FbxNodeTree, Logs},
+use std::{
+    ffi::OsString,
+    io::Write,
+    path::{Path, PathBuf},
+    process,
 };
 
-pub mod fbx;
-pub mod widgets;
+use fbx_tree_view::fbx::{
+    diff_documents, dump_document, export_json, load_file, save_ascii_file, save_binary_file,
+    write_diff, ArrayCompression, DiffOptions, Document, DumpOptions, JsonOptions, ObjectKey,
+    WriteOptions,
+};
+use fbxcel::low::FbxVersion;
+
+/// Usage of the command.
+const USAGE: &str = "\
+Usage:
+    fbx-tree-view [<FILE>]
+    fbx-tree-view dump [--max-depth <N>] [--max-array-len <N>] <FILE>
+    fbx-tree-view json [--max-array-len <N>] <FILE>
+    fbx-tree-view convert [--format <FORMAT>] [--fbx-version <VERSION>] [--compression <MODE>]
+                          <FILE> <OUTPUT>
+    fbx-tree-view diff [--float-tolerance <TOLERANCE>] [--match-objects <KEY>]
+                       [--ignore-numeric-types] <OLD> <NEW>
+
+Subcommands:
+    dump       Print the node tree of the FBX file without GUI
+    json       Print the node tree of the FBX file as JSON
+    convert    Write the FBX file as FBX binary or FBX ASCII
+    diff       Print the structural differences of two FBX files
+               Exit status is 0 if same, 1 if different, and 2 on errors
+
+Options for dump:
+    --max-depth <N>        Print nodes only until depth N (top-level is 0)
+    --max-array-len <N>    Print at most N elements for each array attribute
 
-/// Base of the window title.
-pub const WINDOW_TITLE_BASE: &str = "FBX tree viewer";
+Options for json:
+    --max-array-len <N>    Write at most N elements for each array attribute
+
+Options for convert:
+    --format <FORMAT>          Output format: binary or ascii (default: binary)
+    --fbx-version <VERSION>    FBX version to write: 7400 or 7500
+                               (default: same as the input)
+                               Ignored for ascii format
+    --compression <MODE>       Array encoding: keep, raw, or zlib (default: keep)
+                               Ignored for ascii format
+
+Options for diff:
+    --float-tolerance <TOLERANCE>    Maximum difference of floating point
+                                     numbers regarded as equal (default: 0)
+    --match-objects <KEY>            Align objects by id or name (default: id)
+    --ignore-numeric-types           Compare numbers of different types (such
+                                     as i32 and i64) by value, which is useful
+                                     for FBX ASCII files";
 
 fn main() {
-    gtk::init().expect("Failed to initialize GTK");
-
-    let window_width = 800;
-    let window_height = 600;
-
-    let window = Window::new(WindowType::Toplevel);
-    window.set_title(WINDOW_TITLE_BASE);
-    window.set_default_size(window_width, window_height);
-
-    //
-    // Window root.
-    //
-
-    let root_widget = gtk::Box::new(Orientation::Vertical, 0);
-    window.add(&root_widget);
-    let accel_group = AccelGroup::new();
-    window.add_accel_group(&accel_group);
-
-    //
-    // Menu bar.
-    //
-
-    let menu_bar = MenuBar::new();
-    let menu_file = MenuItem::with_mnemonic("_File");
-    let submenu_file = Menu::new();
-    let menu_file_open = MenuItem::with_mnemonic("_Open FBX binary");
-    submenu_file.append(&menu_file_open);
-    submenu_file.append(&gtk::SeparatorMenuItem::new());
-    let menu_file_quit = MenuItem::with_mnemonic("_Quit");
-    submenu_file.append(&menu_file_quit);
-    menu_file.set_submenu(Some(&submenu_file));
-    menu_bar.append(&menu_file);
-    root_widget.pack_start(&menu_bar, false, false, 0);
+    let args: Vec<OsString> = std::env::args_os().skip(1).collect();
+    match args.first().and_then(|arg| arg.to_str()) {
+        Some("dump") => process::exit(run_dump(&args[1..])),
+        Some("json") => process::exit(run_json(&args[1..])),
+        Some("convert") => process::exit(run_convert(&args[1..])),
+        Some("diff") => process::exit(run_diff(&args[1..])),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            return;
+        }
+        _ => {}
+    }
+    if args.len() > 1 {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
 
+    #[cfg(feature = "gui")]
+    fbx_tree_view::gui::run(args.into_iter().next().map(PathBuf::from));
+    #[cfg(not(feature = "gui"))]
     {
-        menu_file_open.add_accelerator(
-            "activate",
-            &accel_group,
-            *gdk::keys::constants::O,
-            gdk::ModifierType::CONTROL_MASK,
-            AccelFlags::VISIBLE,
-        );
-        menu_file_quit.add_accelerator(
-            "activate",
-            &accel_group,
-            *gdk::keys::constants::Q,
-            gdk::ModifierType::CONTROL_MASK,
-            AccelFlags::VISIBLE,
-        );
-    }
-
-    //
-    // FBX tree.
-    //
-
-    let node_tree = FbxNodeTree::new();
-    let scrolled_node_tree = ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
-    scrolled_node_tree.add(node_tree.widget());
-
-    //
-    // Node data.
-    //
-
-    let node_attrs = FbxAttributeTable::new();
-    let scrolled_node_attrs = ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
-    scrolled_node_attrs.add(node_attrs.widget());
-
-    node_tree.initialize(&node_attrs);
-
-    //
-    // FBX tree and node data.
-    //
-
-    let fbx_data_pane = Paned::new(Orientation::Horizontal);
-    fbx_data_pane.add1(&scrolled_node_tree);
-    fbx_data_pane.add2(&scrolled_node_attrs);
-    fbx_data_pane.set_position(window_width / 5 * 4);
-
-    //
-    // Warnings and errors.
-    //
-
-    let logs = Logs::new();
-    let scrolled_logs = ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
-    scrolled_logs.add(logs.widget());
-
-    //
-    // Main region of the window.
-    //
-
-    let content_pane = Paned::new(Orientation::Vertical);
-    content_pane.add1(&fbx_data_pane);
-    content_pane.add2(&scrolled_logs);
-    content_pane.set_wide_handle(true);
-    content_pane.set_position(window_height / 5 * 4);
-
-    root_widget.pack_start(&content_pane, true, true, 0);
-
-    window.show_all();
-
-    window.connect_delete_event(|_, _| {
-        gtk::main_quit();
-        glib::Propagation::Proceed
-    });
+        eprintln!("GUI is not available: built without `gui` feature");
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+}
 
-    {
-        let fbx_binary_chooser = create_fbx_binary_chooser(&window);
-        menu_file_open.connect_activate(move |_| {
-            if fbx_binary_chooser.run() == gtk::ResponseType::Ok {
-                if let Some(filename) = fbx_binary_chooser.filename() {
-                    load_fbx_binary(filename, &window, &logs, &node_tree, &node_attrs);
+/// Runs `dump` subcommand, and returns the exit status.
+fn run_dump(args: &[OsString]) -> i32 {
+    let mut options = DumpOptions::default();
+    let mut path = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--max-depth") => match parse_usize_arg(arg, args.next()) {
+                Ok(v) => options.max_depth = Some(v),
+                Err(status) => return status,
+            },
+            Some("--max-array-len") => match parse_usize_arg(arg, args.next()) {
+                Ok(v) => options.max_array_len = Some(v),
+                Err(status) => return status,
+            },
+            Some(opt) if opt.starts_with('-') => {
+                eprintln!("Unknown option: {}", opt);
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => {
+                eprintln!("Too many arguments");
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+        }
+    }
+    let path = match path {
+        Some(v) => v,
+        None => {
+            eprintln!("FBX file is not specified");
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+
+    write_loaded(&path, |document, mut out| {
+        dump_document(document, &mut out, &options)
+    })
+}
+
+/// Runs `json` subcommand, and returns the exit status.
+fn run_json(args: &[OsString]) -> i32 {
+    let mut options = JsonOptions::default();
+    let mut path = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--max-array-len") => match parse_usize_arg(arg, args.next()) {
+                Ok(v) => options.max_array_len = Some(v),
+                Err(status) => return status,
+            },
+            Some(opt) if opt.starts_with('-') => {
+                eprintln!("Unknown option: {}", opt);
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => {
+                eprintln!("Too many arguments");
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+        }
+    }
+    let path = match path {
+        Some(v) => v,
+        None => {
+            eprintln!("FBX file is not specified");
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+
+    write_loaded(&path, |document, mut out| {
+        export_json(document, &mut out, &options)
+    })
+}
+
+/// Runs `convert` subcommand, and returns the exit status.
+fn run_convert(args: &[OsString]) -> i32 {
+    let mut options = WriteOptions::default();
+    let mut ascii = false;
+    let mut paths = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--format") => match args.next().and_then(|v| v.to_str()) {
+                Some("binary") => ascii = false,
+                Some("ascii") => ascii = true,
+                Some(v) => {
+                    eprintln!("Invalid value for --format: {}", v);
+                    return 2;
+                }
+                None => {
+                    eprintln!("Missing value for --format");
+                    return 2;
+                }
+            },
+            Some("--fbx-version") => match args.next().and_then(|v| v.to_str()) {
+                Some("7400") => options.fbx_version = Some(FbxVersion::V7_4),
+                Some("7500") => options.fbx_version = Some(FbxVersion::V7_5),
+                Some(v) => {
+                    eprintln!("Invalid value for --fbx-version: {}", v);
+                    return 2;
+                }
+                None => {
+                    eprintln!("Missing value for --fbx-version");
+                    return 2;
+                }
+            },
+            Some("--compression") => match args.next().and_then(|v| v.to_str()) {
+                Some("keep") => options.compression = ArrayCompression::Keep,
+                Some("raw") => options.compression = ArrayCompression::Raw,
+                Some("zlib") => options.compression = ArrayCompression::Zlib,
+                Some(v) => {
+                    eprintln!("Invalid value for --compression: {}", v);
+                    return 2;
                 }
+                None => {
+                    eprintln!("Missing value for --compression");
+                    return 2;
+                }
+            },
+            Some(opt) if opt.starts_with('-') => {
+                eprintln!("Unknown option: {}", opt);
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+            _ if paths.len() < 2 => paths.push(PathBuf::from(arg)),
+            _ => {
+                eprintln!("Too many arguments");
+                eprintln!("{}", USAGE);
+                return 2;
             }
-            fbx_binary_chooser.hide();
-        });
+        }
     }
-    menu_file_quit.connect_activate(move |_| {
-        gtk::main_quit();
-    });
+    let (path, output) = match &paths[..] {
+        [path, output] => (path, output),
+        _ => {
+            eprintln!("Input and output files are not specified");
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
 
-    gtk::main();
+    let result = load_file(path, |_| true);
+    for (warning, syn_pos) in &result.warnings {
+        eprintln!("warning: {} (position: {:?})", warning, syn_pos);
+    }
+    // A partially loaded document is not written, since it would silently
+    // lose the rest of the file.
+    match (result.document, result.error) {
+        (_, Some(err)) => {
+            eprintln!("Failed to load {}: {}", path.display(), err);
+            1
+        }
+        (Some(document), None) if ascii => match save_ascii_file(&document, output) {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("Failed to write {}: {}", output.display(), err);
+                1
+            }
+        },
+        (Some(document), None) => match save_binary_file(&document, output, &options) {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("Failed to write {}: {}", output.display(), err);
+                1
+            }
+        },
+        (None, None) => unreachable!("Should never fail: a document or an error is returned"),
+    }
 }
 
-fn create_fbx_binary_chooser<'a, W: Into<Option<&'a Window>>>(window: W) -> FileChooserDialog {
-    let file_chooser = FileChooserDialog::new(
-        Some("Open FBX binary file"),
-        window.into(),
-        FileChooserAction::Open,
-    );
-    {
-        let fbx_filter = FileFilter::new();
-        fbx_filter.set_name(Some("FBX files"));
-        fbx_filter.add_pattern("*.fbx");
-        fbx_filter.add_pattern("*.FBX");
-        file_chooser.add_filter(fbx_filter);
+/// Runs `diff` subcommand, and returns the exit status.
+///
+/// The status is 0 if the files are same, 1 if different, and 2 on errors.
+fn run_diff(args: &[OsString]) -> i32 {
+    let mut options = DiffOptions::default();
+    let mut paths = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--float-tolerance") => {
+                match args.next().and_then(|v| v.to_str()).map(str::parse::<f64>) {
+                    Some(Ok(v)) if v >= 0.0 => options.float_tolerance = v,
+                    Some(_) => {
+                        eprintln!(
+                            "Invalid value for --float-tolerance: expected non-negative number"
+                        );
+                        return 2;
+                    }
+                    None => {
+                        eprintln!("Missing value for --float-tolerance");
+                        return 2;
+                    }
+                }
+            }
+            Some("--match-objects") => match args.next().and_then(|v| v.to_str()) {
+                Some("id") => options.object_key = ObjectKey::Id,
+                Some("name") => options.object_key = ObjectKey::Name,
+                Some(v) => {
+                    eprintln!("Invalid value for --match-objects: {}", v);
+                    return 2;
+                }
+                None => {
+                    eprintln!("Missing value for --match-objects");
+                    return 2;
+                }
+            },
+            Some("--ignore-numeric-types") => options.ignore_numeric_types = true,
+            Some(opt) if opt.starts_with('-') => {
+                eprintln!("Unknown option: {}", opt);
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+            _ if paths.len() < 2 => paths.push(PathBuf::from(arg)),
+            _ => {
+                eprintln!("Too many arguments");
+                eprintln!("{}", USAGE);
+                return 2;
+            }
+        }
+    }
+    let (old_path, new_path) = match &paths[..] {
+        [old_path, new_path] => (old_path, new_path),
+        _ => {
+            eprintln!("Two files to compare are not specified");
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+
+    let mut documents = Vec::with_capacity(2);
+    for path in &[old_path, new_path] {
+        let result = load_file(path, |_| true);
+        for (warning, syn_pos) in &result.warnings {
+            eprintln!("warning: {} (position: {:?})", warning, syn_pos);
+        }
+        // Partially loaded documents are not compared, since the missing
+        // nodes would be reported as differences.
+        match (result.document, result.error) {
+            (_, Some(err)) => {
+                eprintln!("Failed to load {}: {}", path.display(), err);
+                return 2;
+            }
+            (Some(document), None) => documents.push(document),
+            (None, None) => unreachable!("Should never fail: a document or an error is returned"),
+        }
+    }
+
+    let diff = match diff_documents(&documents[0], &documents[1], &options) {
+        Ok(v) => v,
+        Err(err) => {
+            eprintln!("Failed to compare the files: {}", err);
+            return 2;
+        }
+    };
+    let stdout = std::io::stdout();
+    let mut out = std::io::BufWriter::new(stdout.lock());
+    if let Err(err) = write_diff(&diff, &mut out).and_then(|_| out.flush()) {
+        eprintln!("Failed to write the output: {}", err);
+        return 2;
+    }
+    if diff.has_differences() {
+        1
+    } else {
+        0
+    }
+}
+
+/// Loads the FBX file and writes the document to stdout by `write`, and
+/// returns the exit status.
+///
+/// A partially loaded document is also written, but the status is failure.
+fn write_loaded<F>(path: &Path, write: F) -> i32
+where
+    F: FnOnce(&Document, &mut dyn Write) -> std::io::Result<()>,
+{
+    let result = load_file(path, |_| true);
+    for (warning, syn_pos) in &result.warnings {
+        eprintln!("warning: {} (position: {:?})", warning, syn_pos);
+    }
+    if let Some(document) = &result.document {
+        let stdout = std::io::stdout();
+        let mut out = std::io::BufWriter::new(stdout.lock());
+        if let Err(err) = write(document, &mut out).and_then(|_| out.flush()) {
+            eprintln!("Failed to write the output: {}", err);
+            return 1;
+        }
+    }
+    match result.error {
+        Some(err) => {
+            eprintln!("Failed to load {}: {}", path.display(), err);
+            1
+        }
+        None => 0,
+    }
+}
+
+/// Parses the value of the given option as `usize`.
+///
+/// Returns the exit status on failure.
+fn parse_usize_arg(opt: &OsString, value: Option<&OsString>) -> Result<usize, i32> {
+    let opt = opt.to_string_lossy();
+    match value.and_then(|v| v.to_str()).map(str::parse) {
+        Some(Ok(v)) => Ok(v),
+        Some(Err(_)) => {
+            eprintln!("Invalid value for {}: expected non-negative integer", opt);
+            Err(2)
+        }
+        None => {
+            eprintln!("Missing value for {}", opt);
+            Err(2)
+        }
     }
-    {
-        let all_filter = FileFilter::new();
-        all_filter.set_name(Some("All files"));
-        all_filter.add_pattern("*");
-        file_chooser.add_filter(all_filter);
-    }
-    file_chooser.add_buttons(&[
-        ("Cancel", gtk::ResponseType::Cancel),
-        ("Open", gtk::ResponseType::Ok),
-    ]);
-    file_chooser
 }
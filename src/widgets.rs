@@ -1,7 +1,54 @@
 //! Widgets.
 
-pub use self::{attribute_table::FbxAttributeTable, logs::Logs, node_tree::FbxNodeTree};
+use gtk::{prelude::*, TreeView};
 
+pub use self::{
+    animation_view::FbxAnimationView, array_view::FbxArrayView, attribute_table::FbxAttributeTable,
+    diff_view::FbxDiffView, embedded_view::FbxEmbeddedView, file_summary::FbxFileSummary,
+    hex_view::FbxHexView, layer_element_view::FbxLayerElementView, logs::Logs,
+    mesh_preview::FbxMeshPreview, mesh_view::FbxMeshView, node_search::FbxNodeSearch,
+    node_tree::FbxNodeTree, object_list::FbxObjectList, progress::LoadProgress,
+    property_table::FbxPropertyTable,
+};
+
+mod animation_view;
+mod array_view;
 mod attribute_table;
+mod diff_view;
+mod embedded_view;
+mod file_summary;
+mod hex_view;
+mod layer_element_view;
 mod logs;
+mod mesh_preview;
+mod mesh_view;
+mod node_search;
 mod node_tree;
+mod object_list;
+mod progress;
+mod property_table;
+
+/// Appends a text column.
+///
+/// If `error_column` is given, rows are shown in red when the column is
+/// true.
+pub(crate) fn append_text_column(
+    view: &TreeView,
+    title: &str,
+    column_index: i32,
+    error_column: Option<i32>,
+) {
+    use gtk::{CellRendererText, TreeViewColumn};
+
+    let column = TreeViewColumn::new();
+    let cell = CellRendererText::new();
+    TreeViewColumnExt::pack_start(&column, &cell, true);
+    if let Some(error_column) = error_column {
+        cell.set_foreground(Some("#cc0000"));
+        TreeViewColumnExt::add_attribute(&column, &cell, "foreground-set", error_column);
+    }
+    column.set_title(title);
+    TreeViewColumnExt::add_attribute(&column, &cell, "text", column_index);
+    column.set_resizable(true);
+    view.append_column(&column);
+}
@@ -0,0 +1,310 @@
+//! Animation curve inspector widget.
+
+use std::{cell::RefCell, rc::Rc};
+
+use glib::Type;
+use gtk::{
+    cairo, prelude::*, DrawingArea, Label, ListStore, Orientation, Paned, ScrolledWindow, TreeView,
+};
+
+use crate::{
+    fbx::{
+        curve_node_channels, ticks_to_seconds, AnimationCurve, Document, NodeId, ObjectIndex,
+        TimeMode,
+    },
+    widgets::append_text_column,
+};
+
+/// Colors of the plotted channels.
+const CHANNEL_COLORS: &[(f64, f64, f64)] = &[
+    (0.9, 0.2, 0.2),
+    (0.2, 0.7, 0.2),
+    (0.3, 0.4, 1.0),
+    (0.9, 0.6, 0.1),
+    (0.7, 0.3, 0.8),
+];
+/// Margin around the plot in pixels.
+const PLOT_MARGIN: f64 = 24.0;
+
+/// Animation curve inspector widget.
+///
+/// This shows the keys of an `AnimationCurve` node, or of all curves
+/// connected to an `AnimationCurveNode` node, with a plot.
+#[derive(Debug, Clone)]
+pub struct FbxAnimationView {
+    widget: gtk::Box,
+    summary: Label,
+    plot: DrawingArea,
+    store: ListStore,
+    view: TreeView,
+    /// Channel names and curves being shown.
+    curves: Rc<RefCell<Vec<(String, AnimationCurve)>>>,
+}
+
+impl FbxAnimationView {
+    /// Creates a new animation curve inspector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the inspector.
+    pub fn clear(&self) {
+        self.summary.set_text("");
+        self.store.clear();
+        self.curves.borrow_mut().clear();
+        self.plot.queue_draw();
+    }
+
+    /// Shows the animation curves of the given node.
+    ///
+    /// Returns `false` and clears the inspector if the node is neither an
+    /// `AnimationCurve` nor an `AnimationCurveNode` with curves.
+    pub fn show_node(&self, document: &Document, node_id: NodeId) -> bool {
+        self.clear();
+        let curves: Vec<_> = match document.node(node_id).name() {
+            "AnimationCurve" => AnimationCurve::from_node(document, node_id)
+                .map(|curve| (String::new(), curve))
+                .into_iter()
+                .collect(),
+            "AnimationCurveNode" => {
+                let index = ObjectIndex::new(document);
+                curve_node_channels(document, &index, node_id)
+                    .into_iter()
+                    .filter_map(|(channel, curve)| {
+                        AnimationCurve::from_node(document, curve).map(|curve| (channel, curve))
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        if curves.is_empty() {
+            return false;
+        }
+
+        let time_mode = TimeMode::from_document(document);
+        let mut summary = format!(
+            "TimeMode {} ({} fps)",
+            time_mode.value(),
+            time_mode.frames_per_second()
+        );
+        for (channel, curve) in &curves {
+            summary.push_str(&format!(
+                "\n{}: {} keys",
+                channel_label(channel),
+                curve.keys().len()
+            ));
+            if let Some(default) = curve.default_value() {
+                summary.push_str(&format!(", default {}", default));
+            }
+            for issue in curve.issues() {
+                summary.push_str("\nwarning: ");
+                summary.push_str(issue);
+            }
+        }
+        self.summary.set_text(&summary);
+
+        // Detach the model during bulk insertion, for efficiency.
+        self.view.set_model(None::<&ListStore>);
+        for (channel, curve) in &curves {
+            for (index, key) in curve.keys().iter().enumerate() {
+                let flags = match key.flags_description() {
+                    description if description.is_empty() => format!("{:#x}", key.flags()),
+                    description => format!("{:#x} ({})", key.flags(), description),
+                };
+                self.store.insert_with_values(
+                    None,
+                    &[
+                        (0, &channel_label(channel)),
+                        (1, &(index as u64)),
+                        (2, &key.time()),
+                        (3, &ticks_to_seconds(key.time()).to_string()),
+                        (4, &time_mode.ticks_to_frames(key.time()).to_string()),
+                        (5, &key.value().to_string()),
+                        (6, &key.interpolation().name()),
+                        (7, &flags),
+                    ],
+                );
+            }
+        }
+        self.view.set_model(Some(&self.store));
+
+        *self.curves.borrow_mut() = curves;
+        self.plot.queue_draw();
+
+        true
+    }
+
+    /// Returns a reference to the root widget.
+    pub fn widget(&self) -> &gtk::Box {
+        &self.widget
+    }
+}
+
+impl Default for FbxAnimationView {
+    fn default() -> Self {
+        // channel, key, ticks, seconds, frame, value, interpolation, flags.
+        let store = ListStore::new(&[
+            Type::STRING,
+            Type::U64,
+            Type::I64,
+            Type::STRING,
+            Type::STRING,
+            Type::STRING,
+            Type::STRING,
+            Type::STRING,
+        ]);
+        let view = TreeView::with_model(&store);
+        view.set_grid_lines(gtk::TreeViewGridLines::Vertical);
+        for (index, title) in [
+            "channel",
+            "key",
+            "time (ticks)",
+            "seconds",
+            "frame",
+            "value",
+            "interpolation",
+            "flags",
+        ]
+        .iter()
+        .enumerate()
+        {
+            append_text_column(&view, title, index as i32, None);
+        }
+
+        let curves = Rc::new(RefCell::new(Vec::new()));
+        let plot = DrawingArea::new();
+        plot.set_size_request(200, 150);
+        {
+            let curves = curves.clone();
+            plot.connect_draw(move |plot, cr| {
+                let width = f64::from(plot.allocated_width());
+                let height = f64::from(plot.allocated_height());
+                if let Err(e) = draw_curves(&curves.borrow(), cr, width, height) {
+                    println!("Failed to draw the animation curves: {}", e);
+                }
+                glib::Propagation::Stop
+            });
+        }
+
+        let summary = Label::new(None);
+        summary.set_xalign(0.0);
+        summary.set_selectable(true);
+        summary.set_line_wrap(true);
+
+        let scrolled = ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
+        scrolled.add(&view);
+        let panes = Paned::new(Orientation::Vertical);
+        panes.pack1(&plot, true, true);
+        panes.pack2(&scrolled, true, true);
+
+        let widget = gtk::Box::new(Orientation::Vertical, 4);
+        widget.pack_start(&summary, false, false, 0);
+        widget.pack_start(&panes, true, true, 0);
+
+        Self {
+            widget,
+            summary,
+            plot,
+            store,
+            view,
+            curves,
+        }
+    }
+}
+
+/// Returns the label of the channel.
+fn channel_label(channel: &str) -> &str {
+    if channel.is_empty() {
+        "(curve)"
+    } else {
+        channel
+    }
+}
+
+/// Plots the curves.
+fn draw_curves(
+    curves: &[(String, AnimationCurve)],
+    cr: &cairo::Context,
+    width: f64,
+    height: f64,
+) -> Result<(), cairo::Error> {
+    cr.set_source_rgb(1.0, 1.0, 1.0);
+    cr.paint()?;
+
+    let keys = curves.iter().flat_map(|(_, curve)| curve.keys());
+    let (mut min_time, mut max_time) = (i64::MAX, i64::MIN);
+    let (mut min_value, mut max_value) = (f64::INFINITY, f64::NEG_INFINITY);
+    for key in keys.filter(|key| key.value().is_finite()) {
+        min_time = min_time.min(key.time());
+        max_time = max_time.max(key.time());
+        min_value = min_value.min(key.value());
+        max_value = max_value.max(key.value());
+    }
+    if min_time > max_time {
+        return Ok(());
+    }
+    // Avoid zero ranges, and leave room for cubic overshoots.
+    if max_time == min_time {
+        max_time = min_time + 1;
+    }
+    let value_margin = ((max_value - min_value) * 0.1).max(1e-6);
+    min_value -= value_margin;
+    max_value += value_margin;
+
+    let plot_width = (width - PLOT_MARGIN * 2.0).max(1.0);
+    let plot_height = (height - PLOT_MARGIN * 2.0).max(1.0);
+    let time_span = max_time as f64 - min_time as f64;
+    let to_x = |time: i64| PLOT_MARGIN + (time as f64 - min_time as f64) / time_span * plot_width;
+    let to_y =
+        |value: f64| PLOT_MARGIN + (max_value - value) / (max_value - min_value) * plot_height;
+
+    // Frame and labels.
+    cr.set_source_rgb(0.6, 0.6, 0.6);
+    cr.set_line_width(1.0);
+    cr.rectangle(PLOT_MARGIN, PLOT_MARGIN, plot_width, plot_height);
+    cr.stroke()?;
+    cr.set_source_rgb(0.2, 0.2, 0.2);
+    cr.move_to(PLOT_MARGIN, height - 8.0);
+    cr.show_text(&format!("{}s", ticks_to_seconds(min_time)))?;
+    cr.move_to(PLOT_MARGIN + plot_width - 40.0, height - 8.0);
+    cr.show_text(&format!("{}s", ticks_to_seconds(max_time)))?;
+    cr.move_to(2.0, PLOT_MARGIN - 8.0);
+    cr.show_text(&format!("{:.4}", max_value))?;
+    cr.move_to(2.0, height - PLOT_MARGIN + 14.0);
+    cr.show_text(&format!("{:.4}", min_value))?;
+
+    for (index, (channel, curve)) in curves.iter().enumerate() {
+        let (r, g, b) = CHANNEL_COLORS[index % CHANNEL_COLORS.len()];
+        cr.set_source_rgb(r, g, b);
+
+        // Sample the curve for each pixel.
+        cr.set_line_width(1.5);
+        let num_samples = plot_width as i64;
+        for sample in 0..=num_samples {
+            let time = min_time + (time_span * sample as f64 / num_samples as f64) as i64;
+            let value = match curve.evaluate(time).filter(|v| v.is_finite()) {
+                Some(v) => v,
+                None => continue,
+            };
+            if sample == 0 {
+                cr.move_to(to_x(time), to_y(value));
+            } else {
+                cr.line_to(to_x(time), to_y(value));
+            }
+        }
+        cr.stroke()?;
+
+        for key in curve.keys().iter().filter(|key| key.value().is_finite()) {
+            cr.rectangle(to_x(key.time()) - 2.5, to_y(key.value()) - 2.5, 5.0, 5.0);
+        }
+        cr.fill()?;
+
+        cr.move_to(
+            PLOT_MARGIN + plot_width - 60.0,
+            PLOT_MARGIN + 14.0 * (index + 1) as f64,
+        );
+        cr.show_text(channel_label(channel))?;
+    }
+
+    Ok(())
+}
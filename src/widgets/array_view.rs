@@ -0,0 +1,348 @@
+//! Array attribute viewer.
+
+use std::{cell::RefCell, rc::Rc};
+
+use gtk::{
+    cairo, prelude::*, Adjustment, ComboBoxText, DrawingArea, Label, Orientation, Scrollbar,
+    SpinButton,
+};
+
+use crate::fbx::{Attribute, Document, NodeId};
+
+/// Default number of elements per row.
+const DEFAULT_ROW_WIDTH: f64 = 8.0;
+/// Maximum number of elements per row.
+const MAX_ROW_WIDTH: f64 = 64.0;
+/// Height of a row in pixels.
+const ROW_HEIGHT: f64 = 16.0;
+/// Font size in pixels.
+const FONT_SIZE: f64 = 12.0;
+/// Margin around the text in pixels.
+const MARGIN: f64 = 4.0;
+/// Number of rows scrolled per mouse wheel step.
+const ROWS_PER_SCROLL: f64 = 3.0;
+/// Background color of the element jumped to.
+const TARGET_COLOR: (f64, f64, f64) = (0.99, 0.91, 0.31);
+
+/// Array attribute viewer.
+///
+/// This shows the elements of an array attribute in a grid of index and
+/// values.
+/// Only the visible rows are formatted, so huge arrays can be shown.
+#[derive(Debug, Clone)]
+pub struct FbxArrayView {
+    widget: gtk::Box,
+    attr_combo: ComboBoxText,
+    row_width: SpinButton,
+    jump: SpinButton,
+    area: DrawingArea,
+    adjustment: Adjustment,
+    state: Rc<RefCell<ArrayState>>,
+}
+
+impl FbxArrayView {
+    /// Creates a new array viewer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the viewer.
+    pub fn clear(&self) {
+        self.state.borrow_mut().arrays.clear();
+        self.attr_combo.remove_all();
+        self.update_range();
+    }
+
+    /// Shows the array attributes of the given node.
+    ///
+    /// Returns `false` and clears the viewer if the node has no array
+    /// attributes.
+    pub fn show_node(&self, document: &Document, node_id: NodeId) -> bool {
+        self.clear();
+        let attrs = match document.load_attributes(node_id) {
+            Ok(v) => v,
+            Err(err) => {
+                println!("Failed to load attributes: {}", err);
+                return false;
+            }
+        };
+        let arrays: Vec<_> = attrs
+            .iter()
+            .enumerate()
+            .filter(|(_, attr)| attr.array_len().is_some() && !matches!(attr, Attribute::Binary(_)))
+            .map(|(index, attr)| (index, attr.clone()))
+            .collect();
+        if arrays.is_empty() {
+            return false;
+        }
+
+        for (index, attr) in &arrays {
+            self.attr_combo.append_text(&format!(
+                "#{} {} ({} elements)",
+                index,
+                attr.type_string(),
+                attr.array_len().unwrap_or(0)
+            ));
+        }
+        {
+            let mut state = self.state.borrow_mut();
+            state.arrays = arrays;
+            state.current = 0;
+        }
+        // This updates the range by the `changed` handler.
+        self.attr_combo.set_active(Some(0));
+        true
+    }
+
+    /// Updates the ranges of the scrollbar and the index to jump to.
+    fn update_range(&self) {
+        let (num_rows, len) = {
+            let state = self.state.borrow();
+            (state.num_rows(), state.len())
+        };
+        self.adjustment.set_upper(num_rows as f64);
+        self.adjustment.set_value(0.0);
+        self.jump.set_range(0.0, len.saturating_sub(1) as f64);
+        // Clamping the index to jump to may change the target.
+        self.state.borrow_mut().target = None;
+        self.area.queue_draw();
+    }
+
+    /// Returns a reference to the root widget.
+    pub fn widget(&self) -> &gtk::Box {
+        &self.widget
+    }
+}
+
+impl Default for FbxArrayView {
+    fn default() -> Self {
+        let state = Rc::new(RefCell::new(ArrayState {
+            row_width: DEFAULT_ROW_WIDTH as usize,
+            ..ArrayState::default()
+        }));
+        let adjustment = Adjustment::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0);
+
+        let attr_combo = ComboBoxText::new();
+        let row_width = SpinButton::with_range(1.0, MAX_ROW_WIDTH, 1.0);
+        row_width.set_value(DEFAULT_ROW_WIDTH);
+        let jump = SpinButton::with_range(0.0, 0.0, 1.0);
+        jump.set_numeric(true);
+
+        let controls = gtk::Box::new(Orientation::Horizontal, 4);
+        controls.pack_start(&Label::new(Some("attribute:")), false, false, 0);
+        controls.pack_start(&attr_combo, false, false, 0);
+        controls.pack_start(&Label::new(Some("elements per row:")), false, false, 0);
+        controls.pack_start(&row_width, false, false, 0);
+        controls.pack_start(&Label::new(Some("jump to index:")), false, false, 0);
+        controls.pack_start(&jump, false, false, 0);
+
+        let area = DrawingArea::new();
+        area.add_events(gdk::EventMask::SCROLL_MASK);
+        {
+            let state = state.clone();
+            let adjustment = adjustment.clone();
+            area.connect_draw(move |area, cr| {
+                let height = f64::from(area.allocated_height());
+                if let Err(e) = state.borrow_mut().draw(cr, adjustment.value(), height) {
+                    println!("Failed to draw the array view: {}", e);
+                }
+                glib::Propagation::Stop
+            });
+        }
+        {
+            let adjustment = adjustment.clone();
+            area.connect_size_allocate(move |_, allocation| {
+                // The first line is the header.
+                let rows = ((f64::from(allocation.height()) - MARGIN) / ROW_HEIGHT - 1.0).floor();
+                adjustment.set_page_size(rows.max(1.0));
+                adjustment.set_page_increment(rows.max(1.0));
+            });
+        }
+        {
+            let adjustment = adjustment.clone();
+            area.connect_scroll_event(move |_, event| {
+                let steps = match event.direction() {
+                    gdk::ScrollDirection::Up => -1.0,
+                    gdk::ScrollDirection::Down => 1.0,
+                    _ => 0.0,
+                };
+                adjustment.set_value(adjustment.value() + steps * ROWS_PER_SCROLL);
+                glib::Propagation::Stop
+            });
+        }
+        {
+            let area = area.clone();
+            adjustment.connect_value_changed(move |_| area.queue_draw());
+        }
+
+        let scrollbar = Scrollbar::new(Orientation::Vertical, Some(&adjustment));
+        let view_box = gtk::Box::new(Orientation::Horizontal, 0);
+        view_box.pack_start(&area, true, true, 0);
+        view_box.pack_start(&scrollbar, false, false, 0);
+
+        let widget = gtk::Box::new(Orientation::Vertical, 4);
+        widget.pack_start(&controls, false, false, 0);
+        widget.pack_start(&view_box, true, true, 0);
+
+        let view = Self {
+            widget,
+            attr_combo,
+            row_width,
+            jump,
+            area,
+            adjustment,
+            state,
+        };
+
+        {
+            let view_cloned = view.clone();
+            view.attr_combo.connect_changed(move |combo| {
+                if let Some(active) = combo.active() {
+                    view_cloned.state.borrow_mut().current = active as usize;
+                    view_cloned.update_range();
+                }
+            });
+        }
+        {
+            let view_cloned = view.clone();
+            view.row_width.connect_value_changed(move |spin| {
+                // Keep the first visible element visible.
+                let first = {
+                    let mut state = view_cloned.state.borrow_mut();
+                    let first = view_cloned.adjustment.value() as usize * state.row_width;
+                    state.row_width = spin.value_as_int().max(1) as usize;
+                    first / state.row_width
+                };
+                let num_rows = view_cloned.state.borrow().num_rows();
+                view_cloned.adjustment.set_upper(num_rows as f64);
+                view_cloned.adjustment.set_value(first as f64);
+                view_cloned.area.queue_draw();
+            });
+        }
+        {
+            let view_cloned = view.clone();
+            view.jump.connect_value_changed(move |spin| {
+                let row = {
+                    let mut state = view_cloned.state.borrow_mut();
+                    if state.len() == 0 {
+                        return;
+                    }
+                    let index = spin.value_as_int().max(0) as usize;
+                    state.target = Some(index);
+                    index / state.row_width
+                };
+                let adjustment = &view_cloned.adjustment;
+                let top = adjustment.value();
+                if (row as f64) < top || row as f64 >= top + adjustment.page_size() {
+                    adjustment.set_value(row as f64);
+                }
+                view_cloned.area.queue_draw();
+            });
+        }
+
+        view
+    }
+}
+
+/// State of the array viewer.
+#[derive(Debug, Default)]
+struct ArrayState {
+    /// Array attributes of the node and their indices.
+    arrays: Vec<(usize, Attribute)>,
+    /// Index of the array being shown in `arrays`.
+    current: usize,
+    /// Number of elements per row.
+    row_width: usize,
+    /// Index of the element jumped to.
+    target: Option<usize>,
+}
+
+impl ArrayState {
+    /// Returns the array being shown.
+    fn array(&self) -> Option<&Attribute> {
+        self.arrays.get(self.current).map(|(_, attr)| attr)
+    }
+
+    /// Returns the number of elements of the array being shown.
+    fn len(&self) -> usize {
+        self.array().and_then(Attribute::array_len).unwrap_or(0)
+    }
+
+    /// Returns the number of rows.
+    fn num_rows(&self) -> usize {
+        (self.len() + self.row_width - 1) / self.row_width
+    }
+
+    /// Draws the header and the visible rows.
+    fn draw(&mut self, cr: &cairo::Context, top_row: f64, height: f64) -> Result<(), cairo::Error> {
+        cr.set_source_rgb(1.0, 1.0, 1.0);
+        cr.paint()?;
+        let array = match self.array() {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        cr.select_font_face(
+            "monospace",
+            cairo::FontSlant::Normal,
+            cairo::FontWeight::Normal,
+        );
+        cr.set_font_size(FONT_SIZE);
+        let char_width = cr.text_extents("0")?.x_advance();
+
+        let len = self.len();
+        let first_row = top_row as usize;
+        let num_rows = ((height - MARGIN) / ROW_HEIGHT - 1.0).ceil().max(0.0) as usize;
+        let start = (first_row * self.row_width).min(len);
+        let end = ((first_row + num_rows) * self.row_width).min(len);
+        let values: Vec<String> = (start..end)
+            .map(|i| array.element_string(i).unwrap_or_default())
+            .collect();
+
+        // Columns are as wide as the longest visible value.
+        let index_chars = len.to_string().len().max("index".len()) + 2;
+        let value_chars = values.iter().map(String::len).max().unwrap_or(0).max(3) + 2;
+        let index_width = index_chars as f64 * char_width;
+        let value_width = value_chars as f64 * char_width;
+        let baseline = |row: usize| MARGIN + (row as f64 + 0.8) * ROW_HEIGHT;
+
+        // Highlight.
+        if let Some(target) = self.target.filter(|&i| start <= i && i < end) {
+            let offset = target - start;
+            let (r, g, b) = TARGET_COLOR;
+            cr.set_source_rgb(r, g, b);
+            cr.rectangle(
+                MARGIN + index_width + (offset % self.row_width) as f64 * value_width,
+                MARGIN + (offset / self.row_width + 1) as f64 * ROW_HEIGHT,
+                value_width,
+                ROW_HEIGHT,
+            );
+            cr.fill()?;
+        }
+
+        // Header.
+        cr.set_source_rgb(0.4, 0.4, 0.4);
+        cr.move_to(MARGIN, baseline(0));
+        cr.show_text("index")?;
+        for column in 0..self.row_width {
+            cr.move_to(
+                MARGIN + index_width + column as f64 * value_width,
+                baseline(0),
+            );
+            cr.show_text(&format!("+{}", column))?;
+        }
+
+        // Rows.
+        for (row_index, row) in values.chunks(self.row_width).enumerate() {
+            let y = baseline(row_index + 1);
+            cr.set_source_rgb(0.4, 0.4, 0.4);
+            cr.move_to(MARGIN, y);
+            cr.show_text(&(start + row_index * self.row_width).to_string())?;
+            cr.set_source_rgb(0.0, 0.0, 0.0);
+            for (column, value) in row.iter().enumerate() {
+                cr.move_to(MARGIN + index_width + column as f64 * value_width, y);
+                cr.show_text(value)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,18 +1,51 @@
 //! FBX attributes table.
 
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    rc::Rc,
+};
 
-use crate::fbx::Attribute;
+use crate::fbx::{Attribute, Document, ImageFormat, NodeId, SearchQuery};
 
 use glib::Type;
 use gtk::{prelude::*, ListStore, TreeView};
 
+/// Maximum number of bytes shown for binary attributes.
+const MAX_BINARY_BYTES: usize = 1024;
+/// Maximum number of elements shown for array attributes.
+///
+/// All elements are shown in the array viewer.
+const MAX_PREVIEW_ELEMENTS: usize = 16;
+
 /// FBX attributes table.
+///
+/// Values of single values and strings are editable if a callback is
+/// connected by [`connect_attribute_edited`][`Self::connect_attribute_edited`].
 #[derive(Debug, Clone)]
 pub struct FbxAttributeTable {
     store: ListStore,
     widget: TreeView,
-    attrs: Rc<RefCell<Vec<Attribute>>>,
+    highlight: Rc<RefCell<Option<SearchQuery>>>,
+    /// Node being shown.
+    node: Rc<Cell<Option<NodeId>>>,
+    /// Callbacks for attribute edits.
+    edit_handlers: EditHandlers,
+}
+
+/// Callback for attribute edits.
+type EditHandler = Box<dyn Fn(NodeId, usize, &str)>;
+
+/// Callbacks for attribute edits.
+#[derive(Clone, Default)]
+struct EditHandlers(Rc<RefCell<Vec<EditHandler>>>);
+
+impl fmt::Debug for EditHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EditHandlers")
+            .field(&format_args!("[{} handlers]", self.0.borrow().len()))
+            .finish()
+    }
 }
 
 impl FbxAttributeTable {
@@ -24,29 +57,87 @@ impl FbxAttributeTable {
     /// Clears internal store.
     pub fn clear(&self) {
         self.store.clear();
-        self.attrs.borrow_mut().clear();
+        self.node.set(None);
     }
 
-    /// Pushes the given attribute.
-    pub fn push_attribute(&self, attr: Attribute) {
-        self.attrs.borrow_mut().push(attr);
+    /// Adds a callback called when an attribute value is edited.
+    ///
+    /// The callback receives the node, the attribute index, and the text
+    /// entered, which is not validated yet.
+    pub fn connect_attribute_edited<F>(&self, f: F)
+    where
+        F: Fn(NodeId, usize, &str) + 'static,
+    {
+        self.edit_handlers.0.borrow_mut().push(Box::new(f));
     }
 
-    /// Show the attributes.
-    pub fn show_attrs(&self, attrs_index: u64, num_attrs: u64) {
+    /// Show the attributes of the node.
+    ///
+    /// Attributes matching the highlight query are highlighted.
+    /// Deferred attributes of lazily loaded documents are decoded here.
+    pub fn show_attrs(&self, document: &Document, node_id: NodeId) {
         self.store.clear();
-        for (local_index, attr) in self.attrs.borrow()
-            [attrs_index as usize..(attrs_index + num_attrs) as usize]
-            .iter()
-            .enumerate()
-        {
-            self.append_store(local_index as u64, attr.type_string(), &attr.value_string());
+        self.node.set(Some(node_id));
+        let node = document.node(node_id);
+        let attrs = match document.load_attributes(node_id) {
+            Ok(v) => v,
+            Err(err) => {
+                println!("Failed to load attributes: {}", err);
+                return;
+            }
+        };
+        let highlight = self.highlight.borrow();
+        let editable = !self.edit_handlers.0.borrow().is_empty();
+        for (local_index, attr) in attrs.iter().enumerate() {
+            let highlighted = highlight
+                .as_ref()
+                .map_or(false, |query| query.matches_attribute(attr));
+            let iter = self.append_store(
+                local_index as u64,
+                attr.type_string(),
+                &value_string(attr),
+                highlighted,
+                attr.edit_text().filter(|_| editable),
+            );
+            if let Some(storage) = node.array_storage(local_index) {
+                self.store.set(
+                    &iter,
+                    &[
+                        (4, &storage.encoding_name()),
+                        (5, &storage.on_disk_len().to_string()),
+                        (6, &storage.num_elements().to_string()),
+                    ],
+                );
+            }
         }
     }
 
-    fn append_store(&self, index: u64, typename: &str, value: &str) -> gtk::TreeIter {
-        self.store
-            .insert_with_values(None, &[(0, &index), (1, &typename), (2, &value)])
+    /// Sets the query to highlight matching attributes.
+    ///
+    /// This takes effect at the next [`show_attrs`][`Self::show_attrs`].
+    pub fn set_highlight(&self, query: Option<SearchQuery>) {
+        *self.highlight.borrow_mut() = query;
+    }
+
+    fn append_store(
+        &self,
+        index: u64,
+        typename: &str,
+        value: &str,
+        highlighted: bool,
+        edit_text: Option<String>,
+    ) -> gtk::TreeIter {
+        self.store.insert_with_values(
+            None,
+            &[
+                (0, &index),
+                (1, &typename),
+                (2, &value),
+                (3, &highlighted),
+                (7, &edit_text.is_some()),
+                (8, &edit_text.unwrap_or_default()),
+            ],
+        )
     }
 
     /// Returns a reference to the `TreeView`.
@@ -59,17 +150,31 @@ impl Default for FbxAttributeTable {
     fn default() -> Self {
         use gtk::{CellRendererText, TreeViewColumn};
 
-        // index, type, value.
-        let column_types = &[Type::U64, Type::STRING, Type::STRING];
+        // index, type, value, highlighted, encoding, on-disk length, # of
+        // elements, editable, edit text.
+        let column_types = &[
+            Type::U64,
+            Type::STRING,
+            Type::STRING,
+            Type::BOOL,
+            Type::STRING,
+            Type::STRING,
+            Type::STRING,
+            Type::BOOL,
+            Type::STRING,
+        ];
         let store = ListStore::new(column_types);
         let widget = TreeView::with_model(&store);
         widget.set_grid_lines(gtk::TreeViewGridLines::Vertical);
         widget.set_enable_tree_lines(true);
         widget.set_headers_visible(true);
+        let node = Rc::new(Cell::new(None));
+        let edit_handlers = EditHandlers::default();
         {
             let column = TreeViewColumn::new();
             let cell = CellRendererText::new();
             TreeViewColumnExt::pack_start(&column, &cell, true);
+            set_highlight_style(&column, &cell);
             column.set_title("#");
             TreeViewColumnExt::add_attribute(&column, &cell, "text", 0);
             column.set_clickable(true);
@@ -80,6 +185,7 @@ impl Default for FbxAttributeTable {
             let column = TreeViewColumn::new();
             let cell = CellRendererText::new();
             TreeViewColumnExt::pack_start(&column, &cell, true);
+            set_highlight_style(&column, &cell);
             column.set_title("type");
             TreeViewColumnExt::add_attribute(&column, &cell, "text", 1);
             column.set_clickable(true);
@@ -91,8 +197,59 @@ impl Default for FbxAttributeTable {
             let column = TreeViewColumn::new();
             let cell = CellRendererText::new();
             TreeViewColumnExt::pack_start(&column, &cell, true);
+            set_highlight_style(&column, &cell);
             column.set_title("value");
             TreeViewColumnExt::add_attribute(&column, &cell, "text", 2);
+            TreeViewColumnExt::add_attribute(&column, &cell, "editable", 7);
+            column.set_resizable(true);
+            widget.append_column(&column);
+
+            // The shown value is not always parsable (such as strings with
+            // backslashes), so the editor starts with the edit text.
+            {
+                let store = store.clone();
+                cell.connect_editing_started(move |_, editable, path| {
+                    let entry = match editable.downcast_ref::<gtk::Entry>() {
+                        Some(v) => v,
+                        None => return,
+                    };
+                    if let Some(iter) = store.iter(&path) {
+                        let text = store
+                            .value(&iter, 8)
+                            .get::<String>()
+                            .expect("column[8] of `FbxAttributeTable` is not string");
+                        entry.set_text(&text);
+                    }
+                });
+            }
+            let store = store.clone();
+            let node = node.clone();
+            let edit_handlers = edit_handlers.clone();
+            cell.connect_edited(move |_, path, text| {
+                let node_id = match node.get() {
+                    Some(v) => v,
+                    None => return,
+                };
+                let index = match store.iter(&path) {
+                    Some(iter) => store
+                        .value(&iter, 0)
+                        .get::<u64>()
+                        .expect("column[0] of `FbxAttributeTable` is not u64"),
+                    None => return,
+                };
+                for handler in edit_handlers.0.borrow().iter() {
+                    handler(node_id, index as usize, text);
+                }
+            });
+        }
+        for &(title, column_index) in &[("encoding", 4), ("on-disk bytes", 5), ("# of elements", 6)]
+        {
+            let column = TreeViewColumn::new();
+            let cell = CellRendererText::new();
+            TreeViewColumnExt::pack_start(&column, &cell, true);
+            set_highlight_style(&column, &cell);
+            column.set_title(title);
+            TreeViewColumnExt::add_attribute(&column, &cell, "text", column_index);
             column.set_resizable(true);
             widget.append_column(&column);
         }
@@ -100,7 +257,43 @@ impl Default for FbxAttributeTable {
         Self {
             store,
             widget,
-            attrs: Rc::new(RefCell::new(Vec::new())),
+            highlight: Rc::new(RefCell::new(None)),
+            node,
+            edit_handlers,
         }
     }
 }
+
+/// Returns string representation of the attribute value.
+///
+/// Arrays are shown as previews, since formatting huge arrays hangs the cell
+/// renderer.
+/// Large binaries are truncated, and recognized embedded images are noted.
+fn value_string(attr: &Attribute) -> String {
+    let bytes = match attr {
+        Attribute::Binary(bytes) => bytes,
+        _ if attr.array_len().is_some() => {
+            return attr.value_string_oneline(Some(MAX_PREVIEW_ELEMENTS))
+        }
+        _ => return attr.value_string(),
+    };
+    let mut s = match ImageFormat::sniff(bytes) {
+        Some(format) => format!("({} image, {} bytes)\n", format.name(), bytes.len()),
+        None => String::new(),
+    };
+    if bytes.len() > MAX_BINARY_BYTES {
+        s.push_str(&Attribute::Binary(bytes[..MAX_BINARY_BYTES].to_vec()).value_string());
+        s.push_str(&format!("... ({} bytes)", bytes.len()));
+    } else {
+        s.push_str(&attr.value_string());
+    }
+    s
+}
+
+/// Makes the cell highlighted if the "highlighted" column is true.
+fn set_highlight_style(column: &gtk::TreeViewColumn, cell: &gtk::CellRendererText) {
+    cell.set_background(Some("#fce94f"));
+    cell.set_foreground(Some("#000000"));
+    TreeViewColumnExt::add_attribute(column, cell, "background-set", 3);
+    TreeViewColumnExt::add_attribute(column, cell, "foreground-set", 3);
+}
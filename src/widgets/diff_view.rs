@@ -0,0 +1,323 @@
+//! Structural diff viewer.
+
+use std::{cell::RefCell, path::Path, rc::Rc};
+
+use glib::Type;
+use gtk::{
+    prelude::*, CheckButton, ComboBoxText, Label, Orientation, Paned, ScrolledWindow, SpinButton,
+    TreeStore, TreeView,
+};
+
+use crate::{
+    fbx::{
+        diff_documents, DiffKind, DiffOptions, Document, DocumentDiff, NodeDiff, NodeId, ObjectKey,
+    },
+    widgets::{append_text_column, FbxAttributeTable, FbxNodeTree},
+};
+
+/// Value of the node ID columns for rows without the node.
+const NO_NODE: u64 = u64::MAX;
+
+/// Maximum float tolerance configurable.
+const MAX_FLOAT_TOLERANCE: f64 = 1.0e6;
+
+/// Step of the float tolerance.
+const FLOAT_TOLERANCE_STEP: f64 = 1.0e-6;
+
+/// Structural diff viewer.
+///
+/// This compares the document of the node tree (as old) with another file
+/// (as new), and shows the merged tree of the differences next to the node
+/// tree of the other file.
+/// Selecting a row selects the corresponding nodes in both node trees.
+#[derive(Debug, Clone)]
+pub struct FbxDiffView {
+    widget: gtk::Box,
+    status: Label,
+    float_tolerance: SpinButton,
+    object_key: ComboBoxText,
+    ignore_numeric_types: CheckButton,
+    only_differences: CheckButton,
+    store: TreeStore,
+    diff_tree: TreeView,
+    other_tree: FbxNodeTree,
+    other_attrs: FbxAttributeTable,
+    diff: Rc<RefCell<Option<DocumentDiff>>>,
+}
+
+impl FbxDiffView {
+    /// Creates a new diff viewer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect events.
+    ///
+    /// `node_tree` is the node tree of the old document.
+    pub fn initialize(&self, node_tree: &FbxNodeTree) {
+        self.other_tree.initialize(&self.other_attrs);
+        {
+            let node_tree = node_tree.clone();
+            let other_tree = self.other_tree.clone();
+            self.diff_tree
+                .selection()
+                .connect_changed(move |selection| {
+                    if let Some((model, tree_iter)) = selection.selected() {
+                        if let Some(node_id) = row_node_id(&model, &tree_iter, 3) {
+                            node_tree.select_node(node_id);
+                        }
+                        if let Some(node_id) = row_node_id(&model, &tree_iter, 4) {
+                            other_tree.select_node(node_id);
+                        }
+                    }
+                });
+        }
+        {
+            let view = self.clone();
+            let document = node_tree.document().clone();
+            self.float_tolerance
+                .connect_value_changed(move |_| view.refresh(&document.borrow()));
+        }
+        {
+            let view = self.clone();
+            let document = node_tree.document().clone();
+            self.object_key
+                .connect_changed(move |_| view.refresh(&document.borrow()));
+        }
+        {
+            let view = self.clone();
+            let document = node_tree.document().clone();
+            self.ignore_numeric_types
+                .connect_toggled(move |_| view.refresh(&document.borrow()));
+        }
+        {
+            let view = self.clone();
+            self.only_differences
+                .connect_toggled(move |_| view.update_store());
+        }
+    }
+
+    /// Clears the comparison.
+    pub fn clear(&self) {
+        self.store.clear();
+        self.other_tree.clear();
+        self.other_attrs.clear();
+        *self.diff.borrow_mut() = None;
+        self.status.set_text("No file to compare");
+    }
+
+    /// Sets the document of the other file, and compares `document` with it.
+    pub fn set_other_document(&self, document: &Document, other_path: &Path, other: Document) {
+        self.other_attrs.clear();
+        self.other_tree.set_document(other);
+        self.other_tree
+            .widget()
+            .set_tooltip_text(Some(&other_path.display().to_string()));
+        self.refresh(document);
+    }
+
+    /// Compares the given document with the other file again.
+    ///
+    /// This should be called when the document of the node tree changes.
+    pub fn refresh(&self, document: &Document) {
+        let other = self.other_tree.document().borrow();
+        if other.is_empty() {
+            return;
+        }
+        let options = DiffOptions {
+            float_tolerance: self.float_tolerance.value(),
+            object_key: match self.object_key.active_id().as_deref() {
+                Some("name") => ObjectKey::Name,
+                _ => ObjectKey::Id,
+            },
+            ignore_numeric_types: self.ignore_numeric_types.is_active(),
+        };
+        let diff = match diff_documents(document, &other, &options) {
+            Ok(v) => Some(v),
+            Err(err) => {
+                println!("Failed to compare the documents: {}", err);
+                None
+            }
+        };
+        *self.diff.borrow_mut() = diff;
+        drop(other);
+        self.update_store();
+    }
+
+    /// Rebuilds the diff tree for the current result.
+    fn update_store(&self) {
+        self.store.clear();
+        let diff = self.diff.borrow();
+        let diff = match &*diff {
+            Some(v) => v,
+            None => {
+                self.status.set_text("Failed to compare");
+                return;
+            }
+        };
+        let (old_version, new_version) = diff.versions();
+        let status = if !diff.has_differences() {
+            "No differences".to_owned()
+        } else if old_version != new_version {
+            format!(
+                "FBX version differs: {}.{} -> {}.{}",
+                old_version.major(),
+                old_version.minor(),
+                new_version.major(),
+                new_version.minor()
+            )
+        } else {
+            "Differences found".to_owned()
+        };
+        self.status.set_text(&status);
+
+        // Detach the model during bulk insertion, for efficiency.
+        self.diff_tree.set_model(None::<&TreeStore>);
+        let only_differences = self.only_differences.is_active();
+        for node in diff.nodes() {
+            self.append(None, node, only_differences);
+        }
+        self.diff_tree.set_model(Some(&self.store));
+        if only_differences {
+            self.diff_tree.expand_all();
+        }
+    }
+
+    /// Appends the given node and its descendants.
+    fn append(&self, parent: Option<&gtk::TreeIter>, node: &NodeDiff, only_differences: bool) {
+        if only_differences && !node.has_differences() {
+            return;
+        }
+        let kind = node.kind();
+        let color = match kind {
+            DiffKind::Same => None,
+            DiffKind::Changed => Some("#c06000"),
+            DiffKind::Added => Some("#008000"),
+            DiffKind::Removed => Some("#cc0000"),
+        };
+        let changes = node
+            .attributes()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ");
+        let tree_iter = self.store.insert_with_values(
+            parent,
+            None,
+            &[
+                (0, &kind.marker().to_string()),
+                (1, &node.label()),
+                (2, &changes),
+                (3, &node.old_node().map_or(NO_NODE, |id| id.index() as u64)),
+                (4, &node.new_node().map_or(NO_NODE, |id| id.index() as u64)),
+                (5, &color.unwrap_or_default()),
+                (6, &color.is_some()),
+            ],
+        );
+        for child in node.children() {
+            self.append(Some(&tree_iter), child, only_differences);
+        }
+    }
+
+    /// Returns a reference to the root widget.
+    pub fn widget(&self) -> &gtk::Box {
+        &self.widget
+    }
+}
+
+impl Default for FbxDiffView {
+    fn default() -> Self {
+        let status = Label::new(Some("No file to compare"));
+        status.set_xalign(0.0);
+        let float_tolerance =
+            SpinButton::with_range(0.0, MAX_FLOAT_TOLERANCE, FLOAT_TOLERANCE_STEP);
+        let object_key = ComboBoxText::new();
+        object_key.append(Some("id"), "ID");
+        object_key.append(Some("name"), "name");
+        object_key.set_active_id(Some("id"));
+        let ignore_numeric_types = CheckButton::with_label("ignore numeric types");
+        let only_differences = CheckButton::with_label("only differences");
+        only_differences.set_active(true);
+
+        let controls = gtk::Box::new(Orientation::Horizontal, 4);
+        controls.pack_start(&Label::new(Some("float tolerance:")), false, false, 0);
+        controls.pack_start(&float_tolerance, false, false, 0);
+        controls.pack_start(&Label::new(Some("match objects by:")), false, false, 0);
+        controls.pack_start(&object_key, false, false, 0);
+        controls.pack_start(&ignore_numeric_types, false, false, 0);
+        controls.pack_start(&only_differences, false, false, 0);
+        controls.pack_start(&status, true, true, 0);
+
+        // marker, label, changes, old node ID, new node ID, color, whether
+        // the color is set.
+        let store = TreeStore::new(&[
+            Type::STRING,
+            Type::STRING,
+            Type::STRING,
+            Type::U64,
+            Type::U64,
+            Type::STRING,
+            Type::BOOL,
+        ]);
+        let diff_tree = TreeView::with_model(&store);
+        diff_tree.set_enable_tree_lines(true);
+        for &(title, column_index) in &[("", 0), ("node", 1), ("changes", 2)] {
+            append_text_column(&diff_tree, title, column_index, None);
+            let column = diff_tree
+                .column(column_index)
+                .expect("the column has just been appended");
+            for cell in column.cells() {
+                TreeViewColumnExt::add_attribute(&column, &cell, "foreground", 5);
+                TreeViewColumnExt::add_attribute(&column, &cell, "foreground-set", 6);
+            }
+        }
+        let scrolled_diff_tree = ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
+        scrolled_diff_tree.add(&diff_tree);
+
+        let other_tree = FbxNodeTree::new();
+        let scrolled_other_tree = ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
+        scrolled_other_tree.add(other_tree.widget());
+        let other_attrs = FbxAttributeTable::new();
+        let scrolled_other_attrs =
+            ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
+        scrolled_other_attrs.add(other_attrs.widget());
+        let other_pane = Paned::new(Orientation::Vertical);
+        other_pane.pack1(&scrolled_other_tree, true, true);
+        other_pane.pack2(&scrolled_other_attrs, true, true);
+
+        let pane = Paned::new(Orientation::Horizontal);
+        pane.pack1(&scrolled_diff_tree, true, true);
+        pane.pack2(&other_pane, true, true);
+
+        let widget = gtk::Box::new(Orientation::Vertical, 4);
+        widget.pack_start(&controls, false, false, 0);
+        widget.pack_start(&pane, true, true, 0);
+
+        Self {
+            widget,
+            status,
+            float_tolerance,
+            object_key,
+            ignore_numeric_types,
+            only_differences,
+            store,
+            diff_tree,
+            other_tree,
+            other_attrs,
+            diff: Rc::new(RefCell::new(None)),
+        }
+    }
+}
+
+/// Returns the node ID in the given column of the row, if available.
+fn row_node_id(model: &gtk::TreeModel, tree_iter: &gtk::TreeIter, column: i32) -> Option<NodeId> {
+    let node_id = model
+        .value(tree_iter, column)
+        .get::<u64>()
+        .expect("node ID column of `FbxDiffView` is not u64");
+    if node_id == NO_NODE {
+        None
+    } else {
+        Some(NodeId::new(node_id as usize))
+    }
+}
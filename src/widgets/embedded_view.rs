@@ -0,0 +1,191 @@
+//! Embedded file preview widget.
+
+use std::{cell::RefCell, rc::Rc};
+
+use gtk::{
+    gdk_pixbuf::{InterpType, Pixbuf, PixbufLoader},
+    prelude::*,
+    Button, FileChooserAction, FileChooserDialog, Image, Label, Orientation, Window,
+};
+
+use crate::fbx::{embedded_file_name, Attribute, Document, ImageFormat, NodeId};
+
+/// Maximum width and height of the thumbnail.
+const THUMBNAIL_SIZE: i32 = 256;
+
+/// Embedded file preview widget.
+///
+/// This shows a thumbnail of an image embedded in a binary attribute (such
+/// as `Video/Content`), and allows saving the raw bytes to a file.
+#[derive(Debug, Clone)]
+pub struct FbxEmbeddedView {
+    widget: gtk::Box,
+    summary: Label,
+    image: Image,
+    content: Rc<RefCell<Option<EmbeddedContent>>>,
+}
+
+/// Embedded file being shown.
+#[derive(Debug, Clone)]
+struct EmbeddedContent {
+    /// Raw bytes.
+    bytes: Vec<u8>,
+    /// Suggested file name.
+    file_name: String,
+}
+
+impl FbxEmbeddedView {
+    /// Creates a new embedded file preview.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the preview.
+    pub fn clear(&self) {
+        self.summary.set_text("");
+        self.image.set_from_pixbuf(None);
+        *self.content.borrow_mut() = None;
+    }
+
+    /// Shows the embedded file of the given node.
+    ///
+    /// Returns `false` and clears the preview if the node has no non-empty
+    /// binary attribute.
+    pub fn show_node(&self, document: &Document, node_id: NodeId) -> bool {
+        self.clear();
+        let attrs = match document.load_attributes(node_id) {
+            Ok(v) => v,
+            Err(err) => {
+                println!("Failed to load attributes: {}", err);
+                return false;
+            }
+        };
+        let bytes = attrs.iter().find_map(|attr| match attr {
+            Attribute::Binary(bytes) if !bytes.is_empty() => Some(bytes),
+            _ => None,
+        });
+        let bytes = match bytes {
+            Some(v) => v,
+            None => return false,
+        };
+
+        let format = ImageFormat::sniff(bytes);
+        let file_name = embedded_file_name(document, node_id).unwrap_or_else(|| {
+            format!("embedded.{}", format.map_or("bin", ImageFormat::extension))
+        });
+        let mut summary = format!(
+            "{}, {} bytes, file name: {}",
+            format.map_or("unknown format", ImageFormat::name),
+            bytes.len(),
+            file_name
+        );
+        if format.is_some() {
+            match load_image(bytes) {
+                Ok(pixbuf) => {
+                    summary.push_str(&format!(" ({}x{})", pixbuf.width(), pixbuf.height()));
+                    self.image.set_from_pixbuf(thumbnail(&pixbuf).as_ref());
+                }
+                Err(e) => summary.push_str(&format!("\nPreview is not available: {}", e)),
+            }
+        }
+        self.summary.set_text(&summary);
+        *self.content.borrow_mut() = Some(EmbeddedContent {
+            bytes: bytes.clone(),
+            file_name,
+        });
+
+        true
+    }
+
+    /// Returns a reference to the root widget.
+    pub fn widget(&self) -> &gtk::Box {
+        &self.widget
+    }
+}
+
+impl Default for FbxEmbeddedView {
+    fn default() -> Self {
+        let summary = Label::new(None);
+        summary.set_xalign(0.0);
+        summary.set_selectable(true);
+        summary.set_line_wrap(true);
+
+        let image = Image::new();
+        image.set_halign(gtk::Align::Start);
+        image.set_valign(gtk::Align::Start);
+
+        let content = Rc::new(RefCell::new(None::<EmbeddedContent>));
+        let save_button = Button::with_label("Save embedded file as...");
+        save_button.set_halign(gtk::Align::Start);
+        {
+            let content = content.clone();
+            save_button.connect_clicked(move |button| {
+                let window = button
+                    .toplevel()
+                    .and_then(|toplevel| toplevel.downcast::<Window>().ok());
+                if let Some(content) = &*content.borrow() {
+                    save_embedded_file(window.as_ref(), &content.bytes, &content.file_name);
+                }
+            });
+        }
+
+        let widget = gtk::Box::new(Orientation::Vertical, 4);
+        widget.pack_start(&summary, false, false, 0);
+        widget.pack_start(&save_button, false, false, 0);
+        widget.pack_start(&image, true, true, 0);
+
+        Self {
+            widget,
+            summary,
+            image,
+            content,
+        }
+    }
+}
+
+/// Decodes the image.
+fn load_image(bytes: &[u8]) -> Result<Pixbuf, glib::Error> {
+    let loader = PixbufLoader::new();
+    let written = loader.write(bytes);
+    // The loader should be closed even on failure.
+    let closed = loader.close();
+    written.and(closed)?;
+    loader.pixbuf().ok_or_else(|| {
+        glib::Error::new(
+            gtk::gdk_pixbuf::PixbufError::CorruptImage,
+            "No image is decoded",
+        )
+    })
+}
+
+/// Creates a thumbnail of the image.
+fn thumbnail(pixbuf: &Pixbuf) -> Option<Pixbuf> {
+    let (width, height) = (pixbuf.width(), pixbuf.height());
+    let scale = (f64::from(THUMBNAIL_SIZE) / f64::from(width.max(height))).min(1.0);
+    pixbuf.scale_simple(
+        ((f64::from(width) * scale) as i32).max(1),
+        ((f64::from(height) * scale) as i32).max(1),
+        InterpType::Bilinear,
+    )
+}
+
+/// Asks the destination and saves the embedded file.
+fn save_embedded_file(window: Option<&Window>, bytes: &[u8], file_name: &str) {
+    let file_chooser =
+        FileChooserDialog::new(Some("Save embedded file"), window, FileChooserAction::Save);
+    file_chooser.set_do_overwrite_confirmation(true);
+    file_chooser.set_current_name(file_name);
+    file_chooser.add_buttons(&[
+        ("Cancel", gtk::ResponseType::Cancel),
+        ("Save", gtk::ResponseType::Ok),
+    ]);
+    if file_chooser.run() == gtk::ResponseType::Ok {
+        if let Some(path) = file_chooser.filename() {
+            match std::fs::write(&path, bytes) {
+                Ok(()) => println!("Saved embedded file to {}", path.display()),
+                Err(e) => println!("Failed to save embedded file to {}: {}", path.display(), e),
+            }
+        }
+    }
+    file_chooser.close();
+}
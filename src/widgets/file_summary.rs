@@ -0,0 +1,71 @@
+//! File summary widget.
+
+use gtk::{prelude::*, Label};
+
+use crate::fbx::{ArrayStatistics, Document};
+
+/// File summary widget.
+///
+/// This shows the FBX version, the number of nodes, and the compression
+/// statistics of array attributes.
+#[derive(Debug, Clone)]
+pub struct FbxFileSummary {
+    widget: Label,
+}
+
+impl FbxFileSummary {
+    /// Creates a new file summary.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the summary.
+    pub fn clear(&self) {
+        self.widget.set_text("");
+    }
+
+    /// Shows the summary of the given document.
+    pub fn set_document(&self, document: &Document) {
+        let version = document.fbx_version();
+        let mut summary = format!(
+            "FBX {}.{}, {} nodes",
+            version.major(),
+            version.minor(),
+            document.len()
+        );
+        let stats = ArrayStatistics::new(document);
+        summary.push_str(&format!(
+            "\narrays: {} ({} compressed), {} bytes on disk, {} bytes uncompressed",
+            stats.num_arrays(),
+            stats.num_compressed(),
+            stats.on_disk_len(),
+            stats.decoded_len()
+        ));
+        if let Some(ratio) = stats.compression_ratio() {
+            summary.push_str(&format!(
+                "\ncompression ratio of arrays: {:.1}%",
+                ratio * 100.0
+            ));
+        }
+        if document.is_lazy() {
+            summary.push_str("\narrays and binaries are decoded on demand");
+        }
+        self.widget.set_text(&summary);
+    }
+
+    /// Returns a reference to the `Label`.
+    pub fn widget(&self) -> &Label {
+        &self.widget
+    }
+}
+
+impl Default for FbxFileSummary {
+    fn default() -> Self {
+        let widget = Label::new(None);
+        widget.set_xalign(0.0);
+        widget.set_yalign(0.0);
+        widget.set_selectable(true);
+
+        Self { widget }
+    }
+}
@@ -0,0 +1,379 @@
+//! Hex view of the raw file.
+
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+    rc::Rc,
+};
+
+use gtk::{cairo, prelude::*, Adjustment, DrawingArea, Label, Orientation, Scrollbar};
+
+use crate::{
+    fbx::{Document, NodeId},
+    widgets::FbxNodeTree,
+};
+
+/// Number of bytes per line.
+const BYTES_PER_LINE: u64 = 16;
+/// Height of a line in pixels.
+const LINE_HEIGHT: f64 = 16.0;
+/// Font size in pixels.
+const FONT_SIZE: f64 = 12.0;
+/// Margin around the text in pixels.
+const MARGIN: f64 = 4.0;
+/// Number of characters of the offset column, including the separator.
+const OFFSET_COLUMN_CHARS: f64 = 10.0;
+/// Number of lines scrolled per mouse wheel step.
+const LINES_PER_SCROLL: f64 = 3.0;
+
+/// Background color of the node record header.
+const HEADER_COLOR: (f64, f64, f64) = (0.81, 0.89, 0.95);
+/// Background color of the attributes.
+const ATTRIBUTES_COLOR: (f64, f64, f64) = (1.0, 0.95, 0.66);
+/// Background color of the child nodes.
+const CHILDREN_COLOR: (f64, f64, f64) = (0.85, 0.92, 0.83);
+
+/// Hex view of the raw file.
+///
+/// This highlights the byte regions of the node selected in the node tree,
+/// and clicking a byte selects the innermost node containing it.
+/// Only the visible part of the file is read.
+#[derive(Debug, Clone)]
+pub struct FbxHexView {
+    widget: gtk::Box,
+    area: DrawingArea,
+    adjustment: Adjustment,
+    state: Rc<RefCell<HexState>>,
+}
+
+impl FbxHexView {
+    /// Creates a new hex view.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Initializes the hex view.
+    ///
+    /// This connects the hex view to the node tree.
+    pub fn initialize(&self, node_tree: &FbxNodeTree) {
+        {
+            let hex = self.clone();
+            node_tree
+                .connect_node_selected(move |document, node_id| hex.show_node(document, node_id));
+        }
+        {
+            let state = self.state.clone();
+            let adjustment = self.adjustment.clone();
+            let node_tree = node_tree.clone();
+            self.area.connect_button_press_event(move |_, event| {
+                let (x, y) = event.position();
+                let offset = state.borrow().offset_at(adjustment.value(), x, y);
+                // The document should not be borrowed while selecting, since
+                // selection handlers borrow it.
+                let node_id =
+                    offset.and_then(|offset| node_tree.document().borrow().node_at_offset(offset));
+                if let Some(node_id) = node_id {
+                    node_tree.select_node(node_id);
+                }
+                glib::Propagation::Stop
+            });
+        }
+    }
+
+    /// Clears the hex view and closes the file.
+    pub fn clear(&self) {
+        *self.state.borrow_mut() = HexState::default();
+        self.adjustment.set_upper(0.0);
+        self.adjustment.set_value(0.0);
+        self.area.queue_draw();
+    }
+
+    /// Opens the file to be shown.
+    pub fn set_file<P: AsRef<Path>>(&self, path: P) {
+        self.clear();
+        let opened = File::open(path).and_then(|file| Ok((file.metadata()?.len(), file)));
+        match opened {
+            Ok((file_len, file)) => {
+                let mut state = self.state.borrow_mut();
+                state.file = Some(file);
+                state.file_len = file_len;
+                self.adjustment
+                    .set_upper(((file_len + BYTES_PER_LINE - 1) / BYTES_PER_LINE) as f64);
+            }
+            Err(e) => println!("Failed to open the file for the hex view: {}", e),
+        }
+        self.area.queue_draw();
+    }
+
+    /// Highlights the regions of the given node, and scrolls to the node if
+    /// it is not visible.
+    pub fn show_node(&self, document: &Document, node_id: Option<NodeId>) {
+        let regions = match node_id {
+            Some(node_id) => node_regions(document, node_id),
+            None => Vec::new(),
+        };
+        if let (Some(&(start, _, _)), Some(&(_, end, _))) = (regions.first(), regions.last()) {
+            let top = self.adjustment.value();
+            let bottom = top + self.adjustment.page_size();
+            let start_line = (start / BYTES_PER_LINE) as f64;
+            let end_line = (end.saturating_sub(1) / BYTES_PER_LINE) as f64;
+            if end_line < top || start_line >= bottom {
+                self.adjustment.set_value(start_line);
+            }
+        }
+        self.state.borrow_mut().regions = regions;
+        self.area.queue_draw();
+    }
+
+    /// Returns a reference to the root widget.
+    pub fn widget(&self) -> &gtk::Box {
+        &self.widget
+    }
+}
+
+impl Default for FbxHexView {
+    fn default() -> Self {
+        let state = Rc::new(RefCell::new(HexState::default()));
+        let adjustment = Adjustment::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0);
+
+        let area = DrawingArea::new();
+        area.add_events(gdk::EventMask::BUTTON_PRESS_MASK | gdk::EventMask::SCROLL_MASK);
+        {
+            let state = state.clone();
+            let adjustment = adjustment.clone();
+            area.connect_draw(move |area, cr| {
+                let height = f64::from(area.allocated_height());
+                if let Err(e) = state.borrow_mut().draw(cr, adjustment.value(), height) {
+                    println!("Failed to draw the hex view: {}", e);
+                }
+                glib::Propagation::Stop
+            });
+        }
+        {
+            let adjustment = adjustment.clone();
+            area.connect_size_allocate(move |_, allocation| {
+                let lines = ((f64::from(allocation.height()) - MARGIN) / LINE_HEIGHT).floor();
+                adjustment.set_page_size(lines.max(1.0));
+                adjustment.set_page_increment(lines.max(1.0));
+            });
+        }
+        {
+            let adjustment = adjustment.clone();
+            area.connect_scroll_event(move |_, event| {
+                let steps = match event.direction() {
+                    gdk::ScrollDirection::Up => -1.0,
+                    gdk::ScrollDirection::Down => 1.0,
+                    _ => 0.0,
+                };
+                adjustment.set_value(adjustment.value() + steps * LINES_PER_SCROLL);
+                glib::Propagation::Stop
+            });
+        }
+        {
+            let area = area.clone();
+            adjustment.connect_value_changed(move |_| area.queue_draw());
+        }
+
+        let scrollbar = Scrollbar::new(Orientation::Vertical, Some(&adjustment));
+        let view_box = gtk::Box::new(Orientation::Horizontal, 0);
+        view_box.pack_start(&area, true, true, 0);
+        view_box.pack_start(&scrollbar, false, false, 0);
+
+        let legend = Label::new(None);
+        legend.set_xalign(0.0);
+        let legend_color = |(r, g, b): (f64, f64, f64)| {
+            format!(
+                "#{:02x}{:02x}{:02x}",
+                (r * 255.0) as u8,
+                (g * 255.0) as u8,
+                (b * 255.0) as u8
+            )
+        };
+        legend.set_markup(&format!(
+            "<span background=\"{}\"> node header </span> \
+             <span background=\"{}\"> attributes </span> \
+             <span background=\"{}\"> child nodes </span>",
+            legend_color(HEADER_COLOR),
+            legend_color(ATTRIBUTES_COLOR),
+            legend_color(CHILDREN_COLOR)
+        ));
+
+        let widget = gtk::Box::new(Orientation::Vertical, 4);
+        widget.pack_start(&legend, false, false, 0);
+        widget.pack_start(&view_box, true, true, 0);
+
+        Self {
+            widget,
+            area,
+            adjustment,
+            state,
+        }
+    }
+}
+
+/// Returns the highlighted regions of the node: beginning and end offsets,
+/// and colors.
+fn node_regions(document: &Document, node_id: NodeId) -> Vec<(u64, u64, (f64, f64, f64))> {
+    let node = document.node(node_id);
+    if !node.has_offsets() {
+        // The node is not in the file.
+        return Vec::new();
+    }
+    match node.attribute_offsets() {
+        Some((attrs_start, attrs_end)) => vec![
+            (node.start_offset(), attrs_start, HEADER_COLOR),
+            (attrs_start, attrs_end, ATTRIBUTES_COLOR),
+            (attrs_end, node.end_offset(), CHILDREN_COLOR),
+        ],
+        // Regions are unknown for FBX ASCII.
+        None => vec![(node.start_offset(), node.end_offset(), ATTRIBUTES_COLOR)],
+    }
+}
+
+/// State of the hex view.
+#[derive(Debug, Default)]
+struct HexState {
+    /// File being shown.
+    file: Option<File>,
+    /// File size.
+    file_len: u64,
+    /// Highlighted regions: beginning and end offsets, and colors.
+    regions: Vec<(u64, u64, (f64, f64, f64))>,
+    /// Width of a character, measured at the last drawing.
+    char_width: f64,
+}
+
+impl HexState {
+    /// Returns the x coordinates of the hex and ASCII columns.
+    fn column_positions(&self) -> (f64, f64) {
+        let hex_x = MARGIN + OFFSET_COLUMN_CHARS * self.char_width;
+        let ascii_x = hex_x + (BYTES_PER_LINE as f64 * 3.0 + 1.0) * self.char_width;
+        (hex_x, ascii_x)
+    }
+
+    /// Returns the byte offset at the given position.
+    fn offset_at(&self, top_line: f64, x: f64, y: f64) -> Option<u64> {
+        if self.file.is_none() || self.char_width <= 0.0 || y < MARGIN {
+            return None;
+        }
+        let (hex_x, ascii_x) = self.column_positions();
+        let column = if x >= ascii_x {
+            (x - ascii_x) / self.char_width
+        } else if x >= hex_x {
+            (x - hex_x) / (self.char_width * 3.0)
+        } else {
+            return None;
+        };
+        if column >= BYTES_PER_LINE as f64 {
+            return None;
+        }
+        let line = top_line as u64 + ((y - MARGIN) / LINE_HEIGHT) as u64;
+        let offset = line * BYTES_PER_LINE + column as u64;
+        if offset < self.file_len {
+            Some(offset)
+        } else {
+            None
+        }
+    }
+
+    /// Reads the bytes of the given range from the file.
+    fn read(&mut self, start: u64, len: u64) -> io::Result<Vec<u8>> {
+        let file = match &mut self.file {
+            Some(v) => v,
+            None => return Ok(Vec::new()),
+        };
+        file.seek(SeekFrom::Start(start))?;
+        let mut buf = Vec::with_capacity(len as usize);
+        file.take(len).read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Draws the visible lines.
+    fn draw(
+        &mut self,
+        cr: &cairo::Context,
+        top_line: f64,
+        height: f64,
+    ) -> Result<(), cairo::Error> {
+        cr.set_source_rgb(1.0, 1.0, 1.0);
+        cr.paint()?;
+        cr.select_font_face(
+            "monospace",
+            cairo::FontSlant::Normal,
+            cairo::FontWeight::Normal,
+        );
+        cr.set_font_size(FONT_SIZE);
+        self.char_width = cr.text_extents("0")?.x_advance();
+
+        let first_line = top_line as u64;
+        let num_lines = ((height - MARGIN) / LINE_HEIGHT).ceil().max(0.0) as u64;
+        let start = first_line * BYTES_PER_LINE;
+        let bytes = match self.read(start, num_lines * BYTES_PER_LINE) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("Failed to read the file for the hex view: {}", e);
+                return Ok(());
+            }
+        };
+        let (hex_x, ascii_x) = self.column_positions();
+
+        // Highlights.
+        for (i, _) in bytes.iter().enumerate() {
+            let offset = start + i as u64;
+            let color = self
+                .regions
+                .iter()
+                .find(|&&(begin, end, _)| begin <= offset && offset < end)
+                .map(|&(_, _, color)| color);
+            if let Some((r, g, b)) = color {
+                let column = (i as u64 % BYTES_PER_LINE) as f64;
+                let y = MARGIN + (i as u64 / BYTES_PER_LINE) as f64 * LINE_HEIGHT;
+                cr.set_source_rgb(r, g, b);
+                cr.rectangle(
+                    hex_x + column * 3.0 * self.char_width,
+                    y,
+                    self.char_width * 3.0,
+                    LINE_HEIGHT,
+                );
+                cr.rectangle(
+                    ascii_x + column * self.char_width,
+                    y,
+                    self.char_width,
+                    LINE_HEIGHT,
+                );
+                cr.fill()?;
+            }
+        }
+
+        // Text.
+        cr.set_source_rgb(0.0, 0.0, 0.0);
+        for (line_index, line) in bytes.chunks(BYTES_PER_LINE as usize).enumerate() {
+            let baseline = MARGIN + (line_index as f64 + 0.8) * LINE_HEIGHT;
+            let offset = start + line_index as u64 * BYTES_PER_LINE;
+            cr.move_to(MARGIN, baseline);
+            cr.show_text(&format!("{:08x}", offset))?;
+            let hex = line
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(" ");
+            cr.move_to(hex_x, baseline);
+            cr.show_text(&hex)?;
+            let ascii: String = line
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            cr.move_to(ascii_x, baseline);
+            cr.show_text(&ascii)?;
+        }
+        Ok(())
+    }
+}
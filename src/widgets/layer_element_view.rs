@@ -0,0 +1,156 @@
+//! Layer element inspector widget.
+
+use glib::Type;
+use gtk::{prelude::*, Label, ListStore, Orientation, ScrolledWindow, TreeView};
+
+use crate::{
+    fbx::{Document, LayerElement, NodeId},
+    widgets::append_text_column,
+};
+
+/// Maximum number of rows shown in the table.
+const MAX_ROWS: usize = 100_000;
+
+/// Layer element inspector widget.
+///
+/// This shows the elements of a `LayerElement*` node resolved for each
+/// polygon vertex.
+#[derive(Debug, Clone)]
+pub struct FbxLayerElementView {
+    widget: gtk::Box,
+    summary: Label,
+    store: ListStore,
+    view: TreeView,
+}
+
+impl FbxLayerElementView {
+    /// Creates a new layer element inspector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the inspector.
+    pub fn clear(&self) {
+        self.summary.set_text("");
+        self.store.clear();
+    }
+
+    /// Shows the layer element of the given node.
+    ///
+    /// Returns `false` and clears the inspector if the node is not a layer
+    /// element of a mesh geometry.
+    pub fn show_node(&self, document: &Document, node_id: NodeId) -> bool {
+        self.clear();
+        let element = match LayerElement::from_node(document, node_id) {
+            Some(v) => v,
+            None => return false,
+        };
+
+        let optional_string = |v: Option<String>| v.unwrap_or_else(|| "(unknown)".to_owned());
+        let mut summary = format!(
+            "layer {}, name {:?}, mapping {}, reference {}\n\
+             `{}`: {} elements of {} components, index: {}",
+            optional_string(element.layer().map(|v| v.to_string())),
+            element.name(),
+            optional_string(element.mapping().map(|v| v.to_string())),
+            optional_string(element.reference().map(|v| v.to_string())),
+            element.data_name(),
+            element.num_data(),
+            element.components(),
+            optional_string(element.num_indices().map(|v| format!("{} elements", v))),
+        );
+        if element.resolved().len() > MAX_ROWS {
+            summary.push_str(&format!(" (first {} rows are shown)", MAX_ROWS));
+        }
+        for issue in element.issues() {
+            summary.push_str("\nwarning: ");
+            summary.push_str(issue);
+        }
+        self.summary.set_text(&summary);
+
+        // Detach the model during bulk insertion, for efficiency.
+        self.view.set_model(None::<&ListStore>);
+        for resolved in element.resolved().iter().take(MAX_ROWS) {
+            let data_index = resolved
+                .data_index
+                .map_or_else(|| "(missing)".to_owned(), |v| v.to_string());
+            let value = match &resolved.value {
+                Some(value) => value
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                None => "(out of range)".to_owned(),
+            };
+            self.store.insert_with_values(
+                None,
+                &[
+                    (0, &(resolved.polygon_vertex as u64)),
+                    (1, &(resolved.polygon as u64)),
+                    (2, &resolved.vertex),
+                    (3, &(resolved.mapping_index as u64)),
+                    (4, &data_index),
+                    (5, &value),
+                    (6, &resolved.value.is_none()),
+                ],
+            );
+        }
+        self.view.set_model(Some(&self.store));
+
+        true
+    }
+
+    /// Returns a reference to the root widget.
+    pub fn widget(&self) -> &gtk::Box {
+        &self.widget
+    }
+}
+
+impl Default for FbxLayerElementView {
+    fn default() -> Self {
+        // polygon vertex, polygon, vertex, mapping index, data index, value, error.
+        let store = ListStore::new(&[
+            Type::U64,
+            Type::U64,
+            Type::I64,
+            Type::U64,
+            Type::STRING,
+            Type::STRING,
+            Type::BOOL,
+        ]);
+        let view = TreeView::with_model(&store);
+        view.set_grid_lines(gtk::TreeViewGridLines::Vertical);
+        for (index, title) in [
+            "polygon vertex",
+            "polygon",
+            "vertex",
+            "mapping index",
+            "data index",
+            "value",
+        ]
+        .iter()
+        .enumerate()
+        {
+            append_text_column(&view, title, index as i32, Some(6));
+        }
+
+        let summary = Label::new(None);
+        summary.set_xalign(0.0);
+        summary.set_selectable(true);
+        summary.set_line_wrap(true);
+
+        let scrolled = ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
+        scrolled.add(&view);
+
+        let widget = gtk::Box::new(Orientation::Vertical, 4);
+        widget.pack_start(&summary, false, false, 0);
+        widget.pack_start(&scrolled, true, true, 0);
+
+        Self {
+            widget,
+            summary,
+            store,
+            view,
+        }
+    }
+}
@@ -0,0 +1,298 @@
+//! Wireframe preview widget.
+
+use std::{cell::RefCell, collections::HashSet, f64::consts::PI, rc::Rc};
+
+use gtk::{cairo, prelude::*, DrawingArea};
+
+use crate::fbx::{Document, Mesh, NodeId};
+
+/// Maximum number of edges to be drawn.
+const MAX_EDGES: usize = 500_000;
+/// Rotation angle in radians per pixel of mouse drag.
+const ROTATION_PER_PIXEL: f64 = 0.01;
+/// Zoom factor per scroll step.
+const ZOOM_PER_STEP: f64 = 1.1;
+
+/// Wireframe preview widget.
+///
+/// This draws vertices and polygon edges of a `Geometry` node with cairo.
+/// Dragging rotates the camera around the mesh, and scrolling zooms.
+#[derive(Debug, Clone)]
+pub struct FbxMeshPreview {
+    widget: DrawingArea,
+    state: Rc<RefCell<PreviewState>>,
+}
+
+impl FbxMeshPreview {
+    /// Creates a new wireframe preview.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the preview.
+    pub fn clear(&self) {
+        self.state.borrow_mut().set_mesh(None);
+        self.widget.queue_draw();
+    }
+
+    /// Shows the mesh of the given node.
+    ///
+    /// Returns `false` and clears the preview if the node is not a mesh
+    /// geometry.
+    pub fn show_node(&self, document: &Document, node_id: NodeId) -> bool {
+        let mesh = Mesh::from_geometry(document, node_id);
+        let is_mesh = mesh.is_some();
+        self.state.borrow_mut().set_mesh(mesh.as_ref());
+        self.widget.queue_draw();
+        is_mesh
+    }
+
+    /// Returns a reference to the `DrawingArea`.
+    pub fn widget(&self) -> &DrawingArea {
+        &self.widget
+    }
+}
+
+impl Default for FbxMeshPreview {
+    fn default() -> Self {
+        let widget = DrawingArea::new();
+        widget.set_size_request(200, 200);
+        widget.add_events(
+            gdk::EventMask::BUTTON_PRESS_MASK
+                | gdk::EventMask::BUTTON1_MOTION_MASK
+                | gdk::EventMask::SCROLL_MASK
+                | gdk::EventMask::SMOOTH_SCROLL_MASK,
+        );
+        let state = Rc::new(RefCell::new(PreviewState::default()));
+
+        {
+            let state = state.clone();
+            widget.connect_draw(move |widget, cr| {
+                let width = f64::from(widget.allocated_width());
+                let height = f64::from(widget.allocated_height());
+                if let Err(e) = state.borrow().draw(cr, width, height) {
+                    println!("Failed to draw the wireframe preview: {}", e);
+                }
+                glib::Propagation::Stop
+            });
+        }
+        {
+            let state = state.clone();
+            widget.connect_button_press_event(move |_, event| {
+                if event.button() == 1 {
+                    let mut state = state.borrow_mut();
+                    state.drag_origin = Some((event.position(), state.yaw, state.pitch));
+                }
+                glib::Propagation::Proceed
+            });
+        }
+        {
+            let state = state.clone();
+            widget.connect_motion_notify_event(move |widget, event| {
+                let mut state = state.borrow_mut();
+                if let Some(((x0, y0), yaw, pitch)) = state.drag_origin {
+                    let (x, y) = event.position();
+                    state.yaw = yaw + (x - x0) * ROTATION_PER_PIXEL;
+                    state.pitch =
+                        (pitch + (y - y0) * ROTATION_PER_PIXEL).clamp(-PI / 2.0, PI / 2.0);
+                    widget.queue_draw();
+                }
+                glib::Propagation::Proceed
+            });
+        }
+        {
+            let state = state.clone();
+            widget.connect_scroll_event(move |widget, event| {
+                let steps = match event.direction() {
+                    gdk::ScrollDirection::Up => -1.0,
+                    gdk::ScrollDirection::Down => 1.0,
+                    gdk::ScrollDirection::Smooth => event.delta().1,
+                    _ => 0.0,
+                };
+                state.borrow_mut().zoom *= ZOOM_PER_STEP.powf(-steps);
+                widget.queue_draw();
+                glib::Propagation::Stop
+            });
+        }
+
+        Self { widget, state }
+    }
+}
+
+/// Mesh and camera state of the preview.
+#[derive(Debug, Clone)]
+struct PreviewState {
+    /// Vertex positions.
+    vertices: Vec<[f64; 3]>,
+    /// Polygon edges as pairs of vertex indices.
+    edges: Vec<(usize, usize)>,
+    /// Whether some edges are omitted.
+    truncated: bool,
+    /// Center of the bounding box.
+    center: [f64; 3],
+    /// Radius of the bounding sphere.
+    radius: f64,
+    /// Rotation around the vertical axis.
+    yaw: f64,
+    /// Rotation around the horizontal axis.
+    pitch: f64,
+    /// Zoom factor.
+    zoom: f64,
+    /// Cursor position and camera angles at the start of the drag.
+    drag_origin: Option<((f64, f64), f64, f64)>,
+}
+
+impl PreviewState {
+    /// Sets the mesh to be drawn, and resets the camera.
+    fn set_mesh(&mut self, mesh: Option<&Mesh>) {
+        *self = Self::default();
+        let mesh = match mesh {
+            Some(v) => v,
+            None => return,
+        };
+        let num_vertices = mesh.vertices().len();
+        self.vertices = mesh.vertices().to_vec();
+
+        let in_range = |v: i64| v >= 0 && (v as u64) < num_vertices as u64;
+        let mut edges = HashSet::new();
+        'polygons: for polygon in mesh.polygons() {
+            let vertices = polygon.vertices();
+            for (i, &from) in vertices.iter().enumerate() {
+                let to = vertices[(i + 1) % vertices.len()];
+                if from == to || !in_range(from) || !in_range(to) {
+                    continue;
+                }
+                if edges.len() >= MAX_EDGES {
+                    self.truncated = true;
+                    break 'polygons;
+                }
+                edges.insert((from.min(to) as usize, from.max(to) as usize));
+            }
+        }
+        self.edges = edges.into_iter().collect();
+
+        let mut min = [f64::INFINITY; 3];
+        let mut max = [f64::NEG_INFINITY; 3];
+        for vertex in self
+            .vertices
+            .iter()
+            .filter(|v| v.iter().all(|c| c.is_finite()))
+        {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(vertex[axis]);
+                max[axis] = max[axis].max(vertex[axis]);
+            }
+        }
+        if min[0] <= max[0] {
+            for axis in 0..3 {
+                self.center[axis] = (min[axis] + max[axis]) / 2.0;
+            }
+            let diagonal = (0..3)
+                .map(|axis| (max[axis] - min[axis]).powi(2))
+                .sum::<f64>()
+                .sqrt();
+            if diagonal > 0.0 {
+                self.radius = diagonal / 2.0;
+            }
+        }
+    }
+
+    /// Rotates the given direction by the camera angles.
+    ///
+    /// Returns the screen coordinates (right, up) and depth.
+    fn rotate(&self, [x, y, z]: [f64; 3]) -> [f64; 3] {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let x1 = x * cos_yaw + z * sin_yaw;
+        let z1 = -x * sin_yaw + z * cos_yaw;
+        let y2 = y * cos_pitch - z1 * sin_pitch;
+        let z2 = y * sin_pitch + z1 * cos_pitch;
+        [x1, y2, z2]
+    }
+
+    /// Draws the wireframe and the axes indicator.
+    fn draw(&self, cr: &cairo::Context, width: f64, height: f64) -> Result<(), cairo::Error> {
+        cr.set_source_rgb(0.15, 0.15, 0.15);
+        cr.paint()?;
+        if self.vertices.is_empty() {
+            return Ok(());
+        }
+
+        // Orthographic projection, fitting the bounding sphere to the view.
+        let scale = width.min(height) / 2.0 * 0.9 / self.radius * self.zoom;
+        let projected: Vec<(f64, f64)> = self
+            .vertices
+            .iter()
+            .map(|v| {
+                let [x, y, _] = self.rotate([
+                    v[0] - self.center[0],
+                    v[1] - self.center[1],
+                    v[2] - self.center[2],
+                ]);
+                (width / 2.0 + x * scale, height / 2.0 - y * scale)
+            })
+            .collect();
+
+        cr.set_line_width(1.0);
+        cr.set_source_rgb(0.8, 0.8, 0.8);
+        // Non-finite positions would break the cairo context, so they are skipped.
+        let is_finite = |&(x, y): &(f64, f64)| x.is_finite() && y.is_finite();
+        for &(from, to) in &self.edges {
+            if !is_finite(&projected[from]) || !is_finite(&projected[to]) {
+                continue;
+            }
+            cr.move_to(projected[from].0, projected[from].1);
+            cr.line_to(projected[to].0, projected[to].1);
+        }
+        cr.stroke()?;
+
+        cr.set_source_rgb(0.9, 0.6, 0.1);
+        for &(x, y) in projected.iter().filter(|p| is_finite(p)) {
+            cr.rectangle(x - 1.5, y - 1.5, 3.0, 3.0);
+        }
+        cr.fill()?;
+
+        // Axes indicator: X in red, Y in green, Z in blue.
+        let origin = (30.0, height - 30.0);
+        let axes = [
+            ([1.0, 0.0, 0.0], (0.9, 0.2, 0.2), "X"),
+            ([0.0, 1.0, 0.0], (0.2, 0.8, 0.2), "Y"),
+            ([0.0, 0.0, 1.0], (0.3, 0.4, 1.0), "Z"),
+        ];
+        cr.set_line_width(2.0);
+        for &(direction, (r, g, b), label) in &axes {
+            let [x, y, _] = self.rotate(direction);
+            let end = (origin.0 + x * 20.0, origin.1 - y * 20.0);
+            cr.set_source_rgb(r, g, b);
+            cr.move_to(origin.0, origin.1);
+            cr.line_to(end.0, end.1);
+            cr.stroke()?;
+            cr.move_to(end.0 + 2.0, end.1);
+            cr.show_text(label)?;
+        }
+
+        if self.truncated {
+            cr.set_source_rgb(0.9, 0.9, 0.9);
+            cr.move_to(8.0, 16.0);
+            cr.show_text(&format!("(first {} edges are shown)", MAX_EDGES))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for PreviewState {
+    fn default() -> Self {
+        Self {
+            vertices: Vec::new(),
+            edges: Vec::new(),
+            truncated: false,
+            center: [0.0; 3],
+            radius: 1.0,
+            yaw: -PI / 4.0,
+            pitch: PI / 6.0,
+            zoom: 1.0,
+            drag_origin: None,
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Mesh inspector widget.
+
+use glib::Type;
+use gtk::{prelude::*, Label, ListStore, Orientation, Paned, ScrolledWindow, TreeView};
+
+use crate::{
+    fbx::{Document, Mesh, NodeId},
+    widgets::append_text_column,
+};
+
+/// Maximum number of rows shown in each table.
+const MAX_ROWS: usize = 100_000;
+
+/// Mesh inspector widget.
+///
+/// This shows vertices and decoded polygons of a `Geometry` node.
+#[derive(Debug, Clone)]
+pub struct FbxMeshView {
+    widget: gtk::Box,
+    summary: Label,
+    vertices_store: ListStore,
+    vertices_view: TreeView,
+    polygons_store: ListStore,
+    polygons_view: TreeView,
+}
+
+impl FbxMeshView {
+    /// Creates a new mesh inspector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears the inspector.
+    pub fn clear(&self) {
+        self.summary.set_text("");
+        self.vertices_store.clear();
+        self.polygons_store.clear();
+    }
+
+    /// Shows the mesh of the given node.
+    ///
+    /// Returns `false` and clears the inspector if the node is not a mesh
+    /// geometry.
+    pub fn show_node(&self, document: &Document, node_id: NodeId) -> bool {
+        self.clear();
+        let mesh = match Mesh::from_geometry(document, node_id) {
+            Some(v) => v,
+            None => return false,
+        };
+
+        let mut summary = format!(
+            "{} vertices, {} polygons, {} triangles, {} polygon vertices",
+            mesh.vertices().len(),
+            mesh.polygons().len(),
+            mesh.num_triangles(),
+            mesh.num_polygon_vertices()
+        );
+        if mesh.vertices().len() > MAX_ROWS || mesh.polygons().len() > MAX_ROWS {
+            summary.push_str(&format!(" (first {} rows are shown)", MAX_ROWS));
+        }
+        for issue in mesh.issues() {
+            summary.push_str("\nwarning: ");
+            summary.push_str(issue);
+        }
+        self.summary.set_text(&summary);
+
+        // Detach the models during bulk insertion, for efficiency.
+        self.vertices_view.set_model(None::<&ListStore>);
+        for (index, [x, y, z]) in mesh.vertices().iter().take(MAX_ROWS).enumerate() {
+            self.vertices_store.insert_with_values(
+                None,
+                &[
+                    (0, &(index as u64)),
+                    (1, &x.to_string()),
+                    (2, &y.to_string()),
+                    (3, &z.to_string()),
+                ],
+            );
+        }
+        self.vertices_view.set_model(Some(&self.vertices_store));
+
+        self.polygons_view.set_model(None::<&ListStore>);
+        for (index, polygon) in mesh.polygons().iter().take(MAX_ROWS).enumerate() {
+            let indices = polygon
+                .vertices()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.polygons_store.insert_with_values(
+                None,
+                &[
+                    (0, &(index as u64)),
+                    (1, &(polygon.first_polygon_vertex() as u64)),
+                    (2, &(polygon.vertices().len() as u64)),
+                    (3, &indices),
+                    (4, &polygon.has_out_of_range_index()),
+                ],
+            );
+        }
+        self.polygons_view.set_model(Some(&self.polygons_store));
+
+        true
+    }
+
+    /// Returns a reference to the root widget.
+    pub fn widget(&self) -> &gtk::Box {
+        &self.widget
+    }
+}
+
+impl Default for FbxMeshView {
+    fn default() -> Self {
+        // index, x, y, z.
+        let vertices_store = ListStore::new(&[Type::U64, Type::STRING, Type::STRING, Type::STRING]);
+        let vertices_view = TreeView::with_model(&vertices_store);
+        vertices_view.set_grid_lines(gtk::TreeViewGridLines::Vertical);
+        for (index, title) in ["vertex", "x", "y", "z"].iter().enumerate() {
+            append_text_column(&vertices_view, title, index as i32, None);
+        }
+
+        // index, first polygon vertex, # of vertices, vertex indices, out of range.
+        let polygons_store =
+            ListStore::new(&[Type::U64, Type::U64, Type::U64, Type::STRING, Type::BOOL]);
+        let polygons_view = TreeView::with_model(&polygons_store);
+        polygons_view.set_grid_lines(gtk::TreeViewGridLines::Vertical);
+        for (index, title) in ["polygon", "first PV", "# of vertices", "vertex indices"]
+            .iter()
+            .enumerate()
+        {
+            append_text_column(&polygons_view, title, index as i32, Some(4));
+        }
+
+        let summary = Label::new(None);
+        summary.set_xalign(0.0);
+        summary.set_selectable(true);
+        summary.set_line_wrap(true);
+
+        let scrolled_vertices = ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
+        scrolled_vertices.add(&vertices_view);
+        let scrolled_polygons = ScrolledWindow::new(gtk::Adjustment::NONE, gtk::Adjustment::NONE);
+        scrolled_polygons.add(&polygons_view);
+        let tables = Paned::new(Orientation::Vertical);
+        tables.pack1(&scrolled_vertices, true, true);
+        tables.pack2(&scrolled_polygons, true, true);
+
+        let widget = gtk::Box::new(Orientation::Vertical, 4);
+        widget.pack_start(&summary, false, false, 0);
+        widget.pack_start(&tables, true, true, 0);
+
+        Self {
+            widget,
+            summary,
+            vertices_store,
+            vertices_view,
+            polygons_store,
+            polygons_view,
+        }
+    }
+}
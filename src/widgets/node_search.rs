@@ -0,0 +1,197 @@
+//! FBX node search bar.
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use gtk::{prelude::*, Button, Label, SearchBar, SearchEntry};
+
+use crate::{
+    fbx::{NodeId, SearchQuery},
+    widgets::{FbxAttributeTable, FbxNodeTree},
+};
+
+/// FBX node search bar.
+#[derive(Debug, Clone)]
+pub struct FbxNodeSearch {
+    widget: SearchBar,
+    entry: SearchEntry,
+    prev_button: Button,
+    next_button: Button,
+    status: Label,
+    /// Matched nodes.
+    matches: Rc<RefCell<Vec<NodeId>>>,
+    /// Index of the current match in `matches`.
+    current: Rc<Cell<Option<usize>>>,
+}
+
+impl FbxNodeSearch {
+    /// Creates a new search bar.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect events.
+    pub fn initialize(&self, node_tree: &FbxNodeTree, node_attrs: &FbxAttributeTable) {
+        {
+            let this = self.clone();
+            let node_tree = node_tree.clone();
+            let node_attrs = node_attrs.clone();
+            self.entry.connect_search_changed(move |_| {
+                this.update(&node_tree, &node_attrs);
+            });
+        }
+        {
+            let this = self.clone();
+            let node_tree = node_tree.clone();
+            self.entry
+                .connect_activate(move |_| this.go(&node_tree, Direction::Next));
+        }
+        {
+            let this = self.clone();
+            let node_tree = node_tree.clone();
+            self.entry
+                .connect_next_match(move |_| this.go(&node_tree, Direction::Next));
+        }
+        {
+            let this = self.clone();
+            let node_tree = node_tree.clone();
+            self.entry
+                .connect_previous_match(move |_| this.go(&node_tree, Direction::Prev));
+        }
+        {
+            let this = self.clone();
+            let node_tree = node_tree.clone();
+            self.next_button
+                .connect_clicked(move |_| this.go(&node_tree, Direction::Next));
+        }
+        {
+            let this = self.clone();
+            let node_tree = node_tree.clone();
+            self.prev_button
+                .connect_clicked(move |_| this.go(&node_tree, Direction::Prev));
+        }
+        {
+            let node_tree = node_tree.clone();
+            let node_attrs = node_attrs.clone();
+            self.widget
+                .connect_search_mode_enabled_notify(move |search_bar| {
+                    // Stop highlighting when the search bar is closed.
+                    if !search_bar.is_search_mode() {
+                        node_attrs.set_highlight(None);
+                        node_tree.refresh_selection();
+                    }
+                });
+        }
+    }
+
+    /// Shows the search bar and focuses the entry.
+    pub fn show(&self) {
+        self.widget.set_search_mode(true);
+        self.entry.grab_focus();
+    }
+
+    /// Searches the current document again, for example after a new document
+    /// is loaded.
+    pub fn update(&self, node_tree: &FbxNodeTree, node_attrs: &FbxAttributeTable) {
+        let query = SearchQuery::new(&self.entry.text());
+        let matches = match &query {
+            Some(query) => query.search(&node_tree.document().borrow()),
+            None => Vec::new(),
+        };
+        node_attrs.set_highlight(query);
+        *self.matches.borrow_mut() = matches;
+        self.current.set(None);
+        if self.matches.borrow().is_empty() {
+            self.update_status();
+            node_tree.refresh_selection();
+        } else {
+            self.go(node_tree, Direction::Next);
+        }
+    }
+
+    /// Selects the next or previous match.
+    fn go(&self, node_tree: &FbxNodeTree, direction: Direction) {
+        let len = self.matches.borrow().len();
+        if len == 0 {
+            return;
+        }
+        let index = match (self.current.get(), direction) {
+            (None, Direction::Next) => 0,
+            (None, Direction::Prev) => len - 1,
+            (Some(i), Direction::Next) => (i + 1) % len,
+            (Some(i), Direction::Prev) => (i + len - 1) % len,
+        };
+        self.current.set(Some(index));
+        self.update_status();
+        let node_id = self.matches.borrow()[index];
+        node_tree.select_node(node_id);
+        // The selection may not change if the node is already selected.
+        node_tree.refresh_selection();
+    }
+
+    /// Updates the status label and buttons.
+    fn update_status(&self) {
+        let len = self.matches.borrow().len();
+        let status = match self.current.get() {
+            _ if self.entry.text().trim().is_empty() => String::new(),
+            _ if len == 0 => "No matches".to_owned(),
+            Some(i) => format!("{} / {}", i + 1, len),
+            None => format!("{} matches", len),
+        };
+        self.status.set_text(&status);
+        self.prev_button.set_sensitive(len > 0);
+        self.next_button.set_sensitive(len > 0);
+    }
+
+    /// Returns a reference to the `SearchBar`.
+    pub fn widget(&self) -> &SearchBar {
+        &self.widget
+    }
+}
+
+impl Default for FbxNodeSearch {
+    fn default() -> Self {
+        let entry = SearchEntry::new();
+        entry.set_placeholder_text(Some("Node name, path (e.g. Objects/Geometry) or value"));
+        entry.set_width_chars(40);
+        let prev_button = Button::from_icon_name(Some("go-up-symbolic"), gtk::IconSize::Button);
+        prev_button.set_tooltip_text(Some("Previous match"));
+        prev_button.set_sensitive(false);
+        let next_button = Button::from_icon_name(Some("go-down-symbolic"), gtk::IconSize::Button);
+        next_button.set_tooltip_text(Some("Next match"));
+        next_button.set_sensitive(false);
+        let status = Label::new(None);
+
+        let content = gtk::Box::new(gtk::Orientation::Horizontal, 4);
+        content.pack_start(&entry, true, true, 0);
+        content.pack_start(&prev_button, false, false, 0);
+        content.pack_start(&next_button, false, false, 0);
+        content.pack_start(&status, false, false, 0);
+
+        let widget = SearchBar::new();
+        widget.add(&content);
+        widget.connect_entry(&entry);
+        widget.set_show_close_button(true);
+
+        Self {
+            widget,
+            entry,
+            prev_button,
+            next_button,
+            status,
+            matches: Rc::new(RefCell::new(Vec::new())),
+            current: Rc::new(Cell::new(None)),
+        }
+    }
+}
+
+/// Direction of match navigation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    /// Next match.
+    Next,
+    /// Previous match.
+    Prev,
+}
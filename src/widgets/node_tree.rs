@@ -1,15 +1,86 @@
 //! FBX node tree widget.
 
+use std::{cell::RefCell, fmt, rc::Rc};
+
 use glib::Type;
-use gtk::{prelude::*, TreeStore, TreeView};
+use gtk::{
+    prelude::*, CellRendererText, TreeModelFilter, TreeModelSort, TreeStore, TreeView,
+    TreeViewColumn,
+};
+
+use crate::{
+    fbx::{Document, Edit, NodeFilter, NodeId},
+    widgets::FbxAttributeTable,
+};
+
+/// Value of the node ID column for rows which are not FBX nodes.
+const PSEUDO_NODE: u64 = u64::MAX;
 
-use crate::FbxAttributeTable;
+/// Maximum number of visible nodes to expand all rows after filtering.
+const MAX_NODES_TO_EXPAND: usize = 10000;
 
 /// FBX node tree widget.
+///
+/// Nodes can be renamed, duplicated, and removed from the context menu if a
+/// callback is connected by [`connect_node_edit`][`Self::connect_node_edit`].
 #[derive(Debug, Clone)]
 pub struct FbxNodeTree {
     store: TreeStore,
+    filter_model: TreeModelFilter,
+    sort_model: TreeModelSort,
     widget: TreeView,
+    /// Column of node names.
+    name_column: TreeViewColumn,
+    /// Cell of node names.
+    name_cell: CellRendererText,
+    document: Rc<RefCell<Document>>,
+    /// Current filter.
+    filter: Rc<RefCell<Option<NodeFilter>>>,
+    /// Visibility of nodes indexed by node ID, or `None` if not filtered.
+    visible: Rc<RefCell<Option<Vec<bool>>>>,
+    /// Callbacks for node selection.
+    selection_handlers: SelectionHandlers,
+    /// Callbacks for node edits.
+    edit_handlers: EditHandlers,
+}
+
+/// Callback for node selection.
+type SelectionHandler = Box<dyn Fn(&Document, Option<NodeId>)>;
+
+/// Callbacks for node selection.
+#[derive(Clone, Default)]
+struct SelectionHandlers(Rc<RefCell<Vec<SelectionHandler>>>);
+
+impl fmt::Debug for SelectionHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SelectionHandlers")
+            .field(&format_args!("[{} handlers]", self.0.borrow().len()))
+            .finish()
+    }
+}
+
+/// Callback for node edits.
+type EditHandler = Box<dyn Fn(Edit)>;
+
+/// Callbacks for node edits.
+#[derive(Clone, Default)]
+struct EditHandlers(Rc<RefCell<Vec<EditHandler>>>);
+
+impl EditHandlers {
+    /// Calls the callbacks.
+    fn emit(&self, edit: &Edit) {
+        for handler in self.0.borrow().iter() {
+            handler(edit.clone());
+        }
+    }
+}
+
+impl fmt::Debug for EditHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EditHandlers")
+            .field(&format_args!("[{} handlers]", self.0.borrow().len()))
+            .finish()
+    }
 }
 
 impl FbxNodeTree {
@@ -21,57 +92,340 @@ impl FbxNodeTree {
     /// Connect events.
     pub fn initialize(&self, node_attrs: &FbxAttributeTable) {
         let node_attrs = node_attrs.clone();
+        self.connect_node_selected(move |document, node_id| match node_id {
+            Some(node_id) => node_attrs.show_attrs(document, node_id),
+            None => node_attrs.clear(),
+        });
+
+        let document = self.document.clone();
+        let handlers = self.selection_handlers.clone();
         self.widget.selection().connect_changed(move |selection| {
-            let (paths, model) = selection.selected_rows();
-            let descendant_path = match paths.last() {
-                Some(path) => path,
-                None => {
-                    println!("selection has changed but paths is empty");
-                    return;
-                }
-            };
-            let tree_iter = match model.iter(descendant_path) {
-                Some(iter) => iter,
-                None => {
+            let node_id = selected_node(selection);
+            let document = document.borrow();
+            let node_id = node_id.filter(|&node_id| {
+                let exists = document.get(node_id).is_some();
+                if !exists {
                     println!(
-                        "selection has changed but tree_iter is invalid for path {:?}",
-                        descendant_path
+                        "selected node {} does not exist in the document",
+                        node_id.index()
                     );
-                    return;
                 }
-            };
-            let num_attrs = model
-                .value(&tree_iter, 1)
-                .get::<u64>()
-                .expect("column[1] of `FbxAttributeTable` is not u64");
-            let attrs_index = model
-                .value(&tree_iter, 2)
-                .get::<u64>()
-                .expect("column[2] of `FbxAttributeTable` is not u64");
-            node_attrs.show_attrs(attrs_index, num_attrs);
+                exists
+            });
+            for handler in handlers.0.borrow().iter() {
+                handler(&document, node_id);
+            }
         });
+
+        {
+            let name_cell = self.name_cell.clone();
+            let sort_model = self.sort_model.clone();
+            let handlers = self.edit_handlers.clone();
+            self.name_cell.connect_edited(move |_, path, text| {
+                name_cell.set_editable(false);
+                let node_id = sort_model
+                    .iter(&path)
+                    .and_then(|iter| row_node_id(sort_model.upcast_ref(), &iter));
+                if let Some(node) = node_id {
+                    handlers.emit(&Edit::Rename {
+                        node,
+                        name: text.to_owned(),
+                    });
+                }
+            });
+        }
+        self.name_cell
+            .connect_editing_canceled(|cell| cell.set_editable(false));
+        {
+            let tree = self.clone();
+            self.widget.connect_button_press_event(move |view, event| {
+                if event.event_type() != gdk::EventType::ButtonPress || event.button() != 3 {
+                    return glib::Propagation::Proceed;
+                }
+                if tree.edit_handlers.0.borrow().is_empty() {
+                    return glib::Propagation::Proceed;
+                }
+                let (x, y) = event.position();
+                let path = match view.path_at_pos(x as i32, y as i32) {
+                    Some((Some(path), _, _, _)) => path,
+                    _ => return glib::Propagation::Proceed,
+                };
+                view.set_cursor(&path, None::<&TreeViewColumn>, false);
+                if tree.selected_node().is_some() {
+                    tree.popup_context_menu(event);
+                }
+                glib::Propagation::Stop
+            });
+        }
+    }
+
+    /// Adds a callback called when the user requests an edit of a node.
+    ///
+    /// The edit is not applied by the tree itself.
+    pub fn connect_node_edit<F>(&self, f: F)
+    where
+        F: Fn(Edit) + 'static,
+    {
+        self.edit_handlers.0.borrow_mut().push(Box::new(f));
+    }
+
+    /// Shows the context menu for the selected node.
+    fn popup_context_menu(&self, event: &gdk::EventButton) {
+        let menu = gtk::Menu::new();
+        {
+            let item = gtk::MenuItem::with_mnemonic("_Rename");
+            let tree = self.clone();
+            item.connect_activate(move |_| tree.start_rename());
+            menu.append(&item);
+        }
+        {
+            let item = gtk::MenuItem::with_mnemonic("D_uplicate");
+            let tree = self.clone();
+            item.connect_activate(move |_| {
+                if let Some(node) = tree.selected_node() {
+                    tree.edit_handlers.emit(&Edit::Duplicate { node });
+                }
+            });
+            menu.append(&item);
+        }
+        {
+            let item = gtk::MenuItem::with_mnemonic("_Delete");
+            let tree = self.clone();
+            item.connect_activate(move |_| {
+                if let Some(node) = tree.selected_node() {
+                    tree.edit_handlers.emit(&Edit::Remove { node });
+                }
+            });
+            menu.append(&item);
+        }
+        menu.show_all();
+        menu.popup_at_pointer(Some(event));
+    }
+
+    /// Starts editing the name of the selected node.
+    pub fn start_rename(&self) {
+        if self.selected_node().is_none() {
+            return;
+        }
+        let (paths, _) = self.widget.selection().selected_rows();
+        if let Some(path) = paths.last() {
+            self.name_cell.set_editable(true);
+            self.widget.set_cursor(path, Some(&self.name_column), true);
+        }
+    }
+
+    /// Returns the selected node, if available.
+    pub fn selected_node(&self) -> Option<NodeId> {
+        selected_node(&self.widget.selection())
+    }
+
+    /// Adds a callback called when the selected node changes.
+    ///
+    /// The callback receives `None` if no nodes are selected or a pseudo row
+    /// (such as "(FBX header)") is selected.
+    pub fn connect_node_selected<F>(&self, f: F)
+    where
+        F: Fn(&Document, Option<NodeId>) + 'static,
+    {
+        self.selection_handlers.0.borrow_mut().push(Box::new(f));
     }
 
     /// Clears internal store.
     pub fn clear(&self) {
         self.store.clear();
+        *self.document.borrow_mut() = Document::default();
+        self.update_visibility();
+    }
+
+    /// Sets the filter, and shows only matching nodes, their ancestors and
+    /// their descendants.
+    ///
+    /// Pseudo rows such as "(FBX header)" are hidden while filtering.
+    pub fn set_filter(&self, filter: Option<NodeFilter>) {
+        *self.filter.borrow_mut() = filter;
+        self.update_visibility();
+        self.filter_model.refilter();
+
+        let num_visible = self
+            .visible
+            .borrow()
+            .as_ref()
+            .map(|visible| visible.iter().filter(|&&v| v).count());
+        if let Some(num_visible) = num_visible {
+            if num_visible <= MAX_NODES_TO_EXPAND {
+                self.widget.expand_all();
+            }
+        }
+    }
+
+    /// Recalculates the visibility of nodes for the current filter and
+    /// document.
+    fn update_visibility(&self) {
+        let visible = self
+            .filter
+            .borrow()
+            .as_ref()
+            .map(|filter| filter.visible_nodes(&self.document.borrow()));
+        *self.visible.borrow_mut() = visible;
+    }
+
+    /// Sets the document to be shown.
+    pub fn set_document(&self, document: Document) {
+        self.clear();
+
+        // Descendant counts and record sizes excluding the child nodes,
+        // accumulated from the last node (i.e. children before parents).
+        let mut num_descendants = vec![0_u64; document.len()];
+        let mut record_sizes: Vec<u64> = document
+            .node_ids()
+            .map(|id| subtree_size(&document, id))
+            .collect();
+        for node_id in document.node_ids().rev() {
+            if let Some(parent) = document.node(node_id).parent() {
+                num_descendants[parent.index()] += num_descendants[node_id.index()] + 1;
+                record_sizes[parent.index()] =
+                    record_sizes[parent.index()].saturating_sub(subtree_size(&document, node_id));
+            }
+        }
+
+        // Detach the model during bulk insertion, for efficiency.
+        self.widget.set_model(None::<&TreeModelSort>);
+        let header_size = document
+            .toplevel_nodes()
+            .iter()
+            .map(|&id| document.node(id))
+            .find(|node| node.has_offsets())
+            .map_or(0, |node| node.start_offset());
+        self.append(
+            None,
+            "(FBX header)",
+            None,
+            PSEUDO_NODE,
+            [0, header_size, header_size, 0],
+        );
+        let mut tree_iters: Vec<gtk::TreeIter> = Vec::with_capacity(document.len());
+        for node_id in document.node_ids() {
+            let node = document.node(node_id);
+            let parent = node.parent().map(|parent| &tree_iters[parent.index()]);
+            let tree_iter = self.append(
+                parent,
+                node.name(),
+                node.attributes().len() as u64,
+                node_id.index() as u64,
+                [
+                    node.start_offset(),
+                    record_sizes[node_id.index()],
+                    subtree_size(&document, node_id),
+                    num_descendants[node_id.index()],
+                ],
+            );
+            tree_iters.push(tree_iter);
+        }
+        if document.footer().is_some() {
+            let footer_offset = document
+                .toplevel_nodes()
+                .iter()
+                .rev()
+                .map(|&id| document.node(id))
+                .find(|node| node.has_offsets())
+                .map_or(header_size, |node| node.end_offset());
+            self.append(
+                None,
+                "(FBX footer)",
+                None,
+                PSEUDO_NODE,
+                [footer_offset, 0, 0, 0],
+            );
+        }
+        *self.document.borrow_mut() = document;
+        self.update_visibility();
+        self.filter_model.refilter();
+        self.widget.set_model(Some(&self.sort_model));
+    }
+
+    /// Rebuilds the tree for the current document.
+    ///
+    /// This should be called when the structure or names of the nodes are
+    /// changed.
+    /// The selection is cleared.
+    pub fn reload(&self) {
+        let document = std::mem::take(&mut *self.document.borrow_mut());
+        self.set_document(document);
+    }
+
+    /// Selects the given node, expanding its ancestors and scrolling to it.
+    pub fn select_node(&self, node_id: NodeId) {
+        let path = {
+            let document = self.document.borrow();
+            if document.get(node_id).is_none() {
+                println!("node {} to be selected does not exist", node_id.index());
+                return;
+            }
+            let mut indices: Vec<i32> = document
+                .index_path(node_id)
+                .into_iter()
+                .map(|index| index as i32)
+                .collect();
+            // Skip the "(FBX header)" row.
+            indices[0] += 1;
+            gtk::TreePath::from_indicesv(&indices)
+        };
+        let path = match self
+            .filter_model
+            .convert_child_path_to_path(&path)
+            .and_then(|path| self.sort_model.convert_child_path_to_path(&path))
+        {
+            Some(path) => path,
+            None => {
+                println!("node {} to be selected is filtered out", node_id.index());
+                return;
+            }
+        };
+        self.widget.expand_to_path(&path);
+        self.widget
+            .set_cursor(&path, None::<&gtk::TreeViewColumn>, false);
+        self.widget
+            .scroll_to_cell(Some(&path), None::<&gtk::TreeViewColumn>, true, 0.5, 0.0);
+    }
+
+    /// Notifies the current selection again, so that widgets showing the
+    /// selected node are updated.
+    pub fn refresh_selection(&self) {
+        let selection = self.widget.selection();
+        if selection.count_selected_rows() > 0 {
+            selection.emit_by_name::<()>("changed", &[]);
+        }
+    }
+
+    /// Returns a reference to the shared document.
+    pub fn document(&self) -> &Rc<RefCell<Document>> {
+        &self.document
     }
 
     /// Appends the given node.
-    pub fn append<N: Into<Option<u64>>>(
+    ///
+    /// `sizes` is the byte offset, record size, subtree size, and the number
+    /// of descendants.
+    fn append<N: Into<Option<u64>>>(
         &self,
         parent: Option<&gtk::TreeIter>,
         name: &str,
         num_attrs: N,
-        attr_index: u64,
+        node_id: u64,
+        sizes: [u64; 4],
     ) -> gtk::TreeIter {
+        let [offset, record_size, subtree_size, num_descendants] = sizes;
         self.store.insert_with_values(
             parent,
             None,
             &[
                 (0, &name),
                 (1, num_attrs.into().as_ref().unwrap_or(&0)),
-                (2, &attr_index),
+                (2, &node_id),
+                (3, &offset),
+                (4, &record_size),
+                (5, &subtree_size),
+                (6, &num_descendants),
             ],
         )
     }
@@ -84,23 +438,51 @@ impl FbxNodeTree {
 
 impl Default for FbxNodeTree {
     fn default() -> Self {
-        use gtk::{CellRendererText, TreeViewColumn};
-
-        // node name, # of attributes, index of attribute.
-        let column_types = &[Type::STRING, Type::U64, Type::U64];
+        // node name, # of attributes, node ID, offset, record size, subtree
+        // size, # of descendants.
+        let column_types = &[
+            Type::STRING,
+            Type::U64,
+            Type::U64,
+            Type::U64,
+            Type::U64,
+            Type::U64,
+            Type::U64,
+        ];
         let store = TreeStore::new(column_types);
-        let widget = TreeView::with_model(&store);
+        let filter_model = TreeModelFilter::new(&store, None);
+        let visible: Rc<RefCell<Option<Vec<bool>>>> = Rc::new(RefCell::new(None));
+        {
+            let visible = visible.clone();
+            filter_model.set_visible_func(move |model, iter| {
+                let visible = visible.borrow();
+                let visible = match &*visible {
+                    Some(v) => v,
+                    None => return true,
+                };
+                let node_id = model
+                    .value(iter, 2)
+                    .get::<u64>()
+                    .expect("column[2] of `FbxNodeTree` is not u64");
+                // Rows being inserted may be out of range of `visible`.
+                node_id != PSEUDO_NODE && visible.get(node_id as usize).cloned().unwrap_or(false)
+            });
+        }
+        let sort_model = TreeModelSort::new(&filter_model);
+        let widget = TreeView::with_model(&sort_model);
         widget.set_grid_lines(gtk::TreeViewGridLines::Vertical);
         widget.set_enable_tree_lines(true);
         widget.set_headers_visible(true);
+        let name_column = TreeViewColumn::new();
+        let name_cell = CellRendererText::new();
         {
-            let column = TreeViewColumn::new();
-            let cell = CellRendererText::new();
-            TreeViewColumnExt::pack_start(&column, &cell, true);
+            let column = &name_column;
+            let cell = &name_cell;
+            TreeViewColumnExt::pack_start(column, cell, true);
             column.set_title("node name");
-            TreeViewColumnExt::add_attribute(&column, &cell, "text", 0);
+            TreeViewColumnExt::add_attribute(column, cell, "text", 0);
             column.set_resizable(true);
-            widget.append_column(&column);
+            widget.append_column(column);
         }
         {
             let column = TreeViewColumn::new();
@@ -111,7 +493,72 @@ impl Default for FbxNodeTree {
             column.set_resizable(true);
             widget.append_column(&column);
         }
+        for &(title, column_index) in &[
+            ("offset", 3),
+            ("record size", 4),
+            ("subtree size", 5),
+            ("# of descendants", 6),
+        ] {
+            let column = TreeViewColumn::new();
+            let cell = CellRendererText::new();
+            cell.set_xalign(1.0);
+            TreeViewColumnExt::pack_start(&column, &cell, true);
+            column.set_title(title);
+            TreeViewColumnExt::add_attribute(&column, &cell, "text", column_index);
+            column.set_clickable(true);
+            column.set_resizable(true);
+            column.set_sort_column_id(column_index);
+            widget.append_column(&column);
+        }
+
+        Self {
+            store,
+            filter_model,
+            sort_model,
+            widget,
+            name_column,
+            name_cell,
+            document: Rc::new(RefCell::new(Document::default())),
+            filter: Rc::new(RefCell::new(None)),
+            visible,
+            selection_handlers: SelectionHandlers::default(),
+            edit_handlers: EditHandlers::default(),
+        }
+    }
+}
+
+/// Returns the byte size of the node including its descendants.
+fn subtree_size(document: &Document, node_id: NodeId) -> u64 {
+    let node = document.node(node_id);
+    node.end_offset().saturating_sub(node.start_offset())
+}
+
+/// Returns the selected node, if available.
+fn selected_node(selection: &gtk::TreeSelection) -> Option<NodeId> {
+    let (paths, model) = selection.selected_rows();
+    let descendant_path = paths.last()?;
+    let tree_iter = match model.iter(descendant_path) {
+        Some(iter) => iter,
+        None => {
+            println!(
+                "selection has changed but tree_iter is invalid for path {:?}",
+                descendant_path
+            );
+            return None;
+        }
+    };
+    row_node_id(&model, &tree_iter)
+}
 
-        Self { store, widget }
+/// Returns the node of the row, or `None` for pseudo rows.
+fn row_node_id(model: &gtk::TreeModel, tree_iter: &gtk::TreeIter) -> Option<NodeId> {
+    let node_id = model
+        .value(tree_iter, 2)
+        .get::<u64>()
+        .expect("column[2] of `FbxNodeTree` is not u64");
+    if node_id == PSEUDO_NODE {
+        None
+    } else {
+        Some(NodeId::new(node_id as usize))
     }
 }
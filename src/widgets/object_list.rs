@@ -0,0 +1,231 @@
+//! FBX objects list widget.
+
+use glib::Type;
+use gtk::{prelude::*, TreeStore, TreeView};
+
+use crate::{
+    fbx::{Connection, Document, NodeId, ObjectIndex},
+    widgets::FbxNodeTree,
+};
+
+/// Value of the node ID column for rows which are not FBX nodes.
+const PSEUDO_NODE: u64 = u64::MAX;
+
+/// FBX objects list widget.
+///
+/// Each object has "parents" and "children" rows listing the connected
+/// objects.
+#[derive(Debug, Clone)]
+pub struct FbxObjectList {
+    store: TreeStore,
+    widget: TreeView,
+}
+
+impl FbxObjectList {
+    /// Creates a new objects store and widget.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect events.
+    ///
+    /// Selecting a row selects the corresponding node in the node tree, and
+    /// activating (double-clicking) a row also switches the notebook to the
+    /// page containing the node tree.
+    pub fn initialize(
+        &self,
+        node_tree: &FbxNodeTree,
+        notebook: &gtk::Notebook,
+        node_tree_page: &gtk::Widget,
+    ) {
+        {
+            let node_tree = node_tree.clone();
+            self.widget.selection().connect_changed(move |selection| {
+                if let Some((model, tree_iter)) = selection.selected() {
+                    if let Some(node_id) = row_node_id(&model, &tree_iter) {
+                        node_tree.select_node(node_id);
+                    }
+                }
+            });
+        }
+        {
+            let node_tree = node_tree.clone();
+            let notebook = notebook.clone();
+            let node_tree_page = node_tree_page.clone();
+            self.widget
+                .connect_row_activated(move |widget, path, _column| {
+                    let model = match widget.model() {
+                        Some(v) => v,
+                        None => return,
+                    };
+                    let node_id = model
+                        .iter(path)
+                        .and_then(|tree_iter| row_node_id(&model, &tree_iter));
+                    if let Some(node_id) = node_id {
+                        if let Some(page) = notebook.page_num(&node_tree_page) {
+                            notebook.set_current_page(Some(page));
+                        }
+                        node_tree.select_node(node_id);
+                    }
+                });
+        }
+    }
+
+    /// Clears internal store.
+    pub fn clear(&self) {
+        self.store.clear();
+    }
+
+    /// Shows the objects of the given document.
+    pub fn set_document(&self, document: &Document) {
+        self.clear();
+        let index = ObjectIndex::new(document);
+
+        // Detach the model during bulk insertion, for efficiency.
+        self.widget.set_model(None::<&TreeStore>);
+        for object in index.objects() {
+            let object_iter = self.append(
+                None,
+                &object.id().to_string(),
+                object.class(),
+                object.name(),
+                object.subclass(),
+                object.node().index() as u64,
+            );
+            let parents: Vec<_> = index.parents(object).collect();
+            self.append_connections(&object_iter, &index, "parents", &parents, true);
+            let children: Vec<_> = index.children(object).collect();
+            self.append_connections(&object_iter, &index, "children", &children, false);
+        }
+        self.widget.set_model(Some(&self.store));
+    }
+
+    /// Appends a group of connected objects.
+    fn append_connections(
+        &self,
+        parent: &gtk::TreeIter,
+        index: &ObjectIndex,
+        label: &str,
+        connections: &[&Connection],
+        to_parents: bool,
+    ) {
+        if connections.is_empty() {
+            return;
+        }
+        let group = self.append(
+            Some(parent),
+            &format!("{} ({})", label, connections.len()),
+            "",
+            "",
+            "",
+            PSEUDO_NODE,
+        );
+        for conn in connections {
+            let (id, property) = if to_parents {
+                (conn.destination(), conn.destination_property())
+            } else {
+                (conn.source(), conn.source_property())
+            };
+            let relation = match property {
+                Some(property) => format!("{}: {:?}", conn.kind(), property),
+                None => conn.kind().to_owned(),
+            };
+            match index.object(id) {
+                Some(object) => self.append(
+                    Some(&group),
+                    &id.to_string(),
+                    object.class(),
+                    object.name(),
+                    &relation,
+                    object.node().index() as u64,
+                ),
+                None => {
+                    let name = if id == 0 { "(root)" } else { "(missing)" };
+                    self.append(
+                        Some(&group),
+                        &id.to_string(),
+                        "",
+                        name,
+                        &relation,
+                        PSEUDO_NODE,
+                    )
+                }
+            };
+        }
+    }
+
+    /// Appends a row.
+    fn append(
+        &self,
+        parent: Option<&gtk::TreeIter>,
+        id: &str,
+        class: &str,
+        name: &str,
+        extra: &str,
+        node_id: u64,
+    ) -> gtk::TreeIter {
+        self.store.insert_with_values(
+            parent,
+            None,
+            &[
+                (0, &id),
+                (1, &class),
+                (2, &name),
+                (3, &extra),
+                (4, &node_id),
+            ],
+        )
+    }
+
+    /// Returns a reference to the `TreeView`.
+    pub fn widget(&self) -> &TreeView {
+        &self.widget
+    }
+}
+
+impl Default for FbxObjectList {
+    fn default() -> Self {
+        use gtk::{CellRendererText, TreeViewColumn};
+
+        // object ID, class, name, subclass or connection type, node ID.
+        let column_types = &[
+            Type::STRING,
+            Type::STRING,
+            Type::STRING,
+            Type::STRING,
+            Type::U64,
+        ];
+        let store = TreeStore::new(column_types);
+        let widget = TreeView::with_model(&store);
+        widget.set_grid_lines(gtk::TreeViewGridLines::Vertical);
+        widget.set_enable_tree_lines(true);
+        widget.set_headers_visible(true);
+        for (index, title) in ["ID", "class", "name", "subclass / connection"]
+            .iter()
+            .enumerate()
+        {
+            let column = TreeViewColumn::new();
+            let cell = CellRendererText::new();
+            TreeViewColumnExt::pack_start(&column, &cell, true);
+            column.set_title(title);
+            TreeViewColumnExt::add_attribute(&column, &cell, "text", index as i32);
+            column.set_resizable(true);
+            widget.append_column(&column);
+        }
+
+        Self { store, widget }
+    }
+}
+
+/// Returns the node ID of the row, if the row corresponds to a node.
+fn row_node_id(model: &gtk::TreeModel, tree_iter: &gtk::TreeIter) -> Option<NodeId> {
+    let node_id = model
+        .value(tree_iter, 4)
+        .get::<u64>()
+        .expect("column[4] of `FbxObjectList` is not u64");
+    if node_id == PSEUDO_NODE {
+        None
+    } else {
+        Some(NodeId::new(node_id as usize))
+    }
+}
@@ -0,0 +1,127 @@
+//! Loading progress.
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use gtk::{prelude::*, Button, Orientation, ProgressBar};
+
+/// Loading progress widget.
+///
+/// This consists of a progress bar and a cancel button.
+#[derive(Debug, Clone)]
+pub struct LoadProgress {
+    widget: gtk::Box,
+    bar: ProgressBar,
+    cancel_button: Button,
+    /// Total length of the file being loaded.
+    total: Rc<Cell<u64>>,
+    /// Cancellation flag of the current loading.
+    cancel: Rc<RefCell<Option<Arc<AtomicBool>>>>,
+}
+
+impl LoadProgress {
+    /// Creates a new loading progress widget.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect events.
+    pub fn initialize(&self) {
+        let this = self.clone();
+        self.cancel_button.connect_clicked(move |_| {
+            this.cancel();
+        });
+    }
+
+    /// Starts new loading, and returns the cancellation flag for it.
+    ///
+    /// The previous loading (if exists) is cancelled.
+    pub fn start(&self, total: u64) -> Arc<AtomicBool> {
+        self.cancel();
+        self.cancel.borrow_mut().take();
+        let cancel = Arc::new(AtomicBool::new(false));
+        *self.cancel.borrow_mut() = Some(cancel.clone());
+        self.total.set(total);
+        self.set_position(0);
+        self.cancel_button.set_sensitive(true);
+        cancel
+    }
+
+    /// Updates the current byte position of the loading.
+    pub fn set_position(&self, position: u64) {
+        let total = self.total.get();
+        let fraction = if total == 0 {
+            0.0
+        } else {
+            (position as f64 / total as f64).min(1.0)
+        };
+        self.bar.set_fraction(fraction);
+        self.bar.set_text(Some(&format!(
+            "{:.1} / {:.1} MiB",
+            position as f64 / MIB,
+            total as f64 / MIB
+        )));
+    }
+
+    /// Cancels the current loading.
+    pub fn cancel(&self) {
+        if let Some(cancel) = &*self.cancel.borrow() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        self.cancel_button.set_sensitive(false);
+    }
+
+    /// Returns whether the loading with the given cancellation flag is the
+    /// current one.
+    pub fn is_current(&self, cancel: &Arc<AtomicBool>) -> bool {
+        self.cancel
+            .borrow()
+            .as_ref()
+            .map_or(false, |v| Arc::ptr_eq(v, cancel))
+    }
+
+    /// Marks the loading with the given cancellation flag as finished.
+    ///
+    /// Does nothing if another loading has been started since then.
+    pub fn finish(&self, cancel: &Arc<AtomicBool>) {
+        if self.is_current(cancel) {
+            self.cancel.borrow_mut().take();
+            self.cancel_button.set_sensitive(false);
+        }
+    }
+
+    /// Returns a reference to the container widget.
+    pub fn widget(&self) -> &gtk::Box {
+        &self.widget
+    }
+}
+
+impl Default for LoadProgress {
+    fn default() -> Self {
+        let widget = gtk::Box::new(Orientation::Horizontal, 4);
+        let bar = ProgressBar::new();
+        bar.set_show_text(true);
+        bar.set_valign(gtk::Align::Center);
+        widget.pack_start(&bar, true, true, 0);
+        let cancel_button = Button::with_label("Cancel");
+        cancel_button.set_sensitive(false);
+        widget.pack_start(&cancel_button, false, false, 0);
+
+        Self {
+            widget,
+            bar,
+            cancel_button,
+            total: Rc::new(Cell::new(0)),
+            cancel: Rc::new(RefCell::new(None)),
+        }
+    }
+}
+
+/// Bytes in a mebibyte.
+const MIB: f64 = 1024.0 * 1024.0;
@@ -0,0 +1,120 @@
+//! FBX properties table.
+
+use std::collections::HashMap;
+
+use glib::{translate::IntoGlib, Type};
+use gtk::{prelude::*, ListStore, TreeView};
+
+use crate::fbx::{properties, template_properties, Document, NodeId};
+
+/// FBX properties table.
+///
+/// This shows the properties in a `Properties70` node, with the defaults in
+/// the corresponding property template.
+#[derive(Debug, Clone)]
+pub struct FbxPropertyTable {
+    store: ListStore,
+    widget: TreeView,
+}
+
+impl FbxPropertyTable {
+    /// Creates a new properties store and widget.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clears internal store.
+    pub fn clear(&self) {
+        self.store.clear();
+    }
+
+    /// Shows the properties of the given node.
+    ///
+    /// Returns `false` and clears the table if the node is not a
+    /// `Properties70` node.
+    pub fn show_node(&self, document: &Document, node_id: NodeId) -> bool {
+        self.store.clear();
+        if document.node(node_id).name() != "Properties70" {
+            return false;
+        }
+
+        let template = template_properties(document, node_id);
+        let defaults: HashMap<&str, _> = template
+            .map(|template| {
+                properties(document, template)
+                    .into_iter()
+                    .map(|prop| (prop.name(), prop))
+                    .collect()
+            })
+            .unwrap_or_default();
+        for prop in properties(document, node_id) {
+            let flags = match prop.flags_description() {
+                description if description.is_empty() => String::new(),
+                description => format!("{} ({})", prop.flags(), description),
+            };
+            let (default, overridden) = match defaults.get(prop.name()) {
+                Some(default) => (default.values_string(), default.values() != prop.values()),
+                None if template.is_some() => ("(not in template)".to_owned(), false),
+                None => (String::new(), false),
+            };
+            self.store.insert_with_values(
+                None,
+                &[
+                    (0, &prop.name()),
+                    (1, &prop.type_name()),
+                    (2, &prop.label()),
+                    (3, &flags),
+                    (4, &prop.values_string()),
+                    (5, &default),
+                    (6, &overridden),
+                ],
+            );
+        }
+        true
+    }
+
+    /// Returns a reference to the `TreeView`.
+    pub fn widget(&self) -> &TreeView {
+        &self.widget
+    }
+}
+
+impl Default for FbxPropertyTable {
+    fn default() -> Self {
+        use gtk::{CellRendererText, TreeViewColumn};
+
+        // name, type, subtype, flags, value, default, overridden.
+        let column_types = &[
+            Type::STRING,
+            Type::STRING,
+            Type::STRING,
+            Type::STRING,
+            Type::STRING,
+            Type::STRING,
+            Type::BOOL,
+        ];
+        let store = ListStore::new(column_types);
+        let widget = TreeView::with_model(&store);
+        widget.set_grid_lines(gtk::TreeViewGridLines::Vertical);
+        widget.set_headers_visible(true);
+        for (index, title) in ["name", "type", "subtype", "flags", "value", "default"]
+            .iter()
+            .enumerate()
+        {
+            let column = TreeViewColumn::new();
+            let cell = CellRendererText::new();
+            TreeViewColumnExt::pack_start(&column, &cell, true);
+            // Overridden properties are shown in bold.
+            cell.set_weight(gtk::pango::Weight::Bold.into_glib());
+            TreeViewColumnExt::add_attribute(&column, &cell, "weight-set", 6);
+            column.set_title(title);
+            TreeViewColumnExt::add_attribute(&column, &cell, "text", index as i32);
+            column.set_clickable(true);
+            column.set_resizable(true);
+            column.set_sort_column_id(index as i32);
+            widget.append_column(&column);
+        }
+
+        Self { store, widget }
+    }
+}
@@ -0,0 +1,82 @@
+//! Tests of the command line interface.
+
+use std::{path::PathBuf, process::Command};
+
+use fbx_tree_view::fbx::{save_binary_file, Attribute, Document, WriteOptions};
+
+/// Writes a small FBX binary file with the given value, and returns the path.
+fn write_sample(name: &str, value: f64) -> PathBuf {
+    let mut doc = Document::default();
+    let objects = doc.push_node(None, "Objects".to_owned(), vec![], 0);
+    doc.push_node(
+        Some(objects),
+        "Model".to_owned(),
+        vec![
+            Attribute::SingleI64(1),
+            Attribute::SingleF64(value),
+            Attribute::SingleF32(0.1),
+        ],
+        0,
+    );
+    let path = std::env::temp_dir().join(format!(
+        "fbx-tree-view-cli-{}-{}.fbx",
+        std::process::id(),
+        name
+    ));
+    save_binary_file(&doc, &path, &WriteOptions::default()).expect("failed to write sample");
+    path
+}
+
+/// Runs the command and returns the exit status.
+fn run(args: &[&str]) -> Option<i32> {
+    Command::new(env!("CARGO_BIN_EXE_fbx-tree-view"))
+        .args(args)
+        .output()
+        .expect("failed to run the command")
+        .status
+        .code()
+}
+
+#[test]
+fn diff_exit_status() {
+    let a = write_sample("a", 1.0);
+    let b = write_sample("b", 1.5);
+    let missing = std::env::temp_dir().join("fbx-tree-view-cli-missing.fbx");
+    let (a_str, b_str) = (a.to_str().unwrap(), b.to_str().unwrap());
+
+    assert_eq!(run(&["diff", a_str, a_str]), Some(0));
+    assert_eq!(run(&["diff", a_str, b_str]), Some(1));
+    assert_eq!(
+        run(&["diff", "--float-tolerance", "0.5", a_str, b_str]),
+        Some(0)
+    );
+    assert_eq!(run(&["diff", a_str, missing.to_str().unwrap()]), Some(2));
+    assert_eq!(
+        run(&["diff", "--float-tolerance", "-1", a_str, b_str]),
+        Some(2)
+    );
+
+    let _ = std::fs::remove_file(&a);
+    let _ = std::fs::remove_file(&b);
+}
+
+#[test]
+fn diff_ascii_export() {
+    let binary = write_sample("export", 1.0);
+    let ascii = binary.with_extension("ascii.fbx");
+    let (binary_str, ascii_str) = (binary.to_str().unwrap(), ascii.to_str().unwrap());
+
+    assert_eq!(
+        run(&["convert", "--format", "ascii", binary_str, ascii_str]),
+        Some(0)
+    );
+    // `f32` is loaded back as `f64`.
+    assert_eq!(run(&["diff", binary_str, ascii_str]), Some(1));
+    assert_eq!(
+        run(&["diff", "--ignore-numeric-types", binary_str, ascii_str]),
+        Some(0)
+    );
+
+    let _ = std::fs::remove_file(&binary);
+    let _ = std::fs::remove_file(&ascii);
+}